@@ -0,0 +1,4 @@
+fn main() {
+    serde_xdr_build::compile("proto/sample.x").unwrap();
+    serde_xdr_build::compile("proto/vxi11.x").unwrap();
+}
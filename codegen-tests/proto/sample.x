@@ -0,0 +1,90 @@
+/* Every construct the code generator handles */
+
+const MAXNAME = 0x40;
+const MINTEMP = -40;
+const ID_LEN = 6;
+
+typedef string name_t<MAXNAME>;
+typedef int readings_t<4>;
+
+enum color {
+    RED = 1,
+    GREEN,
+    BLUE = 0x10
+};
+
+struct node {
+    name_t name;
+    unsigned hyper serial;
+    node *next;
+};
+
+struct shades {
+    color shade;
+    shades *next;
+};
+
+typedef shades *shade_list;
+
+struct sample {
+    opaque id[ID_LEN];
+    color shade;
+    color palette[3];
+    readings_t readings;
+    opaque blob<>;
+    string note<>;
+    int type;
+    struct {
+        hyper when;
+        bool valid;
+    } stamp;
+};
+
+struct measurement {
+    float value;
+    double error;
+    quadruple precise;
+};
+
+union result switch (color c) {
+case RED:
+case GREEN:
+    node item;
+case BLUE:
+    void;
+};
+
+union status switch (int code) {
+case 0:
+    string message<>;
+case MINTEMP:
+    measurement reading;
+default:
+    void;
+};
+
+union flag switch (bool set) {
+case TRUE:
+    unsigned int mask;
+case FALSE:
+    void;
+};
+
+typedef union switch (unsigned int kind) {
+case 1:
+    int small;
+default:
+    hyper big;
+} number;
+
+program SAMPLE_PROG {
+    version SAMPLE_VERS {
+        void SAMPLE_NULL(void) = 0;
+        result LOOKUP(name_t) = 1;
+    } = 1;
+    version SAMPLE_VERS2 {
+        void SAMPLE_NULL(void) = 0;
+        result LOOKUP(name_t) = 1;
+        int ADD(int, int) = 2;
+    } = 2;
+} = 0x20000101;
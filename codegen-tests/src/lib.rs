@@ -0,0 +1,9 @@
+// Types generated by serde-xdr-build from the `.x` files in proto/
+
+pub mod sample {
+    include!(concat!(env!("OUT_DIR"), "/sample.rs"));
+}
+
+pub mod vxi11 {
+    include!(concat!(env!("OUT_DIR"), "/vxi11.rs"));
+}
@@ -0,0 +1,169 @@
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use codegen_tests::sample::*;
+use codegen_tests::vxi11;
+use common::{encode, round_trip, words};
+use serde_xdr::{from_bytes, Bounded, FixedArray, FixedOpaque, List, Opaque, Quadruple};
+
+use std::convert::TryFrom;
+
+#[test]
+fn constants() {
+    assert_eq!(MAXNAME, 64);
+    assert_eq!(MINTEMP, -40);
+    assert_eq!((SAMPLE_PROG, SAMPLE_VERS, LOOKUP), (0x2000_0101, 1, 1));
+    assert_eq!(Color::Green as i32, 2);
+    assert_eq!(Color::Blue as i32, 16);
+
+    assert_eq!(vxi11::DEVICE_CORE, serde_xdr::vxi11::DEVICE_CORE);
+    assert_eq!(vxi11::DEVICE_ASYNC, serde_xdr::vxi11::DEVICE_ASYNC);
+    assert_eq!(vxi11::DEVICE_INTR_SRQ, serde_xdr::vxi11::DEVICE_INTR_SRQ);
+    assert_eq!(
+        vxi11::DESTROY_INTR_CHAN,
+        serde_xdr::vxi11::DESTROY_INTR_CHAN
+    );
+}
+
+#[test]
+fn structs() {
+    let list = Node {
+        name: Bounded::try_from("head").unwrap(),
+        serial: 1,
+        next: List(vec![NodeEntry {
+            name: Bounded::try_from("tail").unwrap(),
+            serial: 2,
+        }]),
+    };
+    assert_eq!(
+        round_trip(&list),
+        words(&[4, 0x6865_6164, 0, 1, 1, 4, 0x7461_696c, 0, 2, 0])
+    );
+
+    let sample = Sample {
+        id: FixedOpaque([1, 2, 3, 4, 5, 6]),
+        shade: Color::Blue,
+        palette: FixedArray([Color::Red, Color::Green, Color::Blue]),
+        readings: Bounded::new(vec![-1, 0, 1]).unwrap(),
+        blob: Opaque(vec![0xAB]),
+        note: String::from("hi"),
+        r#type: 7,
+        stamp: SampleStamp {
+            when: -1,
+            valid: true,
+        },
+    };
+    let buf = round_trip(&sample);
+    // Fixed opaque and arrays have no length, the fixed opaque is padded to 8 bytes
+    assert_eq!(&buf[..8], &[1, 2, 3, 4, 5, 6, 0, 0]);
+    assert_eq!(&buf[8..24], &words(&[16, 1, 2, 16])[..]);
+    assert_eq!(buf.len(), 8 + 4 + 12 + 16 + 8 + 8 + 4 + 12);
+
+    round_trip(&Measurement {
+        value: 1.5,
+        error: -0.25,
+        precise: Quadruple::from_f64(3.0),
+    });
+}
+
+#[test]
+fn linked_lists() {
+    // A list of one plain field holds that type, with no entry struct
+    let shades: ShadeList = List(vec![Color::Red, Color::Blue]);
+    assert_eq!(round_trip(&shades), words(&[1, 1, 1, 16, 0]));
+    let first = Shades {
+        shade: Color::Green,
+        next: shades,
+    };
+    assert_eq!(round_trip(&first), words(&[2, 1, 1, 1, 16, 0]));
+
+    // Long enough to run out of stack if each entry were a level of nesting
+    let long: ShadeList = List(vec![Color::Green; 200_000]);
+    let (decoded, _): (ShadeList, _) = from_bytes(&encode(&long)).unwrap();
+    assert_eq!(decoded.len(), 200_000);
+}
+
+#[test]
+fn bounds() {
+    assert!(NameT::new("x".repeat(65)).is_err());
+    assert!(ReadingsT::new(vec![0; 5]).is_err());
+
+    // Five readings on the wire, one more than the bound allows
+    let mut buf = words(&[5, 0, 0, 0, 0, 0]);
+    assert!(from_bytes::<ReadingsT>(&buf).is_err());
+    buf[3] = 4;
+    let (readings, _): (ReadingsT, _) = from_bytes(&buf).unwrap();
+    assert_eq!(readings.len(), 4);
+}
+
+#[test]
+fn unions() {
+    let node = Node {
+        name: Bounded::default(),
+        serial: 9,
+        next: List::default(),
+    };
+    // Cases sharing an arm keep their own discriminant
+    let red = round_trip(&Result::Red(node.clone()));
+    let green = round_trip(&Result::Green(node));
+    assert_eq!(&red[..4], &words(&[1])[..]);
+    assert_eq!(&green[..4], &words(&[2])[..]);
+    assert_eq!(&red[4..], &green[4..]);
+    assert_eq!(round_trip(&Result::Blue), words(&[16]));
+    assert!(from_bytes::<Result>(&words(&[3])).is_err());
+
+    assert_eq!(
+        round_trip(&Status::Case0(String::from("ok"))),
+        [0, 0, 0, 0, 0, 0, 0, 2, b'o', b'k', 0, 0]
+    );
+    let reading = Status::Mintemp(Measurement {
+        value: 0.0,
+        error: 0.0,
+        precise: Quadruple::default(),
+    });
+    assert_eq!(&round_trip(&reading)[..4], &(-40i32).to_be_bytes());
+    assert_eq!(round_trip(&Status::Default(7)), words(&[7]));
+
+    assert_eq!(round_trip(&Flag::True(0xF0)), words(&[1, 0xF0]));
+    assert_eq!(round_trip(&Flag::False), words(&[0]));
+
+    assert_eq!(round_trip(&Number::Case1(-2)), words(&[1, 0xFFFF_FFFE]));
+    assert_eq!(round_trip(&Number::Default(5, 3)), words(&[5, 0, 3]));
+}
+
+#[test]
+fn matches_hand_written_vxi11() {
+    let params = serde_xdr::vxi11::CreateLinkParms {
+        client_id: 42,
+        lock_device: true,
+        lock_timeout: 1000,
+        device: String::from("inst0"),
+    };
+    let generated = vxi11::CreateLinkParms {
+        client_id: 42,
+        lock_device: true,
+        lock_timeout: 1000,
+        device: String::from("inst0"),
+    };
+    assert_eq!(encode(&generated), encode(&params));
+
+    let resp = serde_xdr::vxi11::DeviceReadResp {
+        error: serde_xdr::vxi11::DeviceErrorCode::NO_ERROR,
+        reason: serde_xdr::vxi11::ReadReason::END,
+        data: Opaque(b"1.23\n".to_vec()),
+    };
+    let (decoded, _): (vxi11::DeviceReadResp, _) = from_bytes(&encode(&resp)).unwrap();
+    assert_eq!(decoded.reason, 4);
+    assert_eq!(decoded.data, resp.data);
+
+    let func = vxi11::DeviceRemoteFunc {
+        host_addr: 0x7F00_0001,
+        host_port: 1024,
+        prog_num: vxi11::DEVICE_INTR,
+        prog_vers: vxi11::DEVICE_INTR_VERSION,
+        prog_family: vxi11::DeviceAddrFamily::DeviceTcp,
+    };
+    let (decoded, _): (serde_xdr::vxi11::DeviceRemoteFunc, _) = from_bytes(&encode(&func)).unwrap();
+    assert_eq!(decoded.host_port, 1024);
+    assert_eq!(decoded.prog_family, serde_xdr::vxi11::DeviceAddrFamily::Tcp);
+}
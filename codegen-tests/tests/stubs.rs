@@ -0,0 +1,109 @@
+use codegen_tests::sample::*;
+use codegen_tests::vxi11::{self, DeviceCoreV1Client};
+use serde_xdr::rpc::{ProcError, RpcClient, RpcServer};
+use serde_xdr::vxi11::{ScriptedDevice, SimulatedServer};
+use serde_xdr::{Bounded, RpcError};
+
+use std::convert::TryFrom;
+
+struct Lookup;
+
+impl SampleProgV1 for Lookup {
+    fn sample_null(&self) -> std::result::Result<(), ProcError> {
+        Ok(())
+    }
+
+    fn lookup(&self, args: NameT) -> std::result::Result<Result, ProcError> {
+        match &args[..] {
+            "blue" => Ok(Result::Blue),
+            "nothing" => Err(ProcError::SystemErr),
+            _ => Err(ProcError::GarbageArgs),
+        }
+    }
+}
+
+struct Adder;
+
+impl SampleProgV2 for Adder {
+    fn sample_null(&self) -> std::result::Result<(), ProcError> {
+        Ok(())
+    }
+
+    fn lookup(&self, _: NameT) -> std::result::Result<Result, ProcError> {
+        Err(ProcError::ProcUnavail)
+    }
+
+    fn add(&self, arg1: i32, arg2: i32) -> std::result::Result<i32, ProcError> {
+        Ok(arg1 + arg2)
+    }
+}
+
+#[test]
+fn generated_client_and_server() {
+    let mut server = RpcServer::new();
+    server.register(SampleProgV1Server(Lookup));
+    server.register(SampleProgV2Server(Adder));
+    let handle = server.spawn_tcp("127.0.0.1:0").unwrap();
+
+    let v1 = SampleProgV1Client::connect(handle.local_addr()).unwrap();
+    v1.sample_null().unwrap();
+    let blue = Bounded::try_from("blue").unwrap();
+    assert_eq!(v1.lookup(&blue).unwrap(), Result::Blue);
+    let nothing = Bounded::try_from("nothing").unwrap();
+    assert!(matches!(v1.lookup(&nothing), Err(RpcError::SystemErr)));
+
+    // Both versions are served side by side, with arguments sent one after the other
+    let v2 = SampleProgV2Client::new(v1.into_inner());
+    assert_eq!(v2.add(&2, &-5).unwrap(), -3);
+    assert!(matches!(v2.lookup(&blue), Err(RpcError::ProcUnavail)));
+
+    let mut client = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+    let err = client.call::<_, ()>(SAMPLE_PROG, 3, 0, &()).unwrap_err();
+    assert!(matches!(err, RpcError::ProgMismatch { low: 1, high: 2 }));
+    handle.shutdown();
+}
+
+#[test]
+fn generated_client_talks_to_hand_written_server() {
+    let device = ScriptedDevice::new().respond("*IDN?", "SIM,0,0,1");
+    let simulator = SimulatedServer::new()
+        .device("inst0", device)
+        .spawn("127.0.0.1:0")
+        .unwrap();
+
+    let core = DeviceCoreV1Client::connect(simulator.core_addr()).unwrap();
+    let link = core
+        .create_link(&vxi11::CreateLinkParms {
+            client_id: 1,
+            lock_device: false,
+            lock_timeout: 0,
+            device: String::from("inst0"),
+        })
+        .unwrap();
+    assert_eq!(link.error, 0);
+
+    let write = core
+        .device_write(&vxi11::DeviceWriteParms {
+            lid: link.lid,
+            io_timeout: 1000,
+            lock_timeout: 0,
+            flags: 8,
+            data: serde_xdr::Opaque(b"*IDN?\n".to_vec()),
+        })
+        .unwrap();
+    assert_eq!(write.size, 6);
+
+    let read = core
+        .device_read(&vxi11::DeviceReadParms {
+            lid: link.lid,
+            request_size: 256,
+            io_timeout: 1000,
+            lock_timeout: 0,
+            flags: 0,
+            term_char: 0,
+        })
+        .unwrap();
+    assert_eq!(read.data.0, b"SIM,0,0,1\n");
+    assert_eq!(core.destroy_link(&link.lid).unwrap().error, 0);
+    simulator.shutdown();
+}
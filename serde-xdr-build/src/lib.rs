@@ -0,0 +1,83 @@
+// Build script support for generating serde-xdr types from `.x` files. In `build.rs`:
+//
+//     fn main() {
+//         serde_xdr_build::compile("proto/vxi11.x").unwrap();
+//     }
+//
+// writes `$OUT_DIR/vxi11.rs`, which the crate then pulls in with
+// `include!(concat!(env!("OUT_DIR"), "/vxi11.rs"));`. The generated code refers to `serde` and
+// `serde_xdr`, so both need to be dependencies.
+
+use serde_xdr::idl::generate;
+use serde_xdr::IdlError;
+
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::{env, error, fs, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Idl { path: PathBuf, error: IdlError },
+    NoOutDir,
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref inner) => Some(inner),
+            Error::Idl { ref error, .. } => Some(error),
+            Error::NoOutDir => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref error) => Display::fmt(error, fmt),
+            Error::Idl {
+                ref path,
+                ref error,
+            } => write!(fmt, "{}:{}", path.display(), error),
+            Error::NoOutDir => fmt.write_str("OUT_DIR isn't set, compile is meant for build.rs"),
+        }
+    }
+}
+
+// Generates `$OUT_DIR/<name>.rs` from `<name>.x`, and has cargo rerun the build script when the
+// `.x` file changes. Returns the path written.
+pub fn compile<P: AsRef<Path>>(path: P) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    println!("cargo:rerun-if-changed={}", path.display());
+    let out_dir = env::var_os("OUT_DIR").ok_or(Error::NoOutDir)?;
+    compile_to(path, out_dir)
+}
+
+// Like compile, for use outside of a build script
+pub fn compile_to<P, Q>(path: P, out_dir: Q) -> Result<PathBuf, Error>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let code = generate(&source).map_err(|error| Error::Idl {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    // Appended rather than swapped in with with_extension, which would turn `vxi11.core.x` into
+    // `vxi11.rs`
+    let name = path.file_stem().unwrap_or(path.as_os_str());
+    let out = out_dir
+        .as_ref()
+        .join(format!("{}.rs", name.to_string_lossy()));
+    fs::write(&out, code)?;
+    Ok(out)
+}
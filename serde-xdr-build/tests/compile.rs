@@ -0,0 +1,33 @@
+use serde_xdr_build::{compile_to, Error};
+
+use std::fs;
+
+#[test]
+fn compile_errors_name_the_file() {
+    let dir = std::env::temp_dir().join(format!("serde-xdr-build-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("broken.x");
+    fs::write(&path, "struct s {\n    missing_t field;\n};\n").unwrap();
+
+    let err = compile_to(&path, &dir).unwrap_err();
+    assert!(matches!(err, Error::Idl { ref error, .. } if error.line == 2));
+    let message = err.to_string();
+    assert!(message.starts_with(&format!("{}:2:5: unknown type `missing_t`", path.display())));
+
+    fs::write(&path, "const A = 1;\n").unwrap();
+    let out = compile_to(&path, &dir).unwrap();
+    assert_eq!(out, dir.join("broken.rs"));
+    assert!(fs::read_to_string(&out)
+        .unwrap()
+        .contains("pub const A: u32 = 1;"));
+
+    // Only the `.x` comes off, so specs sharing a prefix don't overwrite each other
+    for name in &["vxi11.core.x", "vxi11.intr.x"] {
+        fs::write(dir.join(name), "const A = 1;\n").unwrap();
+    }
+    let core = compile_to(dir.join("vxi11.core.x"), &dir).unwrap();
+    let intr = compile_to(dir.join("vxi11.intr.x"), &dir).unwrap();
+    assert_eq!(core, dir.join("vxi11.core.rs"));
+    assert_eq!(intr, dir.join("vxi11.intr.rs"));
+    fs::remove_dir_all(&dir).unwrap();
+}
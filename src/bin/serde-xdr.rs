@@ -0,0 +1,149 @@
+// Command line access to schema-driven decoding, for looking at captured messages:
+//
+//     serde-xdr decode --schema vxi11.x --type Device_ReadResp < resp.bin
+//     serde-xdr encode --schema vxi11.x --type Device_ReadResp < resp.json > resp.bin
+//     serde-xdr hexdump --schema vxi11.x --type Device_ReadResp resp.bin
+//     serde-xdr validate --schema vxi11.x --type Device_ReadResp resp.bin
+//     serde-xdr pcap capture.pcapng
+//
+// Input comes from the file named last, or stdin. Decoded values are printed as JSON in the form
+// `encode` reads back. `pcap` needs no schema: it prints the RPC calls and replies in a capture.
+
+use serde_xdr::pcap;
+use serde_xdr::value::{
+    decode_with_schema, encode_with_schema, from_json, to_json, trace_with_schema, XdrSchema,
+};
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+usage: serde-xdr <decode|encode|hexdump|validate> --schema <file.x> --type <name> [input]
+       serde-xdr pcap [capture]";
+
+struct Args {
+    command: String,
+    // The .x file and type name, which everything but pcap needs
+    schema: Option<(String, String)>,
+    input: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = args.next().ok_or_else(|| String::from(USAGE))?;
+    let (mut schema, mut type_name, mut input) = (None, None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => schema = args.next(),
+            "--type" => type_name = args.next(),
+            "-h" | "--help" => return Err(String::from(USAGE)),
+            _ if input.is_none() && !arg.starts_with("--") => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`\n{}", arg, USAGE)),
+        }
+    }
+    let schema = match (schema, type_name) {
+        (Some(schema), Some(type_name)) if command != "pcap" => Some((schema, type_name)),
+        (None, None) if command == "pcap" => None,
+        _ => return Err(String::from(USAGE)),
+    };
+    Ok(Args {
+        command,
+        schema,
+        input,
+    })
+}
+
+fn read_input(input: &Option<String>) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let read = match *input {
+        Some(ref path) => fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes)),
+        None => io::stdin().read_to_end(&mut bytes),
+    };
+    read.map_err(|err| format!("{}: {}", input.as_deref().unwrap_or("stdin"), err))?;
+    Ok(bytes)
+}
+
+const COMMANDS: &[&str] = &["decode", "encode", "hexdump", "validate", "pcap"];
+
+fn run(args: Args) -> Result<(), String> {
+    // Before stdin is read, which would otherwise wait for input that's never used
+    if !COMMANDS.contains(&args.command.as_str()) {
+        return Err(format!("unknown command `{}`\n{}", args.command, USAGE));
+    }
+    let input = read_input(&args.input)?;
+    let (path, type_name) = match args.schema {
+        Some(schema) => schema,
+        None => return write_out(&pcap_transactions(&input)?),
+    };
+    let source = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+    let schema =
+        XdrSchema::from_idl(&source, &type_name).map_err(|err| format!("{}:{}", path, err))?;
+
+    let out = match args.command.as_str() {
+        "decode" => {
+            let (value, consumed) =
+                decode_with_schema(&schema, &input).map_err(|e| e.to_string())?;
+            if consumed < input.len() {
+                eprintln!("{} trailing bytes weren't decoded", input.len() - consumed);
+            }
+            let json = serde_json::to_string_pretty(&to_json(&value)).map_err(|e| e.to_string())?;
+            (json + "\n").into_bytes()
+        }
+        "encode" => {
+            let json = serde_json::from_slice(&input).map_err(|e| e.to_string())?;
+            let value = from_json(&schema, &json).map_err(|e| e.to_string())?;
+            encode_with_schema(&schema, &value).map_err(|e| e.to_string())?
+        }
+        "hexdump" => {
+            // Whatever decodes is still worth seeing when the rest doesn't, so the dump goes out
+            // before the error
+            let (res, trace) = trace_with_schema(&schema, &input);
+            write_out(trace.dump(&input).as_bytes())?;
+            res.map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        "validate" => {
+            let (_, consumed) = decode_with_schema(&schema, &input).map_err(|e| e.to_string())?;
+            if consumed != input.len() {
+                return Err(format!(
+                    "{} bytes decode as {}, leaving {} trailing bytes",
+                    consumed,
+                    type_name,
+                    input.len() - consumed
+                ));
+            }
+            format!("ok: {} bytes\n", consumed).into_bytes()
+        }
+        _ => unreachable!(),
+    };
+    write_out(&out)
+}
+
+// With a blank line between transactions
+fn pcap_transactions(capture: &[u8]) -> Result<Vec<u8>, String> {
+    let transactions = pcap::transactions(capture).map_err(|e| e.to_string())?;
+    let out: Vec<String> = transactions.iter().map(ToString::to_string).collect();
+    Ok(out.join("\n").into_bytes())
+}
+
+fn write_out(out: &[u8]) -> Result<(), String> {
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(out)
+        .and_then(|()| stdout.flush())
+        .map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(usage) => {
+            eprintln!("{}", usage);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(args) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
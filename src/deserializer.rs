@@ -1,7 +1,10 @@
 use crate::errors::{DecoderResult, EncoderError};
+use crate::serializer::padding;
+use crate::trace::Trace;
 
 use byteorder::{BigEndian, ReadBytesExt};
 use serde::de::{self, Deserialize, IntoDeserializer, Visitor};
+use std::collections::HashSet;
 use std::io::{self, Read};
 
 macro_rules! not_implemented {
@@ -16,12 +19,14 @@ macro_rules! not_implemented {
 macro_rules! impl_num {
     ($ty:ty, $deserialize_method:ident, $visitor_method:ident, $read_method:ident, $byte_size:expr) => {
         fn $deserialize_method<V>(self, visitor: V) -> DecoderResult<V::Value>
-            where V: Visitor<'de>, {
-                let res = visitor.$visitor_method(self.$read_method::<BigEndian>()?);
-                self.bytes_consumed += $byte_size;
-                res
+        where
+            V: Visitor<'de>,
+        {
+            let value = self.$read_method::<BigEndian>()?;
+            self.advance($byte_size, stringify!($ty));
+            visitor.$visitor_method(value)
         }
-    }
+    };
 }
 
 #[derive(Debug)]
@@ -31,6 +36,10 @@ where
 {
     reader: R,
     bytes_consumed: usize,
+    deny_duplicate_keys: bool,
+    capture: Option<Vec<u8>>,
+    skipping: bool,
+    trace: Option<Trace>,
 }
 
 impl<R> Deserializer<R>
@@ -41,12 +50,117 @@ where
         Deserializer {
             reader,
             bytes_consumed: 0,
+            deny_duplicate_keys: false,
+            capture: None,
+            skipping: false,
+            trace: None,
         }
     }
 
+    /// Reject maps where the same encoded key shows up more than once
+    pub fn deny_duplicate_keys(mut self, deny: bool) -> Self {
+        self.deny_duplicate_keys = deny;
+        self
+    }
+
+    /// Record every primitive read, see crate::trace
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.trace = if trace { Some(Trace::default()) } else { None };
+        self
+    }
+
     pub fn get_bytes_consumed(&self) -> usize {
         self.bytes_consumed
     }
+
+    pub fn get_trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    // All counting goes through here, so traced offsets always agree with get_bytes_consumed
+    fn advance(&mut self, count: usize, type_name: &'static str) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.record(self.bytes_consumed, count, type_name);
+        }
+        self.bytes_consumed += count;
+    }
+
+    pub(crate) fn relabel(&mut self, type_name: &'static str) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.relabel(type_name);
+        }
+    }
+
+    // Path segments are only built while tracing. An error leaves the segment in place, so the
+    // trace still says where decoding stopped.
+    pub(crate) fn enter<F: FnOnce() -> String>(&mut self, segment: F) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.enter(segment());
+        }
+    }
+
+    pub(crate) fn leave(&mut self) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.leave();
+        }
+    }
+
+    // XDR isn't self describing, so skipping needs the type of the value being passed over, and
+    // serde only learns that type by building the value. Lengths and fixed-size fields are still
+    // read and every sequence element is still constructed and dropped; what's saved is the
+    // string and opaque payloads, which are jumped over and handed to the type as empty. A type
+    // whose Deserialize rejects an empty string or byte buffer can't be skipped this way.
+    pub fn skip<'de, T>(&mut self) -> DecoderResult<()>
+    where
+        T: Deserialize<'de>,
+    {
+        let skipping = self.skipping;
+        self.skipping = true;
+        let res = T::deserialize(&mut *self);
+        self.skipping = skipping;
+        res.map(|_| ())
+    }
+
+    fn jump(&mut self, count: usize, type_name: &'static str) -> DecoderResult<()> {
+        let jumped = io::copy(&mut self.take(count as u64), &mut io::sink())?;
+        if jumped != count as u64 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.advance(count, type_name);
+        Ok(())
+    }
+
+    // Reads a length-prefixed payload along with its padding, or jumps over it while skipping
+    fn read_padded(&mut self, type_name: &'static str) -> DecoderResult<Vec<u8>> {
+        let count: u32 = Deserialize::deserialize(&mut *self)?;
+        self.relabel("length");
+        let count = count as usize;
+        if self.skipping {
+            self.jump(count + padding(count), "skipped")?;
+            return Ok(Vec::new());
+        }
+        self.read_fixed(count, type_name)
+    }
+
+    // Reads a payload of known length along with its padding
+    pub(crate) fn read_fixed(
+        &mut self,
+        count: usize,
+        type_name: &'static str,
+    ) -> DecoderResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.take(count as u64).read_to_end(&mut buf)?;
+        if buf.len() != count {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.advance(count, type_name);
+        self.jump(padding(count), "padding")?;
+        Ok(buf)
+    }
 }
 
 #[derive(Debug)]
@@ -55,7 +169,7 @@ enum XdrEnumType {
     Union,
 }
 
-impl<'de, 'a, R> de::Deserializer<'de> for &'a mut Deserializer<R>
+impl<'de, R> de::Deserializer<'de> for &mut Deserializer<R>
 where
     R: Read,
 {
@@ -65,10 +179,12 @@ where
     impl_num!(u16, deserialize_u16, visit_u16, read_u16, 2);
     impl_num!(u32, deserialize_u32, visit_u32, read_u32, 4);
     impl_num!(u64, deserialize_u64, visit_u64, read_u64, 8);
+    impl_num!(u128, deserialize_u128, visit_u128, read_u128, 16);
 
     impl_num!(i16, deserialize_i16, visit_i16, read_i16, 2);
     impl_num!(i32, deserialize_i32, visit_i32, read_i32, 4);
     impl_num!(i64, deserialize_i64, visit_i64, read_i64, 8);
+    impl_num!(i128, deserialize_i128, visit_i128, read_i128, 16);
 
     impl_num!(f32, deserialize_f32, visit_f32, read_f32, 4);
     impl_num!(f64, deserialize_f64, visit_f64, read_f64, 8);
@@ -76,14 +192,6 @@ where
     not_implemented!(
         deserialize_char();
         deserialize_str();
-        deserialize_unit();
-        deserialize_option();
-        deserialize_bytes();
-        deserialize_map();
-        deserialize_unit_struct(_name: &'static str,);
-        deserialize_tuple_struct(_name: &'static str, _len: usize,);
-        deserialize_tuple(_len: usize,);
-        deserialize_ignored_any();
     );
 
     // See: deserialize_identifier
@@ -99,14 +207,13 @@ where
     where
         V: de::Visitor<'de>,
     {
-        let count: u32 = self.read_u32::<BigEndian>()?;
-        let extra_bytes = 4 - count % 4;
-        let mut accum = String::new();
-        for _ in 0..count {
-            accum.push(self.read_u8()? as char);
+        let bytes = self.read_padded("string")?;
+        match String::from_utf8(bytes) {
+            Ok(accum) => visitor.visit_string(accum),
+            Err(_) => Err(EncoderError::Unknown(String::from(
+                "invalid utf-8 when decoding string",
+            ))),
         }
-        self.bytes_consumed += (extra_bytes + count + 4) as usize;
-        visitor.visit_string(accum)
     }
 
     fn deserialize_enum<V>(
@@ -125,8 +232,12 @@ where
         }
     }
 
-    fn deserialize_byte_buf<V: Visitor<'de>>(self, mut _visitor: V) -> DecoderResult<V::Value> {
-        Err(EncoderError::Unknown(String::from("not done implementing")))
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        visitor.visit_byte_buf(self.read_padded("bytes")?)
     }
 
     fn deserialize_any<V: Visitor<'de>>(self, mut _visitor: V) -> DecoderResult<V::Value> {
@@ -135,27 +246,58 @@ where
         )))
     }
 
+    // With no type information there's no way to tell how long an ignored value is, and
+    // guessing would misread everything after it. Deserializer::skip takes the type instead.
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> DecoderResult<V::Value> {
+        Err(EncoderError::Unknown(String::from(
+            "XDR is not self describing, so untyped data can't be skipped; use Deserializer::skip",
+        )))
+    }
+
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
-        let value: u8 = Deserialize::deserialize(self)?;
+        let value: u32 = Deserialize::deserialize(&mut *self)?;
+        self.relabel("bool");
         match value {
             1 => visitor.visit_bool(true),
             0 => visitor.visit_bool(false),
             _ => Err(EncoderError::Unknown(String::from(
-                "invalid u8 when decoding bool, 0 or 1 needed",
+                "invalid u32 when decoding bool, 0 or 1 needed",
             ))),
         }
     }
 
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        let present: bool = Deserialize::deserialize(&mut *self)?;
+        self.relabel("option");
+        if present {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
-        let res = visitor.visit_u8(self.read_u8()?);
-        self.bytes_consumed += 1;
-        res
+        let value = self.read_u8()?;
+        self.advance(1, "u8");
+        visitor.visit_u8(value)
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
-        let res = visitor.visit_i8(self.read_i8()?);
-        self.bytes_consumed += 1;
-        res
+        let value = self.read_i8()?;
+        self.advance(1, "i8");
+        visitor.visit_i8(value)
+    }
+
+    // XDR void takes up no space on the wire, so there is nothing to read
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> DecoderResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> DecoderResult<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
     }
 
     fn deserialize_struct<V>(
@@ -167,7 +309,7 @@ where
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(SeqVisitor::new(self, Some(fields.len() as u32)))
+        visitor.visit_seq(SeqVisitor::new(self, Some(fields.len() as u32)).fields(fields))
     }
 
     fn deserialize_newtype_struct<V>(
@@ -181,12 +323,41 @@ where
         visitor.visit_newtype_struct(self)
     }
 
+    // No length on the wire, see Serializer::serialize_tuple
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> DecoderResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqVisitor::new(self, Some(len as u32)))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> DecoderResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
     fn deserialize_seq<V>(self, visitor: V) -> DecoderResult<V::Value>
     where
         V: Visitor<'de>,
     {
         visitor.visit_seq(SeqVisitor::new(self, None))
     }
+
+    fn deserialize_map<V>(self, visitor: V) -> DecoderResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let len: u32 = Deserialize::deserialize(&mut *self)?;
+        self.relabel("length");
+        visitor.visit_map(MapVisitor::new(self, len))
+    }
 }
 
 impl<R> Read for Deserializer<R>
@@ -194,7 +365,14 @@ where
     R: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.reader.read(buf)
+        let read = self.reader.read(buf)?;
+        if let Some(capture) = self.capture.as_mut() {
+            capture.extend_from_slice(&buf[..read]);
+        }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.read(&buf[..read]);
+        }
+        Ok(read)
     }
 }
 
@@ -205,9 +383,11 @@ where
 {
     deserializer: &'a mut Deserializer<R>,
     len: Option<u32>,
+    fields: Option<&'static [&'static str]>,
+    index: usize,
 }
 
-impl<'a, 'de, R> SeqVisitor<'a, R>
+impl<'a, R> SeqVisitor<'a, R>
 where
     R: Read,
 {
@@ -215,8 +395,16 @@ where
         SeqVisitor {
             deserializer: de,
             len: size,
+            fields: None,
+            index: 0,
         }
     }
+
+    // Struct fields are traced by name rather than position
+    fn fields(mut self, fields: &'static [&'static str]) -> Self {
+        self.fields = Some(fields);
+        self
+    }
 }
 
 impl<'de, 'a, R> de::SeqAccess<'de> for SeqVisitor<'a, R>
@@ -231,13 +419,22 @@ where
     {
         if self.len.is_none() {
             self.len = Some(Deserialize::deserialize(&mut *self.deserializer)?);
+            self.deserializer.relabel("length");
         }
         let len = self.len.unwrap();
         if len > 0 {
             if let Some(v) = self.len.iter_mut().next() {
                 *v = len - 1
             }
+            let (index, fields) = (self.index, self.fields);
+            self.index += 1;
+            self.deserializer
+                .enter(|| match fields.and_then(|f| f.get(index)) {
+                    Some(field) => format!(".{}", field),
+                    None => format!("[{}]", index),
+                });
             let value = seed.deserialize(&mut *self.deserializer)?;
+            self.deserializer.leave();
             Ok(Some(value))
         } else {
             Ok(None)
@@ -245,6 +442,98 @@ where
     }
 }
 
+#[derive(Debug)]
+struct MapVisitor<'a, R>
+where
+    R: Read,
+{
+    deserializer: &'a mut Deserializer<R>,
+    len: u32,
+    index: u32,
+    seen: Option<HashSet<Vec<u8>>>,
+}
+
+impl<'a, R> MapVisitor<'a, R>
+where
+    R: Read,
+{
+    fn new(de: &'a mut Deserializer<R>, len: u32) -> Self {
+        let seen = if de.deny_duplicate_keys {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        MapVisitor {
+            deserializer: de,
+            len,
+            index: 0,
+            seen,
+        }
+    }
+}
+
+impl<'de, 'a, R> de::MapAccess<'de> for MapVisitor<'a, R>
+where
+    R: Read,
+{
+    type Error = EncoderError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> DecoderResult<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+
+        let index = self.index;
+        self.deserializer.enter(|| format!("[{}].key", index));
+        let seen = match self.seen.as_mut() {
+            Some(seen) => seen,
+            None => {
+                let key = seed.deserialize(&mut *self.deserializer)?;
+                self.deserializer.leave();
+                return Ok(Some(key));
+            }
+        };
+
+        // Keys are compared on their encoded bytes, so record them while the key is read
+        let outer = self.deserializer.capture.replace(Vec::new());
+        let key = seed.deserialize(&mut *self.deserializer);
+        let raw = self.deserializer.capture.take().unwrap_or_default();
+        self.deserializer.capture = outer.map(|mut outer| {
+            outer.extend_from_slice(&raw);
+            outer
+        });
+
+        let key = key?;
+        self.deserializer.leave();
+        if !seen.insert(raw) {
+            return Err(EncoderError::Unknown(String::from(
+                "duplicate key when decoding map",
+            )));
+        }
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> DecoderResult<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let index = self.index;
+        self.index += 1;
+        self.deserializer.enter(|| format!("[{}].value", index));
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.deserializer.leave();
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len as usize)
+    }
+}
+
 impl<'de, R> de::VariantAccess<'de> for Deserializer<R>
 where
     R: Read,
@@ -306,7 +595,7 @@ where
     variants: &'static [&'static str],
 }
 
-impl<'a, 'de, R> VariantVisitor<'a, R>
+impl<'a, R> VariantVisitor<'a, R>
 where
     R: Read,
 {
@@ -401,6 +690,6 @@ where
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(SeqVisitor::new(self.de, Some(fields.len() as u32)))
+        visitor.visit_seq(SeqVisitor::new(self.de, Some(fields.len() as u32)).fields(fields))
     }
 }
@@ -19,19 +19,13 @@ impl From<EncoderError> for io::Error {
     fn from(err: EncoderError) -> io::Error {
         match err {
             EncoderError::Io(e) => e,
-            EncoderError::Unknown(e) => io::Error::new(io::ErrorKind::Other, e),
+            EncoderError::Unknown(e) => io::Error::other(e),
         }
     }
 }
 
 impl error::Error for EncoderError {
-    fn description(&self) -> &str {
-        match *self {
-            EncoderError::Io(ref inner) => inner.description(),
-            EncoderError::Unknown(ref inner) => inner,
-        }
-    }
-    fn cause(&self) -> Option<&dyn error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             EncoderError::Io(ref inner) => Some(inner),
             _ => None,
@@ -0,0 +1,204 @@
+// Syntax tree for the XDR language (RFC 4506 section 6) with the RPC program extensions
+// (RFC 5531 section 12). Every node keeps the byte range of the source it was parsed from.
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    // The smallest span covering both
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    // 1-based line and column of the start of the span
+    pub fn line_col(self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        (line, column)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    Int(i64, Span),
+    Const(Ident),
+}
+
+impl Value {
+    pub fn span(&self) -> Span {
+        match *self {
+            Value::Int(_, span) => span,
+            Value::Const(ref ident) => ident.span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Specification {
+    pub definitions: Vec<Definition>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Definition {
+    Const(ConstDef),
+    Type(TypeDef),
+    Program(ProgramDef),
+    // A `%` line, which rpcgen copies into its output verbatim
+    Passthrough(Passthrough),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConstDef {
+    pub name: Ident,
+    pub value: Value,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeDef {
+    pub name: Ident,
+    pub body: TypeDefBody,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeDefBody {
+    // `typedef`, with the declaration naming the new type
+    Alias(FieldType),
+    Enum(EnumBody),
+    Struct(StructBody),
+    Union(UnionBody),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Passthrough {
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeSpec {
+    Int,
+    UnsignedInt,
+    Hyper,
+    UnsignedHyper,
+    Float,
+    Double,
+    Quadruple,
+    Bool,
+    // Only in procedure arguments and results
+    Void,
+    Enum(EnumBody),
+    Struct(StructBody),
+    Union(UnionBody),
+    Named(Ident),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FieldType {
+    Plain(TypeSpec),
+    // `type *name`
+    Optional(TypeSpec),
+    FixedArray(TypeSpec, Value),
+    VarArray(TypeSpec, Option<Value>),
+    FixedOpaque(Value),
+    VarOpaque(Option<Value>),
+    String(Option<Value>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Field {
+    pub name: Ident,
+    pub ty: FieldType,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Declaration {
+    // Only in union arms
+    Void(Span),
+    Field(Field),
+}
+
+impl Declaration {
+    pub fn span(&self) -> Span {
+        match *self {
+            Declaration::Void(span) => span,
+            Declaration::Field(ref field) => field.span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EnumBody {
+    pub variants: Vec<EnumVariant>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EnumVariant {
+    pub name: Ident,
+    // rpcgen lets values be left out, C style
+    pub value: Option<Value>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StructBody {
+    pub fields: Vec<Field>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnionBody {
+    pub discriminant: Box<Field>,
+    pub arms: Vec<UnionArm>,
+    pub default: Option<Box<Declaration>>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnionArm {
+    pub cases: Vec<Value>,
+    pub decl: Declaration,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProgramDef {
+    pub name: Ident,
+    pub versions: Vec<VersionDef>,
+    pub number: Value,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionDef {
+    pub name: Ident,
+    pub procedures: Vec<ProcedureDef>,
+    pub number: Value,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProcedureDef {
+    pub name: Ident,
+    pub result: TypeSpec,
+    pub args: Vec<TypeSpec>,
+    pub number: Value,
+    pub span: Span,
+}
@@ -0,0 +1,905 @@
+// Generates Rust types from a `.x` specification, ready to be `include!`d. Structs derive serde,
+// enums go through `xdr_enum!`, and unions become Rust enums with one variant per case value so
+// the discriminant survives a round trip. Names are converted to Rust conventions, so
+// `create_link_parms` becomes `CreateLinkParms` and enum variant `DEVICE_TCP` becomes
+// `DeviceTcp`. Program blocks give typed RPC clients and server traits.
+//
+// Generated code spells out `::std` paths, as a `.x` file is free to define types called
+// `Result` or `String`.
+
+use crate::idl::ast::*;
+use crate::idl::parse;
+use crate::idl::symbols::{Discriminant, Symbols};
+use crate::idl::IdlError;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+const HEADER: &str = "// Generated by serde-xdr from a .x specification, do not edit\n";
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+pub fn generate(source: &str) -> Result<String, IdlError> {
+    let spec = parse(source)?;
+    let mut generator = Generator {
+        symbols: Symbols::collect(source, &spec)?,
+        out: String::from(HEADER),
+    };
+    for definition in &spec.definitions {
+        generator.definition(definition)?;
+    }
+    Ok(generator.out)
+}
+
+// `CreateLinkParms` from `create_link_parms`, `Create_LinkParms` or `CREATE_LINK_PARMS`
+pub fn type_name(name: &str) -> String {
+    let mut camel = String::new();
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let shouting = !word.chars().any(|c| c.is_ascii_lowercase());
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.push(first.to_ascii_uppercase());
+        }
+        if shouting {
+            camel.extend(chars.map(|c| c.to_ascii_lowercase()));
+        } else {
+            camel.extend(chars);
+        }
+    }
+    match camel.as_str() {
+        "" | "Self" => camel + "_",
+        _ => camel,
+    }
+}
+
+// `lock_timeout` from `lockTimeout` or `LOCK_TIMEOUT`
+pub fn field_name(name: &str) -> String {
+    let snake = snake_case(name);
+    match snake.as_str() {
+        "self" | "super" | "crate" | "" => snake + "_",
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{}", keyword),
+        _ => snake,
+    }
+}
+
+pub fn const_name(name: &str) -> String {
+    match snake_case(name).to_ascii_uppercase().as_str() {
+        "" | "SELF" => String::from("SELF_"),
+        upper => upper.to_string(),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            snake.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+struct Case {
+    variant: String,
+    // How the discriminant is matched on when decoding, and written out when encoding
+    pattern: String,
+    value: String,
+}
+
+struct Generator<'a> {
+    symbols: Symbols<'a>,
+    out: String,
+}
+
+impl<'a> Generator<'a> {
+    fn error<T, M: Into<String>>(&self, span: Span, message: M) -> Result<T, IdlError> {
+        self.symbols.error(span, message)
+    }
+
+    fn definition(&mut self, definition: &'a Definition) -> Result<(), IdlError> {
+        match *definition {
+            Definition::Const(ref def) => {
+                let value = self.symbols.resolve(&def.value)?;
+                let item = const_item(&def.name.name, self.literal(&def.value, value), value);
+                self.out.push('\n');
+                self.out.push_str(&item);
+            }
+            Definition::Type(ref def) => {
+                let name = type_name(&def.name.name);
+                match def.body {
+                    TypeDefBody::Alias(FieldType::Plain(ref spec)) if is_inline(spec) => {
+                        self.inline_type(&name, spec)?;
+                    }
+                    TypeDefBody::Alias(ref ty) => {
+                        let ty = self.field_type(ty, &name)?;
+                        let _ = write!(self.out, "\npub type {} = {};\n", name, ty);
+                    }
+                    TypeDefBody::Enum(ref body) => self.enum_type(&name, body),
+                    TypeDefBody::Struct(ref body) => self.struct_type(&name, body)?,
+                    TypeDefBody::Union(ref body) => self.union_type(&name, body)?,
+                }
+            }
+            Definition::Program(ref def) => {
+                self.program_consts(def)?;
+                for version in &def.versions {
+                    self.version_stubs(def, version)?;
+                }
+            }
+            Definition::Passthrough(ref passthrough) => {
+                self.out.push_str(&passthrough.text);
+                self.out.push('\n');
+            }
+        }
+        Ok(())
+    }
+
+    // rpcgen makes constants out of program, version and procedure numbers
+    fn program_consts(&mut self, program: &ProgramDef) -> Result<(), IdlError> {
+        let mut items = String::from("\n");
+        let mut numbers = vec![(&program.name, &program.number)];
+        for version in &program.versions {
+            numbers.push((&version.name, &version.number));
+            numbers.extend(version.procedures.iter().map(|p| (&p.name, &p.number)));
+        }
+        for (name, number) in numbers {
+            let value = self.symbols.resolve(number)?;
+            if u32::try_from(value).is_err() {
+                return self.error(number.span(), "RPC numbers have to fit in an unsigned int");
+            }
+            match self.symbols.consts.get(&name.name) {
+                // The same procedure in several versions
+                Some(constant) if constant.value == value => continue,
+                Some(_) => {
+                    return self.error(name.span, format!("`{}` is already defined", name.name))
+                }
+                None => self.symbols.define_const(name, value, true)?,
+            }
+            items.push_str(&const_item(&name.name, self.literal(number, value), value));
+        }
+        self.out.push_str(&items);
+        Ok(())
+    }
+
+    // Each version gets a client, a trait to implement on the server side, and an RpcProgram
+    // that decodes calls and hands them to that trait. `DEVICE_CORE` version 1 becomes
+    // `DeviceCoreV1Client`, `DeviceCoreV1` and `DeviceCoreV1Server`.
+    fn version_stubs(
+        &mut self,
+        program: &ProgramDef,
+        version: &'a VersionDef,
+    ) -> Result<(), IdlError> {
+        let name = format!(
+            "{}V{}",
+            type_name(&program.name.name),
+            self.symbols.resolve(&version.number)?
+        );
+        let prog = const_name(&program.name.name);
+        let vers = const_name(&version.name.name);
+
+        let mut client = String::new();
+        let mut handler = String::new();
+        let mut dispatch = String::new();
+        let mut numbers = HashSet::new();
+        for procedure in &version.procedures {
+            if !numbers.insert(self.symbols.resolve(&procedure.number)?) {
+                return self.error(procedure.number.span(), "duplicate procedure number");
+            }
+            let method = field_name(&procedure.name.name);
+            let number = const_name(&procedure.name.name);
+            let hoist = type_name(&procedure.name.name);
+            let args = match procedure.args[..] {
+                [TypeSpec::Void] => Vec::new(),
+                [ref arg] => vec![(
+                    "args".to_string(),
+                    self.type_spec(arg, &format!("{}Args", hoist))?,
+                )],
+                ref args => {
+                    let mut typed = Vec::new();
+                    for (i, arg) in args.iter().enumerate() {
+                        if *arg == TypeSpec::Void {
+                            return self.error(
+                                procedure.span,
+                                "`void` can only be a procedure's only argument",
+                            );
+                        }
+                        let ty = self.type_spec(arg, &format!("{}Arg{}", hoist, i + 1))?;
+                        typed.push((format!("arg{}", i + 1), ty));
+                    }
+                    typed
+                }
+            };
+            let result = match procedure.result {
+                TypeSpec::Void => String::from("()"),
+                ref spec => self.type_spec(spec, &format!("{}Result", hoist))?,
+            };
+
+            let params = |by_ref: &str| -> String {
+                args.iter()
+                    .map(|(arg, ty)| format!(", {}: {}{}", arg, by_ref, ty))
+                    .collect()
+            };
+            let names: Vec<&str> = args.iter().map(|(arg, _)| arg.as_str()).collect();
+            let sent = match names[..] {
+                [] => String::from("&()"),
+                [arg] => arg.to_string(),
+                _ => format!("&({})", names.join(", ")),
+            };
+            let _ = write!(
+                client,
+                "
+    pub fn {method}(&self{params}) -> ::serde_xdr::RpcResult<{result}> {{
+        self.client
+            .lock()
+            .unwrap_or_else(::std::sync::PoisonError::into_inner)
+            .call({prog}, {vers}, {number}, {sent})
+    }}
+",
+                method = method,
+                params = params("&"),
+                result = result,
+                prog = prog,
+                vers = vers,
+                number = number,
+                sent = sent,
+            );
+            let _ = writeln!(
+                handler,
+                "    fn {}(&self{}) -> ::std::result::Result<{}, ::serde_xdr::rpc::ProcError>;",
+                method,
+                params(""),
+                result
+            );
+            let _ = writeln!(dispatch, "            {} => {{", number);
+            for arg in &names {
+                let _ = writeln!(dispatch, "                let {} = call.args()?;", arg);
+            }
+            let invoke = format!("self.0.{}({})?", method, names.join(", "));
+            if procedure.result == TypeSpec::Void {
+                let _ = writeln!(
+                    dispatch,
+                    "                {};\n                Ok(())",
+                    invoke
+                );
+            } else {
+                let _ = writeln!(dispatch, "                call.reply(&{})", invoke);
+            }
+            dispatch.push_str("            }\n");
+        }
+        // The null procedure answers even when the specification leaves it out
+        if !numbers.contains(&0) {
+            dispatch.push_str("            0 => Ok(()),\n");
+        }
+
+        let _ = write!(
+            self.out,
+            "
+pub struct {name}Client {{
+    client: ::std::sync::Mutex<::serde_xdr::rpc::RpcClient>,
+}}
+
+impl {name}Client {{
+    pub fn new(client: ::serde_xdr::rpc::RpcClient) -> Self {{
+        {name}Client {{
+            client: ::std::sync::Mutex::new(client),
+        }}
+    }}
+
+    pub fn connect<A: ::std::net::ToSocketAddrs>(addr: A) -> ::std::io::Result<Self> {{
+        ::serde_xdr::rpc::RpcClient::connect_tcp(addr).map(Self::new)
+    }}
+
+    pub fn into_inner(self) -> ::serde_xdr::rpc::RpcClient {{
+        self.client
+            .into_inner()
+            .unwrap_or_else(::std::sync::PoisonError::into_inner)
+    }}
+{client}}}
+
+pub trait {name}: Send + Sync {{
+{handler}}}
+
+pub struct {name}Server<T>(pub T);
+
+impl<T: {name}> ::serde_xdr::rpc::RpcProgram for {name}Server<T> {{
+    fn program(&self) -> u32 {{
+        {prog}
+    }}
+
+    fn versions(&self) -> (u32, u32) {{
+        ({vers}, {vers})
+    }}
+
+    fn dispatch(
+        &self,
+        call: &mut ::serde_xdr::rpc::Call,
+    ) -> ::std::result::Result<(), ::serde_xdr::rpc::ProcError> {{
+        match call.proc() {{
+{dispatch}            _ => Err(::serde_xdr::rpc::ProcError::ProcUnavail),
+        }}
+    }}
+}}
+",
+            name = name,
+            client = client,
+            handler = handler,
+            prog = prog,
+            vers = vers,
+            dispatch = dispatch,
+        );
+        Ok(())
+    }
+
+    // Hex stays hex, anything else is written out in decimal
+    fn literal(&self, value: &Value, resolved: i64) -> String {
+        if let Value::Int(_, span) = *value {
+            let text = &self.symbols.source[span.start..span.end];
+            if text.trim_start_matches('-').starts_with("0x") {
+                return text.to_string();
+            }
+        }
+        resolved.to_string()
+    }
+
+    fn length(&self, value: &Value) -> Result<(u32, Option<String>), IdlError> {
+        let resolved = self.symbols.resolve(value)?;
+        let length = match u32::try_from(resolved) {
+            Ok(length) => length,
+            Err(_) => return self.error(value.span(), "lengths have to fit in an unsigned int"),
+        };
+        let name = match *value {
+            Value::Const(ref ident)
+                if self.symbols.consts.get(&ident.name).is_some_and(|c| c.item) =>
+            {
+                Some(const_name(&ident.name))
+            }
+            _ => None,
+        };
+        Ok((length, name))
+    }
+
+    fn bound(&self, value: &Value) -> Result<String, IdlError> {
+        let (length, name) = self.length(value)?;
+        Ok(name.unwrap_or_else(|| length.to_string()))
+    }
+
+    fn size(&self, value: &Value) -> Result<String, IdlError> {
+        let (length, name) = self.length(value)?;
+        Ok(name.map_or_else(
+            || length.to_string(),
+            |name| format!("{{ {} as usize }}", name),
+        ))
+    }
+
+    // The Rust type of a declaration. Inline enum, struct and union bodies are pulled out into
+    // types of their own, called `hoist`.
+    fn field_type(&mut self, ty: &'a FieldType, hoist: &str) -> Result<String, IdlError> {
+        Ok(match *ty {
+            FieldType::Plain(ref spec) => self.type_spec(spec, hoist)?,
+            FieldType::Optional(ref spec) => match self.list_entry(spec) {
+                Some(entry) => format!("::serde_xdr::List<{}>", entry),
+                None => format!(
+                    "::std::option::Option<::std::boxed::Box<{}>>",
+                    self.type_spec(spec, hoist)?
+                ),
+            },
+            FieldType::FixedArray(ref spec, ref len) => format!(
+                "::serde_xdr::FixedArray<{}, {}>",
+                self.type_spec(spec, hoist)?,
+                self.size(len)?
+            ),
+            FieldType::VarArray(ref spec, ref max) => {
+                let vec = format!("::std::vec::Vec<{}>", self.type_spec(spec, hoist)?);
+                self.bounded(vec, max)?
+            }
+            FieldType::FixedOpaque(ref len) => {
+                format!("::serde_xdr::FixedOpaque<{}>", self.size(len)?)
+            }
+            FieldType::VarOpaque(ref max) => {
+                self.bounded(String::from("::serde_xdr::Opaque"), max)?
+            }
+            FieldType::String(ref max) => {
+                self.bounded(String::from("::std::string::String"), max)?
+            }
+        })
+    }
+
+    // `struct node { ...; node *next; }` is how a `.x` file spells a list. As nested options it
+    // would recurse once per entry on the way in and out, so `node *` becomes a List of what
+    // each entry holds: the one other field's type, or a `NodeEntry` struct of the other fields.
+    fn list_entry(&self, spec: &TypeSpec) -> Option<String> {
+        let ident = match *spec {
+            TypeSpec::Named(ref ident) => ident,
+            _ => return None,
+        };
+        let body = match self.symbols.types.get(ident.name.as_str()) {
+            Some(TypeDefBody::Struct(body)) => body,
+            _ => return None,
+        };
+        let entries = self.list_fields(body)?;
+        Some(match entry_spec(entries) {
+            Some(spec) => plain_type(spec),
+            None => format!("{}Entry", type_name(&ident.name)),
+        })
+    }
+
+    // The fields before the pointer to the next entry, if the struct is a list
+    fn list_fields(&self, body: &'a StructBody) -> Option<&'a [Field]> {
+        let (last, entries) = body.fields.split_last()?;
+        match last.ty {
+            FieldType::Optional(TypeSpec::Named(ref ident)) if !entries.is_empty() => {
+                match self.symbols.types.get(ident.name.as_str()) {
+                    Some(TypeDefBody::Struct(own)) if std::ptr::eq(own, body) => Some(entries),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn bounded(&self, ty: String, max: &Option<Value>) -> Result<String, IdlError> {
+        Ok(match *max {
+            Some(ref max) => format!("::serde_xdr::Bounded<{}, {}>", ty, self.bound(max)?),
+            None => ty,
+        })
+    }
+
+    fn type_spec(&mut self, spec: &'a TypeSpec, hoist: &str) -> Result<String, IdlError> {
+        match *spec {
+            TypeSpec::Named(ref ident) if !self.symbols.types.contains_key(ident.name.as_str()) => {
+                self.error(ident.span, format!("unknown type `{}`", ident.name))
+            }
+            TypeSpec::Enum(_) | TypeSpec::Struct(_) | TypeSpec::Union(_) => {
+                self.inline_type(hoist, spec)?;
+                Ok(hoist.to_string())
+            }
+            _ => Ok(plain_type(spec)),
+        }
+    }
+
+    fn inline_type(&mut self, name: &str, spec: &'a TypeSpec) -> Result<(), IdlError> {
+        match *spec {
+            TypeSpec::Enum(ref body) => self.enum_type(name, body),
+            TypeSpec::Struct(ref body) => self.struct_type(name, body)?,
+            TypeSpec::Union(ref body) => self.union_type(name, body)?,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn enum_type(&mut self, name: &str, body: &EnumBody) {
+        let _ = writeln!(self.out, "\n::serde_xdr::xdr_enum!({} {{", name);
+        for variant in &body.variants {
+            let value = self.symbols.consts[&variant.name.name].value;
+            let _ = writeln!(
+                self.out,
+                "    {} = {},",
+                type_name(&variant.name.name),
+                value
+            );
+        }
+        self.out.push_str("});\n");
+    }
+
+    fn struct_type(&mut self, name: &str, body: &'a StructBody) -> Result<(), IdlError> {
+        self.struct_item(name, &body.fields)?;
+        match self.list_fields(body) {
+            Some(entries) if entry_spec(entries).is_none() => {
+                self.struct_item(&format!("{}Entry", name), entries)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn struct_item(&mut self, name: &str, fields: &'a [Field]) -> Result<(), IdlError> {
+        let mut item = String::new();
+        for field in fields {
+            let hoist = format!("{}{}", name, type_name(&field.name.name));
+            let ty = self.field_type(&field.ty, &hoist)?;
+            let _ = writeln!(item, "    pub {}: {},", field_name(&field.name.name), ty);
+        }
+        let eq = !fields
+            .iter()
+            .any(|f| self.has_float(&f.ty, &mut HashSet::new()));
+        let _ = write!(
+            self.out,
+            "\n{}\npub struct {} {{\n{}}}\n",
+            derives(eq, true),
+            name,
+            item
+        );
+        Ok(())
+    }
+
+    fn case(&self, disc: &Discriminant, disc_ty: &str, label: &Value) -> Result<Case, IdlError> {
+        let value = self.symbols.resolve(label)?;
+        let out_of_range =
+            |this: &Self| this.error(label.span(), format!("`{}` isn't a valid case here", value));
+        match *disc {
+            Discriminant::Enum(body) => {
+                let by_name = |v: &&EnumVariant| match *label {
+                    Value::Const(ref ident) => v.name.name == ident.name,
+                    Value::Int(..) => false,
+                };
+                let variant = body.variants.iter().find(by_name).or_else(|| {
+                    let by_value =
+                        |v: &&EnumVariant| self.symbols.consts[&v.name.name].value == value;
+                    body.variants.iter().find(by_value)
+                });
+                match variant {
+                    Some(variant) => {
+                        let variant = type_name(&variant.name.name);
+                        let pattern = format!("{}::{}", disc_ty, variant);
+                        Ok(Case {
+                            variant,
+                            value: pattern.clone(),
+                            pattern,
+                        })
+                    }
+                    None => out_of_range(self),
+                }
+            }
+            Discriminant::Bool => match value {
+                0 => Ok(Case {
+                    variant: String::from("False"),
+                    pattern: String::from("false"),
+                    value: String::from("false"),
+                }),
+                1 => Ok(Case {
+                    variant: String::from("True"),
+                    pattern: String::from("true"),
+                    value: String::from("true"),
+                }),
+                _ => out_of_range(self),
+            },
+            Discriminant::Int(prim) => {
+                let fits = if prim == "u32" {
+                    u32::try_from(value).is_ok()
+                } else {
+                    i32::try_from(value).is_ok()
+                };
+                if !fits {
+                    return out_of_range(self);
+                }
+                let variant = match *label {
+                    Value::Const(ref ident) => type_name(&ident.name),
+                    Value::Int(..) if value < 0 => format!("CaseMinus{}", -value),
+                    Value::Int(..) => format!("Case{}", value),
+                };
+                Ok(Case {
+                    variant,
+                    pattern: value.to_string(),
+                    value: format!("{}{}", value, prim),
+                })
+            }
+        }
+    }
+
+    fn union_type(&mut self, name: &str, body: &'a UnionBody) -> Result<(), IdlError> {
+        let discriminant = &body.discriminant;
+        let disc_field = field_name(&discriminant.name.name);
+        let (spec, disc) = self.symbols.discriminant(discriminant)?;
+        let hoist = format!("{}{}", name, type_name(&discriminant.name.name));
+        let disc_ty = self.type_spec(spec, &hoist)?;
+
+        // One variant per case value, with the arm type if it isn't void
+        let mut arms = Vec::new();
+        let mut seen = HashSet::new();
+        for arm in &body.arms {
+            let ty = self.arm_type(name, &arm.decl)?;
+            for label in &arm.cases {
+                let case = self.case(&disc, &disc_ty, label)?;
+                if !seen.insert(case.pattern.clone()) {
+                    return self.error(label.span(), "duplicate case in union");
+                }
+                arms.push((case, ty.clone()));
+            }
+        }
+        let exhaustive = match disc {
+            Discriminant::Enum(enum_body) => seen.len() == enum_body.variants.len(),
+            Discriminant::Bool => seen.len() == 2,
+            Discriminant::Int(_) => false,
+        };
+        let default = match body.default {
+            Some(ref decl) if !exhaustive => Some(self.arm_type(name, decl)?),
+            _ => None,
+        };
+
+        let eq = !self.union_has_float(body, &mut HashSet::new());
+
+        let mut item = String::new();
+        let _ = writeln!(item, "\n{}\npub enum {} {{", derives(eq, false), name);
+        for (case, ty) in &arms {
+            let _ = match *ty {
+                Some(ref ty) => writeln!(item, "    {}({}),", case.variant, ty),
+                None => writeln!(item, "    {},", case.variant),
+            };
+        }
+        let _ = match default {
+            Some(Some(ref ty)) => writeln!(item, "    Default({}, {}),", disc_ty, ty),
+            Some(None) => writeln!(item, "    Default({}),", disc_ty),
+            None => Ok(()),
+        };
+        item.push_str("}\n");
+
+        // Like the RPC message unions: a struct of the discriminant followed by the arm
+        let _ = write!(
+            item,
+            "
+impl ::serde::Serialize for {name} {{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {{
+        use ::serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct(\"{name}\", 2)?;
+        match *self {{
+",
+            name = name
+        );
+        for (case, ty) in &arms {
+            if ty.is_some() {
+                let _ = write!(
+                    item,
+                    "            {name}::{variant}(ref arm) => {{
+                state.serialize_field(\"{disc}\", &{value})?;
+                state.serialize_field(\"arm\", arm)?;
+            }}
+",
+                    name = name,
+                    variant = case.variant,
+                    disc = disc_field,
+                    value = case.value
+                );
+            } else {
+                let _ = writeln!(
+                    item,
+                    "            {}::{} => state.serialize_field(\"{}\", &{})?,",
+                    name, case.variant, disc_field, case.value
+                );
+            }
+        }
+        match default {
+            Some(Some(_)) => {
+                let _ = write!(
+                    item,
+                    "            {name}::Default(ref disc, ref arm) => {{
+                state.serialize_field(\"{disc}\", disc)?;
+                state.serialize_field(\"arm\", arm)?;
+            }}
+",
+                    name = name,
+                    disc = disc_field
+                );
+            }
+            Some(None) => {
+                let _ = writeln!(
+                    item,
+                    "            {}::Default(ref disc) => state.serialize_field(\"{}\", disc)?,",
+                    name, disc_field
+                );
+            }
+            None => {}
+        }
+        let next = "
+                        seq.next_element()?
+                            .ok_or_else(|| ::serde::de::Error::invalid_length(1, &self))?,
+                    ";
+        let _ = write!(
+            item,
+            "        }}
+        state.end()
+    }}
+}}
+
+impl<'de> ::serde::Deserialize<'de> for {name} {{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {{
+        struct UnionVisitor;
+
+        impl<'de> ::serde::de::Visitor<'de> for UnionVisitor {{
+            type Value = {name};
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {{
+                formatter.write_str(\"union {name}\")
+            }}
+
+            fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<{name}, A::Error>
+            where
+                A: ::serde::de::SeqAccess<'de>,
+            {{
+                let disc: {disc_ty} = seq
+                    .next_element()?
+                    .ok_or_else(|| ::serde::de::Error::invalid_length(0, &self))?;
+                ::std::result::Result::Ok(match disc {{
+",
+            name = name,
+            disc_ty = disc_ty
+        );
+        for (case, ty) in &arms {
+            if ty.is_some() {
+                let _ = writeln!(
+                    item,
+                    "                    {} => {}::{}({}),",
+                    case.pattern, name, case.variant, next
+                );
+            } else {
+                let _ = writeln!(
+                    item,
+                    "                    {} => {}::{},",
+                    case.pattern, name, case.variant
+                );
+            }
+        }
+        match default {
+            Some(Some(_)) => {
+                let _ = writeln!(
+                    item,
+                    "                    disc => {}::Default(disc,{}),",
+                    name, next
+                );
+            }
+            Some(None) => {
+                let _ = writeln!(item, "                    disc => {}::Default(disc),", name);
+            }
+            None if !exhaustive => {
+                let _ = write!(
+                    item,
+                    "                    disc => {{
+                        return ::std::result::Result::Err(::serde::de::Error::custom(
+                            format!(\"unknown {name} discriminant {{:?}}\", disc),
+                        ))
+                    }}
+",
+                    name = name
+                );
+            }
+            None => {}
+        }
+        let _ = write!(
+            item,
+            "                }})
+            }}
+        }}
+
+        deserializer.deserialize_struct(\"{name}\", &[\"{disc}\", \"arm\"], UnionVisitor)
+    }}
+}}
+",
+            name = name,
+            disc = disc_field
+        );
+        self.out.push_str(&item);
+        Ok(())
+    }
+
+    fn arm_type(&mut self, union: &str, decl: &'a Declaration) -> Result<Option<String>, IdlError> {
+        match *decl {
+            Declaration::Void(_) => Ok(None),
+            Declaration::Field(ref field) => {
+                let hoist = format!("{}{}", union, type_name(&field.name.name));
+                self.field_type(&field.ty, &hoist).map(Some)
+            }
+        }
+    }
+
+    // Floats aren't Eq, so neither is anything holding one
+    fn has_float(&self, ty: &'a FieldType, seen: &mut HashSet<&'a str>) -> bool {
+        match *ty {
+            FieldType::Plain(ref spec)
+            | FieldType::Optional(ref spec)
+            | FieldType::FixedArray(ref spec, _)
+            | FieldType::VarArray(ref spec, _) => self.spec_has_float(spec, seen),
+            FieldType::FixedOpaque(_) | FieldType::VarOpaque(_) | FieldType::String(_) => false,
+        }
+    }
+
+    fn spec_has_float(&self, spec: &'a TypeSpec, seen: &mut HashSet<&'a str>) -> bool {
+        match *spec {
+            TypeSpec::Float | TypeSpec::Double => true,
+            TypeSpec::Struct(ref body) => self.struct_has_float(body, seen),
+            TypeSpec::Union(ref body) => self.union_has_float(body, seen),
+            TypeSpec::Named(ref ident) => {
+                if !seen.insert(&ident.name) {
+                    return false;
+                }
+                match self.symbols.types.get(ident.name.as_str()).copied() {
+                    Some(TypeDefBody::Alias(ty)) => self.has_float(ty, seen),
+                    Some(TypeDefBody::Struct(body)) => self.struct_has_float(body, seen),
+                    Some(TypeDefBody::Union(body)) => self.union_has_float(body, seen),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn struct_has_float(&self, body: &'a StructBody, seen: &mut HashSet<&'a str>) -> bool {
+        body.fields
+            .iter()
+            .any(|field| self.has_float(&field.ty, seen))
+    }
+
+    fn union_has_float(&self, body: &'a UnionBody, seen: &mut HashSet<&'a str>) -> bool {
+        let arms = body.arms.iter().map(|arm| &arm.decl);
+        for decl in arms.chain(body.default.as_deref()) {
+            if let Declaration::Field(ref field) = *decl {
+                if self.has_float(&field.ty, seen) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+// The Rust type of anything but an inline body
+fn plain_type(spec: &TypeSpec) -> String {
+    String::from(match *spec {
+        TypeSpec::Int => "i32",
+        TypeSpec::UnsignedInt => "u32",
+        TypeSpec::Hyper => "i64",
+        TypeSpec::UnsignedHyper => "u64",
+        TypeSpec::Float => "f32",
+        TypeSpec::Double => "f64",
+        TypeSpec::Quadruple => "::serde_xdr::Quadruple",
+        TypeSpec::Bool => "bool",
+        TypeSpec::Void => "::serde_xdr::Void",
+        TypeSpec::Named(ref ident) => return type_name(&ident.name),
+        TypeSpec::Enum(_) | TypeSpec::Struct(_) | TypeSpec::Union(_) => unreachable!(),
+    })
+}
+
+// A list whose entries are one field of a plain type holds that type directly
+fn entry_spec(entries: &[Field]) -> Option<&TypeSpec> {
+    match *entries {
+        [Field {
+            ty: FieldType::Plain(ref spec),
+            ..
+        }] if !is_inline(spec) => Some(spec),
+        _ => None,
+    }
+}
+
+fn is_inline(spec: &TypeSpec) -> bool {
+    matches!(
+        *spec,
+        TypeSpec::Enum(_) | TypeSpec::Struct(_) | TypeSpec::Union(_)
+    )
+}
+
+fn derives(eq: bool, serde: bool) -> String {
+    let mut derives = vec!["Clone", "Debug"];
+    if eq {
+        derives.push("Eq");
+    }
+    derives.push("PartialEq");
+    if serde {
+        derives.extend(["::serde::Serialize", "::serde::Deserialize"]);
+    }
+    format!("#[derive({})]", derives.join(", "))
+}
+
+// Typed by what the value fits in: u32 for anything that could be a length or RPC number
+fn const_item(name: &str, literal: String, value: i64) -> String {
+    let ty = if u32::try_from(value).is_ok() {
+        "u32"
+    } else if i32::try_from(value).is_ok() {
+        "i32"
+    } else {
+        "i64"
+    };
+    format!("pub const {}: {} = {};\n", const_name(name), ty, literal)
+}
@@ -0,0 +1,40 @@
+// Errors in `.x` source, reported by the lexer, parser and everything built on the parsed AST
+
+use crate::idl::ast::Span;
+
+use std::error;
+use std::fmt::{self, Display};
+
+// A problem in `.x` source, located by span and by line and column
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdlError {
+    pub message: String,
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+    // The offending source line, shown under the message
+    pub source_line: String,
+}
+
+impl IdlError {
+    pub fn new<M: Into<String>>(source: &str, span: Span, message: M) -> IdlError {
+        let (line, column) = span.line_col(source);
+        IdlError {
+            message: message.into(),
+            span,
+            line,
+            column,
+            source_line: source.lines().nth(line - 1).unwrap_or("").to_string(),
+        }
+    }
+}
+
+impl error::Error for IdlError {}
+
+impl Display for IdlError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "{}:{}: {}", self.line, self.column, self.message)?;
+        writeln!(fmt, "    {}", self.source_line)?;
+        write!(fmt, "    {:>width$}", "^", width = self.column)
+    }
+}
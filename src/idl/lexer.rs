@@ -0,0 +1,129 @@
+// Splits `.x` source into tokens. Comments are dropped, as are `#` lines, which are left over
+// from the C preprocessor rpcgen runs first. `%` lines become a single Passthrough token.
+
+use crate::idl::ast::Span;
+use crate::idl::IdlError;
+
+use std::convert::TryFrom;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Int(i64),
+    Punct(char),
+    Passthrough(String),
+    Eof,
+}
+
+impl Token {
+    // How the token is named in error messages
+    pub fn describe(&self) -> String {
+        match *self {
+            Token::Ident(ref name) => format!("`{}`", name),
+            Token::Int(value) => format!("`{}`", value),
+            Token::Punct(c) => format!("`{}`", c),
+            Token::Passthrough(_) => String::from("a `%` line"),
+            Token::Eof => String::from("end of file"),
+        }
+    }
+}
+
+const PUNCTUATION: &str = "{}()[]<>;,=*:";
+
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, IdlError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let mut line_start = true;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c == b'\n' {
+            line_start = true;
+            pos += 1;
+            continue;
+        }
+        if c.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        if line_start && (c == b'%' || c == b'#') {
+            let end = source[pos..].find('\n').map_or(bytes.len(), |n| pos + n);
+            if c == b'%' {
+                let text = source[pos + 1..end].to_string();
+                tokens.push((Token::Passthrough(text), Span::new(start, end)));
+            }
+            pos = end;
+            continue;
+        }
+        line_start = false;
+
+        if source[pos..].starts_with("/*") {
+            match source[pos + 2..].find("*/") {
+                Some(n) => pos += n + 4,
+                None => {
+                    return Err(IdlError::new(
+                        source,
+                        Span::new(start, start + 2),
+                        "unterminated comment",
+                    ))
+                }
+            }
+        } else if source[pos..].starts_with("//") {
+            pos = source[pos..].find('\n').map_or(bytes.len(), |n| pos + n);
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            let ident = source[start..pos].to_string();
+            tokens.push((Token::Ident(ident), Span::new(start, pos)));
+        } else if c.is_ascii_digit()
+            || (c == b'-' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit))
+        {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_alphanumeric() {
+                pos += 1;
+            }
+            let span = Span::new(start, pos);
+            let value = parse_int(&source[start..pos])
+                .ok_or_else(|| IdlError::new(source, span, "invalid integer constant"))?;
+            tokens.push((Token::Int(value), span));
+        } else if PUNCTUATION.as_bytes().contains(&c) {
+            pos += 1;
+            tokens.push((Token::Punct(c as char), Span::new(start, pos)));
+        } else {
+            let ch = source[pos..].chars().next().unwrap();
+            let span = Span::new(start, pos + ch.len_utf8());
+            return Err(IdlError::new(
+                source,
+                span,
+                format!("unexpected character `{}`", ch),
+            ));
+        }
+    }
+    tokens.push((Token::Eof, Span::new(bytes.len(), bytes.len())));
+    Ok(tokens)
+}
+
+// Decimal, hex with 0x, or octal with a leading 0, optionally negative
+fn parse_int(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let magnitude = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8).ok()?
+    } else {
+        digits.parse::<u64>().ok()?
+    };
+    if negative {
+        0i64.checked_sub_unsigned(magnitude)
+    } else {
+        i64::try_from(magnitude).ok()
+    }
+}
@@ -0,0 +1,14 @@
+// The XDR and ONC RPC interface definition language, as used by rpcgen's `.x` files
+
+pub mod ast;
+pub mod codegen;
+mod errors;
+pub mod lexer;
+pub mod parser;
+pub mod schema;
+mod symbols;
+
+pub use self::codegen::generate;
+pub use self::errors::IdlError;
+pub use self::parser::parse;
+pub use self::schema::schema;
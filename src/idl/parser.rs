@@ -0,0 +1,529 @@
+// Recursive descent parser for `.x` files. Besides the RFC grammar it takes the rpcgen extras
+// found in the wild: `long`, `short` and `char` as integer types, bare `unsigned`, `struct foo`
+// style type references and enum variants without values.
+
+use crate::idl::ast::*;
+use crate::idl::lexer::{tokenize, Token};
+use crate::idl::IdlError;
+
+const KEYWORDS: &[&str] = &[
+    "bool",
+    "case",
+    "char",
+    "const",
+    "default",
+    "double",
+    "enum",
+    "float",
+    "hyper",
+    "int",
+    "long",
+    "opaque",
+    "program",
+    "quadruple",
+    "short",
+    "string",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "version",
+    "void",
+];
+
+pub fn parse(source: &str) -> Result<Specification, IdlError> {
+    let tokens = tokenize(source)?;
+    Parser {
+        source,
+        tokens,
+        pos: 0,
+    }
+    .specification()
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn peek_at(&self, ahead: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + ahead).min(last)].0
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.pos].1
+    }
+
+    // The span from `start` to the end of the last token consumed
+    fn since(&self, start: Span) -> Span {
+        start.to(self.tokens[self.pos.saturating_sub(1)].1)
+    }
+
+    fn bump(&mut self) -> (Token, Span) {
+        let token = self.tokens[self.pos].clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T, M: Into<String>>(&self, span: Span, message: M) -> Result<T, IdlError> {
+        Err(IdlError::new(self.source, span, message))
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, IdlError> {
+        self.error(
+            self.span(),
+            format!("expected {}, found {}", expected, self.peek().describe()),
+        )
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        *self.peek() == Token::Punct(c)
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        let found = self.is_punct(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), IdlError> {
+        if self.eat_punct(c) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("`{}`", c))
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(*self.peek(), Token::Ident(ref name) if name == keyword)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), IdlError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("`{}`", keyword))
+        }
+    }
+
+    fn ident(&mut self) -> Result<Ident, IdlError> {
+        match *self.peek() {
+            Token::Ident(ref name) if KEYWORDS.contains(&name.as_str()) => self.error(
+                self.span(),
+                format!("expected an identifier, found keyword `{}`", name),
+            ),
+            Token::Ident(_) => match self.bump() {
+                (Token::Ident(name), span) => Ok(Ident { name, span }),
+                _ => unreachable!(),
+            },
+            _ => self.unexpected("an identifier"),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, IdlError> {
+        match *self.peek() {
+            Token::Int(value) => {
+                let (_, span) = self.bump();
+                Ok(Value::Int(value, span))
+            }
+            Token::Ident(_) => self.ident().map(Value::Const),
+            _ => self.unexpected("a constant or constant name"),
+        }
+    }
+
+    fn specification(mut self) -> Result<Specification, IdlError> {
+        let mut definitions = Vec::new();
+        while *self.peek() != Token::Eof {
+            definitions.push(self.definition()?);
+        }
+        Ok(Specification { definitions })
+    }
+
+    fn definition(&mut self) -> Result<Definition, IdlError> {
+        let start = self.span();
+        if let Token::Passthrough(_) = *self.peek() {
+            return match self.bump() {
+                (Token::Passthrough(text), span) => {
+                    Ok(Definition::Passthrough(Passthrough { text, span }))
+                }
+                _ => unreachable!(),
+            };
+        }
+
+        if self.eat_keyword("const") {
+            let name = self.ident()?;
+            self.expect_punct('=')?;
+            let value = self.value()?;
+            self.expect_punct(';')?;
+            return Ok(Definition::Const(ConstDef {
+                name,
+                value,
+                span: self.since(start),
+            }));
+        }
+        if self.is_keyword("program") {
+            return self.program().map(Definition::Program);
+        }
+
+        let (name, body) = if self.eat_keyword("typedef") {
+            match self.declaration()? {
+                Declaration::Field(field) => (field.name, TypeDefBody::Alias(field.ty)),
+                Declaration::Void(span) => {
+                    return self.error(span, "a typedef needs a name, not `void`")
+                }
+            }
+        } else if self.eat_keyword("enum") {
+            (self.ident()?, TypeDefBody::Enum(self.enum_body()?))
+        } else if self.eat_keyword("struct") {
+            (self.ident()?, TypeDefBody::Struct(self.struct_body()?))
+        } else if self.eat_keyword("union") {
+            (self.ident()?, TypeDefBody::Union(self.union_body()?))
+        } else {
+            return self.unexpected("a definition");
+        };
+        self.expect_punct(';')?;
+        Ok(Definition::Type(TypeDef {
+            name,
+            body,
+            span: self.since(start),
+        }))
+    }
+
+    fn enum_body(&mut self) -> Result<EnumBody, IdlError> {
+        let start = self.span();
+        self.expect_punct('{')?;
+        let mut variants = Vec::new();
+        loop {
+            let name = self.ident()?;
+            let value = if self.eat_punct('=') {
+                Some(self.value()?)
+            } else {
+                None
+            };
+            variants.push(EnumVariant {
+                span: self.since(name.span),
+                name,
+                value,
+            });
+            if !self.eat_punct(',') {
+                break;
+            }
+        }
+        self.expect_punct('}')?;
+        Ok(EnumBody {
+            variants,
+            span: self.since(start),
+        })
+    }
+
+    fn struct_body(&mut self) -> Result<StructBody, IdlError> {
+        let start = self.span();
+        self.expect_punct('{')?;
+        let mut fields = Vec::new();
+        loop {
+            match self.declaration()? {
+                Declaration::Field(field) => fields.push(field),
+                Declaration::Void(span) => {
+                    return self.error(span, "`void` is only allowed in union arms")
+                }
+            }
+            self.expect_punct(';')?;
+            if self.eat_punct('}') {
+                break;
+            }
+        }
+        Ok(StructBody {
+            fields,
+            span: self.since(start),
+        })
+    }
+
+    fn union_body(&mut self) -> Result<UnionBody, IdlError> {
+        let start = self.span();
+        self.expect_keyword("switch")?;
+        self.expect_punct('(')?;
+        let discriminant = match self.declaration()? {
+            Declaration::Field(field) => Box::new(field),
+            Declaration::Void(span) => {
+                return self.error(span, "a union discriminant can't be `void`")
+            }
+        };
+        self.expect_punct(')')?;
+        self.expect_punct('{')?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while !self.eat_punct('}') {
+            let arm_start = self.span();
+            if self.eat_keyword("default") {
+                if default.is_some() {
+                    return self.error(arm_start, "a union can only have one `default` arm");
+                }
+                self.expect_punct(':')?;
+                default = Some(Box::new(self.declaration()?));
+                self.expect_punct(';')?;
+                continue;
+            }
+            if !self.is_keyword("case") {
+                return self.unexpected("`case`, `default` or `}`");
+            }
+            let mut cases = Vec::new();
+            while self.eat_keyword("case") {
+                cases.push(self.value()?);
+                self.expect_punct(':')?;
+            }
+            let decl = self.declaration()?;
+            self.expect_punct(';')?;
+            arms.push(UnionArm {
+                cases,
+                decl,
+                span: self.since(arm_start),
+            });
+        }
+        if arms.is_empty() {
+            return self.error(start, "a union needs at least one `case` arm");
+        }
+        Ok(UnionBody {
+            discriminant,
+            arms,
+            default,
+            span: self.since(start),
+        })
+    }
+
+    fn type_spec(&mut self, allow_void: bool) -> Result<TypeSpec, IdlError> {
+        let span = self.span();
+        let name = match *self.peek() {
+            Token::Ident(ref name) => name.clone(),
+            _ => return self.unexpected("a type"),
+        };
+        let ty = match name.as_str() {
+            "unsigned" => {
+                self.bump();
+                match *self.peek() {
+                    Token::Ident(ref next) if next == "hyper" => {
+                        self.bump();
+                        TypeSpec::UnsignedHyper
+                    }
+                    Token::Ident(ref next)
+                        if ["int", "long", "short", "char"].contains(&next.as_str()) =>
+                    {
+                        self.bump();
+                        TypeSpec::UnsignedInt
+                    }
+                    _ => TypeSpec::UnsignedInt,
+                }
+            }
+            "int" | "long" | "short" | "char" => {
+                self.bump();
+                TypeSpec::Int
+            }
+            "hyper" => {
+                self.bump();
+                TypeSpec::Hyper
+            }
+            "float" => {
+                self.bump();
+                TypeSpec::Float
+            }
+            "double" => {
+                self.bump();
+                TypeSpec::Double
+            }
+            "quadruple" => {
+                self.bump();
+                TypeSpec::Quadruple
+            }
+            "bool" => {
+                self.bump();
+                TypeSpec::Bool
+            }
+            "void" if allow_void => {
+                self.bump();
+                TypeSpec::Void
+            }
+            "void" => return self.error(span, "`void` isn't allowed here"),
+            "enum" | "struct" | "union" => {
+                self.bump();
+                // Either an inline body or a reference like `struct foo`
+                match *self.peek() {
+                    Token::Ident(ref next) if !KEYWORDS.contains(&next.as_str()) => {
+                        return self.ident().map(TypeSpec::Named)
+                    }
+                    _ => {}
+                }
+                match name.as_str() {
+                    "enum" => TypeSpec::Enum(self.enum_body()?),
+                    "struct" => TypeSpec::Struct(self.struct_body()?),
+                    _ => TypeSpec::Union(self.union_body()?),
+                }
+            }
+            "opaque" | "string" => {
+                return self.error(
+                    span,
+                    format!("`{}` can only be used in a declaration", name),
+                )
+            }
+            _ => return self.ident().map(TypeSpec::Named),
+        };
+        Ok(ty)
+    }
+
+    fn declaration(&mut self) -> Result<Declaration, IdlError> {
+        let start = self.span();
+        // `void` on its own, as opposed to `void *` style nonsense caught by type_spec
+        if self.is_keyword("void") && !matches!(*self.peek_at(1), Token::Ident(_)) {
+            self.bump();
+            return Ok(Declaration::Void(start));
+        }
+
+        let ty = if self.eat_keyword("opaque") {
+            let name = self.ident()?;
+            let ty = if self.eat_punct('[') {
+                let len = self.value()?;
+                self.expect_punct(']')?;
+                FieldType::FixedOpaque(len)
+            } else if self.is_punct('<') {
+                FieldType::VarOpaque(self.max_len()?)
+            } else {
+                return self.unexpected("`[` or `<` after an opaque declaration");
+            };
+            return Ok(self.field(start, name, ty));
+        } else if self.eat_keyword("string") {
+            let name = self.ident()?;
+            if !self.is_punct('<') {
+                return self.unexpected("`<` after a string declaration");
+            }
+            let ty = FieldType::String(self.max_len()?);
+            return Ok(self.field(start, name, ty));
+        } else {
+            self.type_spec(false)?
+        };
+
+        if self.eat_punct('*') {
+            let name = self.ident()?;
+            return Ok(self.field(start, name, FieldType::Optional(ty)));
+        }
+        let name = self.ident()?;
+        let ty = if self.eat_punct('[') {
+            let len = self.value()?;
+            self.expect_punct(']')?;
+            FieldType::FixedArray(ty, len)
+        } else if self.is_punct('<') {
+            FieldType::VarArray(ty, self.max_len()?)
+        } else {
+            FieldType::Plain(ty)
+        };
+        Ok(self.field(start, name, ty))
+    }
+
+    // `<>` or `<max>`
+    fn max_len(&mut self) -> Result<Option<Value>, IdlError> {
+        self.expect_punct('<')?;
+        if self.eat_punct('>') {
+            return Ok(None);
+        }
+        let max = self.value()?;
+        self.expect_punct('>')?;
+        Ok(Some(max))
+    }
+
+    fn field(&self, start: Span, name: Ident, ty: FieldType) -> Declaration {
+        Declaration::Field(Field {
+            name,
+            ty,
+            span: self.since(start),
+        })
+    }
+
+    fn program(&mut self) -> Result<ProgramDef, IdlError> {
+        let start = self.span();
+        self.expect_keyword("program")?;
+        let name = self.ident()?;
+        self.expect_punct('{')?;
+        let mut versions = Vec::new();
+        while !self.eat_punct('}') {
+            versions.push(self.version()?);
+        }
+        if versions.is_empty() {
+            return self.error(name.span, "a program needs at least one version");
+        }
+        self.expect_punct('=')?;
+        let number = self.value()?;
+        self.expect_punct(';')?;
+        Ok(ProgramDef {
+            name,
+            versions,
+            number,
+            span: self.since(start),
+        })
+    }
+
+    fn version(&mut self) -> Result<VersionDef, IdlError> {
+        let start = self.span();
+        if !self.is_keyword("version") {
+            return self.unexpected("`version` or `}`");
+        }
+        self.bump();
+        let name = self.ident()?;
+        self.expect_punct('{')?;
+        let mut procedures = Vec::new();
+        while !self.eat_punct('}') {
+            procedures.push(self.procedure()?);
+        }
+        if procedures.is_empty() {
+            return self.error(name.span, "a version needs at least one procedure");
+        }
+        self.expect_punct('=')?;
+        let number = self.value()?;
+        self.expect_punct(';')?;
+        Ok(VersionDef {
+            name,
+            procedures,
+            number,
+            span: self.since(start),
+        })
+    }
+
+    fn procedure(&mut self) -> Result<ProcedureDef, IdlError> {
+        let start = self.span();
+        let result = self.type_spec(true)?;
+        let name = self.ident()?;
+        self.expect_punct('(')?;
+        let mut args = vec![self.type_spec(true)?];
+        while self.eat_punct(',') {
+            args.push(self.type_spec(true)?);
+        }
+        self.expect_punct(')')?;
+        self.expect_punct('=')?;
+        let number = self.value()?;
+        self.expect_punct(';')?;
+        Ok(ProcedureDef {
+            name,
+            result,
+            args,
+            number,
+            span: self.since(start),
+        })
+    }
+}
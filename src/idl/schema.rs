@@ -0,0 +1,150 @@
+// Builds an XdrSchema from a specification, for decoding messages without generated code. Every
+// type of the specification is defined in the schema, so recursive types work as they do in Rust.
+
+use crate::idl::ast::*;
+use crate::idl::parse;
+use crate::idl::symbols::{Discriminant, Symbols};
+use crate::idl::IdlError;
+use crate::value::{XdrSchema, XdrType};
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+pub fn schema(source: &str, name: &str) -> Result<XdrSchema, IdlError> {
+    let spec = parse(source)?;
+    let symbols = Symbols::collect(source, &spec)?;
+    if !symbols.types.contains_key(name) {
+        return symbols.error(Span::default(), format!("unknown type `{}`", name));
+    }
+    let mut schema = XdrSchema::new(XdrType::Named(name.to_string()));
+    for definition in &spec.definitions {
+        if let Definition::Type(ref def) = *definition {
+            let ty = match def.body {
+                TypeDefBody::Alias(ref ty) => field_type(&symbols, ty)?,
+                TypeDefBody::Enum(ref body) => enum_type(&symbols, body),
+                TypeDefBody::Struct(ref body) => struct_type(&symbols, body)?,
+                TypeDefBody::Union(ref body) => union_type(&symbols, body)?,
+            };
+            schema = schema.define(&def.name.name, ty);
+        }
+    }
+    Ok(schema)
+}
+
+fn length(symbols: &Symbols, value: &Value) -> Result<u32, IdlError> {
+    match u32::try_from(symbols.resolve(value)?) {
+        Ok(length) => Ok(length),
+        Err(_) => symbols.error(value.span(), "lengths have to fit in an unsigned int"),
+    }
+}
+
+fn max_length(symbols: &Symbols, max: &Option<Value>) -> Result<Option<u32>, IdlError> {
+    max.as_ref().map(|max| length(symbols, max)).transpose()
+}
+
+fn field_type(symbols: &Symbols, ty: &FieldType) -> Result<XdrType, IdlError> {
+    Ok(match *ty {
+        FieldType::Plain(ref spec) => type_spec(symbols, spec)?,
+        FieldType::Optional(ref spec) => XdrType::Optional(Box::new(type_spec(symbols, spec)?)),
+        FieldType::FixedArray(ref spec, ref len) => {
+            XdrType::FixedArray(Box::new(type_spec(symbols, spec)?), length(symbols, len)?)
+        }
+        FieldType::VarArray(ref spec, ref max) => XdrType::Array(
+            Box::new(type_spec(symbols, spec)?),
+            max_length(symbols, max)?,
+        ),
+        FieldType::FixedOpaque(ref len) => XdrType::FixedOpaque(length(symbols, len)?),
+        FieldType::VarOpaque(ref max) => XdrType::Opaque(max_length(symbols, max)?),
+        FieldType::String(ref max) => XdrType::String(max_length(symbols, max)?),
+    })
+}
+
+fn type_spec(symbols: &Symbols, spec: &TypeSpec) -> Result<XdrType, IdlError> {
+    Ok(match *spec {
+        TypeSpec::Int => XdrType::Int,
+        TypeSpec::UnsignedInt => XdrType::UInt,
+        TypeSpec::Hyper => XdrType::Hyper,
+        TypeSpec::UnsignedHyper => XdrType::UHyper,
+        TypeSpec::Float => XdrType::Float,
+        TypeSpec::Double => XdrType::Double,
+        TypeSpec::Quadruple => XdrType::Quadruple,
+        TypeSpec::Bool => XdrType::Bool,
+        TypeSpec::Void => XdrType::Void,
+        TypeSpec::Enum(ref body) => enum_type(symbols, body),
+        TypeSpec::Struct(ref body) => struct_type(symbols, body)?,
+        TypeSpec::Union(ref body) => union_type(symbols, body)?,
+        TypeSpec::Named(ref ident) => {
+            if !symbols.types.contains_key(ident.name.as_str()) {
+                return symbols.error(ident.span, format!("unknown type `{}`", ident.name));
+            }
+            XdrType::Named(ident.name.clone())
+        }
+    })
+}
+
+// Values were checked to fit in an int when they were collected
+fn enum_type(symbols: &Symbols, body: &EnumBody) -> XdrType {
+    XdrType::Enum(
+        body.variants
+            .iter()
+            .map(|v| {
+                (
+                    v.name.name.clone(),
+                    symbols.consts[&v.name.name].value as i32,
+                )
+            })
+            .collect(),
+    )
+}
+
+fn struct_type(symbols: &Symbols, body: &StructBody) -> Result<XdrType, IdlError> {
+    let mut fields = Vec::with_capacity(body.fields.len());
+    for field in &body.fields {
+        fields.push((field.name.name.clone(), field_type(symbols, &field.ty)?));
+    }
+    Ok(XdrType::Struct(fields))
+}
+
+fn arm_type(symbols: &Symbols, decl: &Declaration) -> Result<XdrType, IdlError> {
+    match *decl {
+        Declaration::Void(_) => Ok(XdrType::Void),
+        Declaration::Field(ref field) => field_type(symbols, &field.ty),
+    }
+}
+
+fn union_type(symbols: &Symbols, body: &UnionBody) -> Result<XdrType, IdlError> {
+    let (spec, disc) = symbols.discriminant(&body.discriminant)?;
+    let mut arms = Vec::new();
+    let mut seen = HashSet::new();
+    for arm in &body.arms {
+        let ty = arm_type(symbols, &arm.decl)?;
+        for label in &arm.cases {
+            let value = symbols.resolve(label)?;
+            let valid = match disc {
+                Discriminant::Enum(enum_body) => enum_body
+                    .variants
+                    .iter()
+                    .any(|v| symbols.consts[&v.name.name].value == value),
+                Discriminant::Bool => value == 0 || value == 1,
+                Discriminant::Int("u32") => u32::try_from(value).is_ok(),
+                Discriminant::Int(_) => i32::try_from(value).is_ok(),
+            };
+            if !valid {
+                return symbols.error(label.span(), format!("`{}` isn't a valid case here", value));
+            }
+            if !seen.insert(value) {
+                return symbols.error(label.span(), "duplicate case in union");
+            }
+            arms.push((value, ty.clone()));
+        }
+    }
+    let default = match body.default {
+        Some(ref decl) => Some(Box::new(arm_type(symbols, decl)?)),
+        None => None,
+    };
+    Ok(XdrType::Union {
+        disc: Box::new(type_spec(symbols, spec)?),
+        arms,
+        default,
+    })
+}
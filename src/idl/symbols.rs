@@ -0,0 +1,197 @@
+// Names defined by a specification: the types, and the values of constants and enum variants.
+// Shared by everything that works from a parsed `.x` file.
+
+use crate::idl::ast::*;
+use crate::idl::IdlError;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy)]
+pub(crate) struct Constant {
+    pub value: i64,
+    // Whether it came from a `const` definition, and so has an item of its own, rather than
+    // from an enum variant
+    pub item: bool,
+}
+
+// What a union switches on
+pub(crate) enum Discriminant<'a> {
+    Int(&'static str),
+    Bool,
+    Enum(&'a EnumBody),
+}
+
+pub(crate) struct Symbols<'a> {
+    pub source: &'a str,
+    pub types: HashMap<&'a str, &'a TypeDefBody>,
+    pub consts: HashMap<String, Constant>,
+}
+
+impl<'a> Symbols<'a> {
+    pub fn error<T, M: Into<String>>(&self, span: Span, message: M) -> Result<T, IdlError> {
+        Err(IdlError::new(self.source, span, message))
+    }
+
+    // Constants and enum variants share the one namespace, as they do in C
+    pub fn define_const(&mut self, name: &Ident, value: i64, item: bool) -> Result<(), IdlError> {
+        if self.consts.contains_key(&name.name) {
+            return self.error(name.span, format!("`{}` is already defined", name.name));
+        }
+        self.consts
+            .insert(name.name.clone(), Constant { value, item });
+        Ok(())
+    }
+
+    pub fn resolve(&self, value: &Value) -> Result<i64, IdlError> {
+        match *value {
+            Value::Int(value, _) => Ok(value),
+            Value::Const(ref ident) => match (self.consts.get(&ident.name), ident.name.as_str()) {
+                (Some(constant), _) => Ok(constant.value),
+                (None, "TRUE") => Ok(1),
+                (None, "FALSE") => Ok(0),
+                (None, name) => self.error(ident.span, format!("unknown constant `{}`", name)),
+            },
+        }
+    }
+
+    // Every type name, and the value of every constant and enum variant
+    pub fn collect(source: &'a str, spec: &'a Specification) -> Result<Self, IdlError> {
+        let mut symbols = Symbols {
+            source,
+            types: HashMap::new(),
+            consts: HashMap::new(),
+        };
+        for definition in &spec.definitions {
+            match *definition {
+                Definition::Const(ref def) => {
+                    let value = symbols.resolve(&def.value)?;
+                    symbols.define_const(&def.name, value, true)?;
+                }
+                Definition::Type(ref def) => {
+                    if symbols.types.insert(&def.name.name, &def.body).is_some() {
+                        return symbols.error(
+                            def.name.span,
+                            format!("type `{}` is already defined", def.name.name),
+                        );
+                    }
+                    match def.body {
+                        TypeDefBody::Alias(ref ty) => symbols.collect_field(ty)?,
+                        TypeDefBody::Enum(ref body) => symbols.collect_enum(body)?,
+                        TypeDefBody::Struct(ref body) => symbols.collect_struct(body)?,
+                        TypeDefBody::Union(ref body) => symbols.collect_union(body)?,
+                    }
+                }
+                Definition::Program(_) | Definition::Passthrough(_) => {}
+            }
+        }
+        for definition in &spec.definitions {
+            if let Definition::Type(ref def) = *definition {
+                if symbols.alias_loops(&def.name.name) {
+                    return symbols.error(
+                        def.name.span,
+                        format!("typedef `{}` refers back to itself", def.name.name),
+                    );
+                }
+            }
+        }
+        Ok(symbols)
+    }
+
+    // `typedef a b; typedef b a;` never gets to an actual type
+    fn alias_loops(&self, mut name: &'a str) -> bool {
+        for _ in 0..=self.types.len() {
+            match self.types.get(name).copied() {
+                Some(TypeDefBody::Alias(FieldType::Plain(TypeSpec::Named(next)))) => {
+                    name = &next.name
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    fn collect_enum(&mut self, body: &EnumBody) -> Result<(), IdlError> {
+        let mut next = 0;
+        for variant in &body.variants {
+            let value = match variant.value {
+                Some(ref value) => self.resolve(value)?,
+                None => next,
+            };
+            if i32::try_from(value).is_err() {
+                return self.error(variant.span, "enum values have to fit in an int");
+            }
+            self.define_const(&variant.name, value, false)?;
+            next = value + 1;
+        }
+        Ok(())
+    }
+
+    fn collect_struct(&mut self, body: &StructBody) -> Result<(), IdlError> {
+        for field in &body.fields {
+            self.collect_field(&field.ty)?;
+        }
+        Ok(())
+    }
+
+    fn collect_union(&mut self, body: &UnionBody) -> Result<(), IdlError> {
+        self.collect_field(&body.discriminant.ty)?;
+        let arms = body.arms.iter().map(|arm| &arm.decl);
+        for decl in arms.chain(body.default.as_deref()) {
+            if let Declaration::Field(ref field) = *decl {
+                self.collect_field(&field.ty)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Inline enums define constants too
+    fn collect_field(&mut self, ty: &FieldType) -> Result<(), IdlError> {
+        match *ty {
+            FieldType::Plain(ref spec)
+            | FieldType::Optional(ref spec)
+            | FieldType::FixedArray(ref spec, _)
+            | FieldType::VarArray(ref spec, _) => match *spec {
+                TypeSpec::Enum(ref body) => self.collect_enum(body),
+                TypeSpec::Struct(ref body) => self.collect_struct(body),
+                TypeSpec::Union(ref body) => self.collect_union(body),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn discriminant(
+        &self,
+        field: &'a Field,
+    ) -> Result<(&'a TypeSpec, Discriminant<'a>), IdlError> {
+        if let FieldType::Plain(ref spec) = field.ty {
+            if let Some(disc) = self.switch_type(spec) {
+                return Ok((spec, disc));
+            }
+        }
+        self.error(
+            field.span,
+            "a union discriminant has to be an int, unsigned int, bool or enum",
+        )
+    }
+
+    fn switch_type(&self, mut spec: &'a TypeSpec) -> Option<Discriminant<'a>> {
+        // Typedefs that refer to each other in a loop never get to a discriminant type
+        for _ in 0..=self.types.len() {
+            match *spec {
+                TypeSpec::Int => return Some(Discriminant::Int("i32")),
+                TypeSpec::UnsignedInt => return Some(Discriminant::Int("u32")),
+                TypeSpec::Bool => return Some(Discriminant::Bool),
+                TypeSpec::Enum(ref body) => return Some(Discriminant::Enum(body)),
+                TypeSpec::Named(ref ident) => match self.types.get(ident.name.as_str()).copied() {
+                    Some(TypeDefBody::Enum(body)) => return Some(Discriminant::Enum(body)),
+                    Some(TypeDefBody::Alias(FieldType::Plain(inner))) => spec = inner,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        None
+    }
+}
@@ -1,15 +1,27 @@
-use serde;
-
 pub mod deserializer;
 pub mod errors;
+pub mod idl;
+pub mod pcap;
+pub mod portmap;
+pub mod record;
+pub mod rpc;
+pub mod rpcbind;
 pub mod serializer;
+pub mod trace;
+pub mod types;
+pub mod value;
+pub mod vxi11;
 
 pub use errors::{DecoderResult, EncoderError, EncoderResult};
+pub use idl::IdlError;
+pub use rpc::{RpcError, RpcResult};
 use serde::{Deserialize, Serialize};
 use std::io::Read;
+pub use vxi11::{Vxi11Error, Vxi11Result};
 
 pub use self::deserializer::Deserializer;
 pub use self::serializer::Serializer;
+pub use self::types::{Bounded, FixedArray, FixedOpaque, List, Opaque, Quadruple, Void};
 
 pub fn to_bytes<T>(value: &T, buf: &mut Vec<u8>) -> EncoderResult<()>
 where
@@ -40,33 +52,33 @@ where
 #[macro_export]
 macro_rules! xdr_enum {
     ($name:ident { $($variant:ident = $value:expr, )* }) => {
-        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-        pub enum $name<'a> {
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        pub enum $name {
             $($variant = $value,)*
         }
 
-        impl<'a> ::serde::Serialize<'a> for $name<'a> {
-            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: ::serde::Serializer {
+        impl ::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> where S: ::serde::Serializer {
                 serializer.serialize_i32(*self as i32) // All Enums are signed ints in XDR
             }
         }
 
-        impl<'a> ::serde::Deserialize<'a> for $name<'a> {
-            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: ::serde::Deserializer<'a> {
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error> where D: ::serde::Deserializer<'de> {
 
                 struct Visitor;
 
-                impl<'a> ::serde::de::Visitor<'a> for Visitor {
-                    type Value = $name<'a>;
+                impl<'de> ::serde::de::Visitor<'de> for Visitor {
+                    type Value = $name;
 
-                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
                         formatter.write_str("i32")
                     }
 
-                    fn visit_i32<E>(self, value: i32) -> Result<$name<'a>, E> where E: ::serde::de::Error {
+                    fn visit_i32<E>(self, value: i32) -> ::std::result::Result<$name, E> where E: ::serde::de::Error {
                         match value {
-                            $( $value => Ok($name::$variant), )*
-                            _ => Err(E::custom(
+                            $( v if v == $value => ::std::result::Result::Ok($name::$variant), )*
+                            _ => ::std::result::Result::Err(E::custom(
                                 format!("unknown {} value: {}",
                                 stringify!($name), value))),
                         }
@@ -0,0 +1,231 @@
+// Capture file formats: classic pcap, in either byte order with micro or nanosecond timestamps,
+// and pcapng's enhanced, simple and obsolete packet blocks.
+
+use std::convert::TryInto;
+use std::io;
+use std::time::Duration;
+
+const PCAP_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_NANOS: u32 = 0xA1B2_3C4D;
+const PCAPNG_SECTION: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER: u32 = 0x1A2B_3C4D;
+
+const BLOCK_INTERFACE: u32 = 1;
+const BLOCK_PACKET: u32 = 2;
+const BLOCK_SIMPLE_PACKET: u32 = 3;
+const BLOCK_ENHANCED_PACKET: u32 = 6;
+const OPTION_TSRESOL: u16 = 9;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Packet {
+    // Since the Unix epoch
+    pub timestamp: Duration,
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Clone, Copy)]
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid("capture file is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+}
+
+// Every packet in the capture, in file order
+pub fn read_packets(capture: &[u8]) -> io::Result<Vec<Packet>> {
+    if capture.len() < 4 {
+        return Err(invalid("not a pcap or pcapng file"));
+    }
+    let magic = u32::from_be_bytes(capture[..4].try_into().unwrap());
+    match magic {
+        PCAPNG_SECTION => read_pcapng(capture),
+        _ if [PCAP_MICROS, PCAP_NANOS].contains(&magic) => read_pcap(capture, true),
+        _ if [PCAP_MICROS, PCAP_NANOS].contains(&magic.swap_bytes()) => read_pcap(capture, false),
+        _ => Err(invalid("not a pcap or pcapng file")),
+    }
+}
+
+fn read_pcap(capture: &[u8], big_endian: bool) -> io::Result<Vec<Packet>> {
+    let mut cursor = Cursor {
+        bytes: capture,
+        big_endian,
+    };
+    let nanos = cursor.u32()? == PCAP_NANOS;
+    // Version, time zone, sigfigs and snaplen
+    cursor.take(16)?;
+    let link_type = cursor.u32()?;
+
+    // A capture that was cut off partway through its last packet, as happens when the capturing
+    // process is killed, still gives the packets before it
+    let mut packets = Vec::new();
+    while cursor.bytes.len() >= 16 {
+        let seconds = cursor.u32()?;
+        let fraction = cursor.u32()?;
+        let captured = cursor.u32()? as usize;
+        cursor.u32()?;
+        if captured > cursor.bytes.len() {
+            break;
+        }
+        let fraction = if nanos {
+            Duration::from_nanos(u64::from(fraction))
+        } else {
+            Duration::from_micros(u64::from(fraction))
+        };
+        packets.push(Packet {
+            timestamp: Duration::from_secs(u64::from(seconds)) + fraction,
+            link_type,
+            data: cursor.take(captured)?.to_vec(),
+        });
+    }
+    Ok(packets)
+}
+
+// Link type and timestamp units per second for each interface in the current section
+struct Interface {
+    link_type: u32,
+    resolution: u64,
+}
+
+fn read_pcapng(capture: &[u8]) -> io::Result<Vec<Packet>> {
+    let mut rest = capture;
+    let mut big_endian = true;
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut packets = Vec::new();
+
+    while !rest.is_empty() {
+        let mut cursor = Cursor {
+            bytes: rest,
+            big_endian,
+        };
+        let block_type = cursor.u32()?;
+        if block_type == PCAPNG_SECTION {
+            // The byte order magic comes after the length, so it's checked before trusting it
+            let magic = rest
+                .get(8..12)
+                .ok_or_else(|| invalid("capture file is truncated"))?;
+            big_endian = u32::from_be_bytes(magic.try_into().unwrap()) == PCAPNG_BYTE_ORDER;
+            cursor.big_endian = big_endian;
+            interfaces.clear();
+        }
+        let len = cursor.u32()? as usize;
+        if len < 12 || len & 3 != 0 {
+            return Err(invalid("bad pcapng block length"));
+        }
+        // Cut off partway through the last block, like a classic pcap cut off mid-packet
+        if len > rest.len() {
+            break;
+        }
+        let mut body = Cursor {
+            bytes: &rest[8..len - 4],
+            big_endian,
+        };
+        rest = &rest[len..];
+
+        match block_type {
+            BLOCK_INTERFACE => {
+                let link_type = u32::from(body.u16()?);
+                body.take(6)?;
+                interfaces.push(Interface {
+                    link_type,
+                    resolution: tsresol(body)?,
+                });
+            }
+            BLOCK_ENHANCED_PACKET | BLOCK_PACKET => {
+                let interface = if block_type == BLOCK_PACKET {
+                    let interface = body.u16()?;
+                    body.u16()?;
+                    u32::from(interface)
+                } else {
+                    body.u32()?
+                };
+                let high = body.u32()?;
+                let low = body.u32()?;
+                let captured = body.u32()? as usize;
+                body.u32()?;
+                let interface = interfaces
+                    .get(interface as usize)
+                    .ok_or_else(|| invalid("packet block names an undeclared interface"))?;
+                let units = u64::from(high) << 32 | u64::from(low);
+                packets.push(Packet {
+                    timestamp: timestamp(units, interface.resolution),
+                    link_type: interface.link_type,
+                    data: body.take(captured)?.to_vec(),
+                });
+            }
+            BLOCK_SIMPLE_PACKET => {
+                let interface = interfaces
+                    .first()
+                    .ok_or_else(|| invalid("packet block names an undeclared interface"))?;
+                let original = body.u32()? as usize;
+                let captured = original.min(body.bytes.len());
+                packets.push(Packet {
+                    timestamp: Duration::default(),
+                    link_type: interface.link_type,
+                    data: body.take(captured)?.to_vec(),
+                });
+            }
+            // Section headers, name resolution, statistics and anything newer
+            _ => {}
+        }
+    }
+    Ok(packets)
+}
+
+// The if_tsresol option: a power of ten, or of two if the top bit is set. Microseconds otherwise.
+fn tsresol(mut options: Cursor) -> io::Result<u64> {
+    while options.bytes.len() >= 4 {
+        let code = options.u16()?;
+        let len = options.u16()? as usize;
+        let value = options.take(len)?;
+        options.take((4 - len % 4) % 4)?;
+        match (code, value) {
+            (0, _) => break,
+            (OPTION_TSRESOL, &[resol]) => {
+                let exponent = u32::from(resol & 0x7F);
+                let base: u64 = if resol & 0x80 != 0 { 2 } else { 10 };
+                return base
+                    .checked_pow(exponent)
+                    .ok_or_else(|| invalid("unsupported timestamp resolution"));
+            }
+            _ => {}
+        }
+    }
+    Ok(1_000_000)
+}
+
+fn timestamp(units: u64, per_second: u64) -> Duration {
+    let nanos = u128::from(units % per_second) * 1_000_000_000 / u128::from(per_second);
+    Duration::new(units / per_second, nanos as u32)
+}
@@ -0,0 +1,275 @@
+// ONC RPC traffic pulled out of capture files, for looking at what a client and instrument
+// actually said to each other:
+//
+//     for transaction in pcap::read_file("field.pcapng")? {
+//         print!("{}", transaction);
+//     }
+//
+// TCP streams are reassembled and split on record marks, UDP datagrams are taken a message each,
+// and calls are paired with their replies by xid. Arguments and results of portmap and VXI-11
+// procedures are decoded into this crate's types; anything else is kept as raw bytes.
+
+mod file;
+mod net;
+mod programs;
+mod stream;
+
+pub use self::file::{read_packets, Packet};
+pub use self::net::Protocol;
+pub use self::programs::Payload;
+
+use self::net::{TCP_RST, TCP_SYN};
+use self::stream::Stream;
+use crate::rpc::{CallBody, MsgBody, ReplyBody, ReplyData, RpcMsg, RPC_VERSION};
+use crate::Deserializer;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CapturedCall {
+    // Since the first packet in the capture
+    pub time: Duration,
+    pub header: CallBody,
+    pub args: Payload,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CapturedReply {
+    pub time: Duration,
+    pub header: ReplyBody,
+    // Only successful replies carry results
+    pub results: Option<Payload>,
+}
+
+// A call and its reply. Either can be missing when the capture starts or stops between them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Transaction {
+    pub protocol: Protocol,
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub xid: u32,
+    pub call: Option<CapturedCall>,
+    pub reply: Option<CapturedReply>,
+}
+
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<Transaction>> {
+    transactions(&fs::read(path)?)
+}
+
+// Transactions in the order their first message was captured
+pub fn transactions(capture: &[u8]) -> io::Result<Vec<Transaction>> {
+    let packets = read_packets(capture)?;
+    let start = packets.first().map(|p| p.timestamp).unwrap_or_default();
+    let mut pairing = Pairing::default();
+    let mut streams: HashMap<(SocketAddr, SocketAddr), Stream> = HashMap::new();
+
+    for packet in &packets {
+        let segment = match net::segment(packet.link_type, &packet.data) {
+            Some(segment) => segment,
+            None => continue,
+        };
+        // Captures aren't always in timestamp order
+        let time = packet.timestamp.checked_sub(start).unwrap_or_default();
+        let from = (segment.protocol, segment.src, segment.dst);
+        match segment.protocol {
+            Protocol::Udp => pairing.message(from, time, segment.payload),
+            Protocol::Tcp => {
+                let key = (segment.src, segment.dst);
+                let syn = segment.flags & TCP_SYN != 0;
+                if syn || segment.flags & TCP_RST != 0 {
+                    streams.remove(&key);
+                }
+                let records = streams.entry(key).or_default().segment(
+                    segment.seq,
+                    syn,
+                    segment.payload,
+                    time,
+                );
+                for (time, record) in records {
+                    pairing.message(from, time, &record);
+                }
+            }
+        }
+    }
+    Ok(pairing.transactions)
+}
+
+#[derive(Default)]
+struct Pairing {
+    transactions: Vec<Transaction>,
+    // Calls still waiting for a reply, by protocol, client, server and xid
+    waiting: HashMap<(Protocol, SocketAddr, SocketAddr, u32), usize>,
+}
+
+impl Pairing {
+    // Anything that doesn't parse as an RPC message is ignored, since every TCP and UDP payload
+    // in the capture comes through here
+    fn message(&mut self, from: (Protocol, SocketAddr, SocketAddr), time: Duration, bytes: &[u8]) {
+        let (protocol, src, dst) = from;
+        let mut de = Deserializer::new(bytes);
+        let msg = match RpcMsg::deserialize(&mut de) {
+            Ok(msg) => msg,
+            Err(_) => return,
+        };
+        let body = &bytes[de.get_bytes_consumed()..];
+
+        match msg.body {
+            MsgBody::Call(header) if header.rpcvers == RPC_VERSION => {
+                let key = (protocol, src, dst, msg.xid);
+                // A retransmitted call is still the same transaction
+                if self.waiting.contains_key(&key) {
+                    return;
+                }
+                let args = programs::args(header.prog, header.vers, header.proc, body);
+                self.waiting.insert(key, self.transactions.len());
+                self.transactions.push(Transaction {
+                    protocol,
+                    client: src,
+                    server: dst,
+                    xid: msg.xid,
+                    call: Some(CapturedCall { time, header, args }),
+                    reply: None,
+                });
+            }
+            MsgBody::Reply(header) => {
+                let waiting = self.waiting.remove(&(protocol, dst, src, msg.xid));
+                let call = waiting.and_then(|i| self.transactions[i].call.as_ref());
+                let results = match header {
+                    ReplyBody::Accepted(ref accepted)
+                        if accepted.reply_data == ReplyData::Success =>
+                    {
+                        Some(match call {
+                            Some(call) => programs::results(
+                                call.header.prog,
+                                call.header.vers,
+                                call.header.proc,
+                                body,
+                            ),
+                            None => Payload::Undecoded(body.to_vec()),
+                        })
+                    }
+                    _ => None,
+                };
+                let reply = Some(CapturedReply {
+                    time,
+                    header,
+                    results,
+                });
+                match waiting {
+                    Some(i) => self.transactions[i].reply = reply,
+                    None => self.transactions.push(Transaction {
+                        protocol,
+                        client: dst,
+                        server: src,
+                        xid: msg.xid,
+                        call: None,
+                        reply,
+                    }),
+                }
+            }
+            MsgBody::Call(_) => {}
+        }
+    }
+}
+
+// Payloads past this are cut short in the printed form
+const MAX_PRINTED_BYTES: usize = 64;
+
+fn hex(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    let words: Vec<String> = bytes[..bytes.len().min(MAX_PRINTED_BYTES)]
+        .chunks(4)
+        .map(|word| word.iter().map(|b| format!("{:02x}", b)).collect())
+        .collect();
+    write!(f, "{}", words.join(" "))?;
+    if bytes.len() > MAX_PRINTED_BYTES {
+        write!(f, " ...")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            // Continuation lines of the pretty-printed value line up under the label
+            Payload::Decoded(ref value) => write!(f, "{}", value.replace('\n', "\n  ")),
+            Payload::Undecoded(ref bytes) => {
+                write!(f, "{} bytes: ", bytes.len())?;
+                hex(f, bytes)
+            }
+            Payload::Failed {
+                ref error,
+                ref bytes,
+            } => {
+                write!(f, "undecodable ({}), {} bytes: ", error, bytes.len())?;
+                hex(f, bytes)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let protocol = match self.protocol {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        };
+        let time = self
+            .call
+            .as_ref()
+            .map(|call| call.time)
+            .or_else(|| self.reply.as_ref().map(|reply| reply.time))
+            .unwrap_or_default();
+        write!(
+            f,
+            "{:.6} {} {} -> {} xid {:#010x}",
+            time.as_secs_f64(),
+            protocol,
+            self.client,
+            self.server,
+            self.xid
+        )?;
+
+        match self.call {
+            Some(ref call) => {
+                let CallBody {
+                    prog, vers, proc, ..
+                } = call.header;
+                match programs::program_name(prog) {
+                    Some(name) => write!(f, " {} v{}", name, vers)?,
+                    None => write!(f, " prog {} v{}", prog, vers)?,
+                }
+                match programs::procedure_name(prog, vers, proc) {
+                    Some(name) => writeln!(f, " {}", name)?,
+                    None => writeln!(f, " proc {}", proc)?,
+                }
+                writeln!(f, "  call: {}", call.args)?;
+            }
+            None => writeln!(f, " (call not captured)")?,
+        }
+
+        match self.reply {
+            Some(ref reply) => {
+                write!(f, "  reply")?;
+                if let Some(ref call) = self.call {
+                    let elapsed = reply.time.checked_sub(call.time).unwrap_or_default();
+                    write!(f, " +{:.6}", elapsed.as_secs_f64())?;
+                }
+                match (&reply.header, &reply.results) {
+                    (_, Some(results)) => writeln!(f, ": {}", results),
+                    (ReplyBody::Accepted(accepted), None) => {
+                        writeln!(f, ": {:?}", accepted.reply_data)
+                    }
+                    (ReplyBody::Denied(rejected), None) => writeln!(f, ": denied, {:?}", rejected),
+                }
+            }
+            None => writeln!(f, "  (no reply captured)"),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// Just enough of the link, IP and transport headers to find TCP and UDP payloads. Checksums
+// aren't verified, since captures taken on the sending host usually have them offloaded.
+
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88A8;
+
+const PROTO_TCP: u8 = 6;
+const PROTO_UDP: u8 = 17;
+
+pub(crate) const TCP_SYN: u8 = 0x02;
+pub(crate) const TCP_RST: u8 = 0x04;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+pub(crate) struct Segment<'a> {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub protocol: Protocol,
+    // TCP only
+    pub seq: u32,
+    pub flags: u8,
+    pub payload: &'a [u8],
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+// None for anything that isn't a whole TCP segment or UDP datagram
+pub(crate) fn segment(link_type: u32, frame: &[u8]) -> Option<Segment<'_>> {
+    let ip = match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut at = 12;
+            let mut ethertype = u16_at(frame, at)?;
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                at += 4;
+                ethertype = u16_at(frame, at)?;
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => frame.get(at + 2..)?,
+                _ => return None,
+            }
+        }
+        LINKTYPE_LINUX_SLL => frame.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => frame.get(20..)?,
+        // The address family is in the capturing host's byte order, but the IP version nibble
+        // says the same thing
+        LINKTYPE_NULL => frame.get(4..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => frame,
+        _ => return None,
+    };
+
+    let (src, dst, protocol, payload) = match ip.first()? >> 4 {
+        4 => ipv4(ip)?,
+        6 => ipv6(ip)?,
+        _ => return None,
+    };
+    let (src_port, dst_port) = (u16_at(payload, 0)?, u16_at(payload, 2)?);
+    let (protocol, seq, flags, payload) = match protocol {
+        PROTO_TCP => {
+            let offset = usize::from(payload.get(12)? >> 4) * 4;
+            let flags = *payload.get(13)?;
+            (
+                Protocol::Tcp,
+                u32_at(payload, 4)?,
+                flags,
+                payload.get(offset..)?,
+            )
+        }
+        PROTO_UDP => {
+            let len = usize::from(u16_at(payload, 4)?);
+            (Protocol::Udp, 0, 0, payload.get(8..len)?)
+        }
+        _ => return None,
+    };
+    Some(Segment {
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        protocol,
+        seq,
+        flags,
+        payload,
+    })
+}
+
+// Fragments are passed over; RPC over UDP rarely needs them and TCP never should
+fn ipv4(ip: &[u8]) -> Option<(IpAddr, IpAddr, u8, &[u8])> {
+    let header = usize::from(ip.first()? & 0x0F) * 4;
+    let total = usize::from(u16_at(ip, 2)?);
+    let fragment = u16_at(ip, 6)?;
+    if fragment & 0x3FFF != 0 {
+        return None;
+    }
+    let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+    let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+    // Ethernet pads short frames, so the payload ends where the IP header says it does
+    Some((
+        IpAddr::V4(Ipv4Addr::from(src)),
+        IpAddr::V4(Ipv4Addr::from(dst)),
+        *ip.get(9)?,
+        ip.get(header..total)?,
+    ))
+}
+
+fn ipv6(ip: &[u8]) -> Option<(IpAddr, IpAddr, u8, &[u8])> {
+    let total = 40 + usize::from(u16_at(ip, 4)?);
+    let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+    let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+    let mut next = *ip.get(6)?;
+    let mut at = 40;
+    // Hop-by-hop, routing and destination options headers are skipped; fragments aren't handled
+    while let 0 | 43 | 60 = next {
+        next = *ip.get(at)?;
+        at += (usize::from(*ip.get(at + 1)?) + 1) * 8;
+    }
+    Some((
+        IpAddr::V6(Ipv6Addr::from(src)),
+        IpAddr::V6(Ipv6Addr::from(dst)),
+        next,
+        ip.get(at..total)?,
+    ))
+}
@@ -0,0 +1,144 @@
+// Argument and result types of the programs this crate knows, so captured calls can be decoded
+// into the same types the clients and servers use.
+
+use crate::portmap::*;
+use crate::vxi11::*;
+use crate::Deserializer;
+
+use serde::Deserialize;
+use std::fmt::Debug;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Payload {
+    // The `{:#?}` of the decoded value
+    Decoded(String),
+    // A program or procedure that isn't known here
+    Undecoded(Vec<u8>),
+    Failed { error: String, bytes: Vec<u8> },
+}
+
+fn decode<'a, T>(bytes: &'a [u8]) -> Payload
+where
+    T: Deserialize<'a> + Debug,
+{
+    let mut de = Deserializer::new(bytes);
+    let error = match T::deserialize(&mut de) {
+        Ok(value) if de.get_bytes_consumed() == bytes.len() => {
+            return Payload::Decoded(format!("{:#?}", value))
+        }
+        Ok(_) => format!("{} trailing bytes", bytes.len() - de.get_bytes_consumed()),
+        Err(err) => err.to_string(),
+    };
+    Payload::Failed {
+        error,
+        bytes: bytes.to_vec(),
+    }
+}
+
+pub(crate) fn program_name(prog: u32) -> Option<&'static str> {
+    match prog {
+        PMAP_PROG => Some("portmap"),
+        DEVICE_CORE => Some("vxi11_core"),
+        DEVICE_ASYNC => Some("vxi11_async"),
+        DEVICE_INTR => Some("vxi11_intr"),
+        _ => None,
+    }
+}
+
+pub(crate) fn procedure_name(prog: u32, vers: u32, proc: u32) -> Option<&'static str> {
+    let name = match (prog, vers, proc) {
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_NULL) => "null",
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_SET) => "set",
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_UNSET) => "unset",
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_GETPORT) => "getport",
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_DUMP) => "dump",
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_CALLIT) => "callit",
+        (DEVICE_CORE, DEVICE_CORE_VERSION, proc) => match proc {
+            0 => "null",
+            CREATE_LINK => "create_link",
+            DEVICE_WRITE => "device_write",
+            DEVICE_READ => "device_read",
+            DEVICE_READSTB => "device_readstb",
+            DEVICE_TRIGGER => "device_trigger",
+            DEVICE_CLEAR => "device_clear",
+            DEVICE_REMOTE => "device_remote",
+            DEVICE_LOCAL => "device_local",
+            DEVICE_LOCK => "device_lock",
+            DEVICE_UNLOCK => "device_unlock",
+            DEVICE_ENABLE_SRQ => "device_enable_srq",
+            DEVICE_DOCMD => "device_docmd",
+            DESTROY_LINK => "destroy_link",
+            CREATE_INTR_CHAN => "create_intr_chan",
+            DESTROY_INTR_CHAN => "destroy_intr_chan",
+            _ => return None,
+        },
+        (DEVICE_ASYNC, DEVICE_ASYNC_VERSION, 0) | (DEVICE_INTR, DEVICE_INTR_VERSION, 0) => "null",
+        (DEVICE_ASYNC, DEVICE_ASYNC_VERSION, DEVICE_ABORT) => "device_abort",
+        (DEVICE_INTR, DEVICE_INTR_VERSION, DEVICE_INTR_SRQ) => "device_intr_srq",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub(crate) fn args(prog: u32, vers: u32, proc: u32, bytes: &[u8]) -> Payload {
+    match (prog, vers, proc) {
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_NULL) | (PMAP_PROG, PMAP_VERS, PMAPPROC_DUMP) => {
+            decode::<()>(bytes)
+        }
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_SET)
+        | (PMAP_PROG, PMAP_VERS, PMAPPROC_UNSET)
+        | (PMAP_PROG, PMAP_VERS, PMAPPROC_GETPORT) => decode::<Mapping>(bytes),
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_CALLIT) => decode::<CallArgs>(bytes),
+        (DEVICE_CORE, DEVICE_CORE_VERSION, proc) => match proc {
+            0 | DESTROY_INTR_CHAN => decode::<()>(bytes),
+            CREATE_LINK => decode::<CreateLinkParms>(bytes),
+            DEVICE_WRITE => decode::<DeviceWriteParms>(bytes),
+            DEVICE_READ => decode::<DeviceReadParms>(bytes),
+            DEVICE_READSTB | DEVICE_TRIGGER | DEVICE_CLEAR | DEVICE_REMOTE | DEVICE_LOCAL => {
+                decode::<DeviceGenericParms>(bytes)
+            }
+            DEVICE_LOCK => decode::<DeviceLockParms>(bytes),
+            DEVICE_UNLOCK | DESTROY_LINK => decode::<DeviceLink>(bytes),
+            DEVICE_ENABLE_SRQ => decode::<DeviceEnableSrqParms>(bytes),
+            DEVICE_DOCMD => decode::<DeviceDocmdParms>(bytes),
+            CREATE_INTR_CHAN => decode::<DeviceRemoteFunc>(bytes),
+            _ => Payload::Undecoded(bytes.to_vec()),
+        },
+        (DEVICE_ASYNC, DEVICE_ASYNC_VERSION, 0) | (DEVICE_INTR, DEVICE_INTR_VERSION, 0) => {
+            decode::<()>(bytes)
+        }
+        (DEVICE_ASYNC, DEVICE_ASYNC_VERSION, DEVICE_ABORT) => decode::<DeviceLink>(bytes),
+        (DEVICE_INTR, DEVICE_INTR_VERSION, DEVICE_INTR_SRQ) => decode::<DeviceSrqParms>(bytes),
+        _ => Payload::Undecoded(bytes.to_vec()),
+    }
+}
+
+pub(crate) fn results(prog: u32, vers: u32, proc: u32, bytes: &[u8]) -> Payload {
+    match (prog, vers, proc) {
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_NULL) => decode::<()>(bytes),
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_SET) | (PMAP_PROG, PMAP_VERS, PMAPPROC_UNSET) => {
+            decode::<bool>(bytes)
+        }
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_GETPORT) => decode::<u32>(bytes),
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_DUMP) => decode::<PmapList>(bytes),
+        (PMAP_PROG, PMAP_VERS, PMAPPROC_CALLIT) => decode::<CallResult>(bytes),
+        (DEVICE_CORE, DEVICE_CORE_VERSION, proc) => match proc {
+            0 => decode::<()>(bytes),
+            CREATE_LINK => decode::<CreateLinkResp>(bytes),
+            DEVICE_WRITE => decode::<DeviceWriteResp>(bytes),
+            DEVICE_READ => decode::<DeviceReadResp>(bytes),
+            DEVICE_READSTB => decode::<DeviceReadStbResp>(bytes),
+            DEVICE_DOCMD => decode::<DeviceDocmdResp>(bytes),
+            DEVICE_TRIGGER | DEVICE_CLEAR | DEVICE_REMOTE | DEVICE_LOCAL | DEVICE_LOCK
+            | DEVICE_UNLOCK | DEVICE_ENABLE_SRQ | DESTROY_LINK | CREATE_INTR_CHAN
+            | DESTROY_INTR_CHAN => decode::<DeviceError>(bytes),
+            _ => Payload::Undecoded(bytes.to_vec()),
+        },
+        (DEVICE_ASYNC, DEVICE_ASYNC_VERSION, 0) | (DEVICE_INTR, DEVICE_INTR_VERSION, 0) => {
+            decode::<()>(bytes)
+        }
+        (DEVICE_ASYNC, DEVICE_ASYNC_VERSION, DEVICE_ABORT) => decode::<DeviceError>(bytes),
+        (DEVICE_INTR, DEVICE_INTR_VERSION, DEVICE_INTR_SRQ) => decode::<()>(bytes),
+        _ => Payload::Undecoded(bytes.to_vec()),
+    }
+}
@@ -0,0 +1,93 @@
+// One direction of a TCP connection, put back in order and split into RPC records.
+//
+// Retransmitted and overlapping data is trimmed against what's already been delivered, and data
+// that arrives early waits until the gap before it is filled. A gap that's never filled (a
+// segment the capture dropped) stalls the direction, since there's no telling where the next
+// record mark is after it.
+
+use crate::record::{parse_fragment_header, DEFAULT_MAX_RECORD_SIZE};
+
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub(crate) struct Stream {
+    // Unknown until the SYN or, for connections already open when the capture started, the
+    // first segment seen
+    next_seq: Option<u32>,
+    early: Vec<(u32, Vec<u8>)>,
+    buf: Vec<u8>,
+    record: Vec<u8>,
+    // Set when the record marks stop making sense, after which the direction is ignored
+    lost: bool,
+}
+
+impl Stream {
+    // Records completed by this segment, each with the time of the segment that finished it
+    pub(crate) fn segment(
+        &mut self,
+        seq: u32,
+        syn: bool,
+        payload: &[u8],
+        time: Duration,
+    ) -> Vec<(Duration, Vec<u8>)> {
+        if syn {
+            self.next_seq = Some(seq.wrapping_add(1));
+            return Vec::new();
+        }
+        if payload.is_empty() || self.lost {
+            return Vec::new();
+        }
+        let next = *self.next_seq.get_or_insert(seq);
+        // Sequence numbers wrap, so they're compared by their distance from the next one expected
+        let ahead = seq.wrapping_sub(next) as i32;
+        if ahead > 0 {
+            self.early.push((seq, payload.to_vec()));
+            return Vec::new();
+        }
+        self.deliver(seq, payload);
+        while let Some(i) = self
+            .early
+            .iter()
+            .position(|(seq, _)| seq.wrapping_sub(self.next_seq.unwrap()) as i32 <= 0)
+        {
+            let (seq, payload) = self.early.swap_remove(i);
+            self.deliver(seq, &payload);
+        }
+        self.records(time)
+    }
+
+    fn deliver(&mut self, seq: u32, payload: &[u8]) {
+        let next = self.next_seq.unwrap();
+        let seen = next.wrapping_sub(seq) as usize;
+        if seen < payload.len() {
+            self.buf.extend_from_slice(&payload[seen..]);
+            self.next_seq = Some(seq.wrapping_add(payload.len() as u32));
+        }
+    }
+
+    fn records(&mut self, time: Duration) -> Vec<(Duration, Vec<u8>)> {
+        let mut records = Vec::new();
+        let mut at = 0;
+        while let Some(header) = self.buf.get(at..at + 4) {
+            let (len, last) = parse_fragment_header(u32::from_be_bytes([
+                header[0], header[1], header[2], header[3],
+            ]));
+            if self.record.len() + len > DEFAULT_MAX_RECORD_SIZE {
+                self.lost = true;
+                self.buf.clear();
+                return records;
+            }
+            let fragment = match self.buf.get(at + 4..at + 4 + len) {
+                Some(fragment) => fragment,
+                None => break,
+            };
+            self.record.extend_from_slice(fragment);
+            at += 4 + len;
+            if last {
+                records.push((time, std::mem::take(&mut self.record)));
+            }
+        }
+        self.buf.drain(..at);
+        records
+    }
+}
@@ -0,0 +1,222 @@
+// Port mapper program, version 2 (RFC 1833 section 3), with a client and a small in-process server.
+
+use crate::rpc::{Call, ProcError, RpcClient, RpcProgram, RpcResult, RpcServer, ServerHandle};
+use crate::{List, Opaque};
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+
+pub const PMAP_PORT: u16 = 111;
+pub const PMAP_PROG: u32 = 100000;
+pub const PMAP_VERS: u32 = 2;
+
+pub const IPPROTO_TCP: u32 = 6;
+pub const IPPROTO_UDP: u32 = 17;
+
+pub const PMAPPROC_NULL: u32 = 0;
+pub const PMAPPROC_SET: u32 = 1;
+pub const PMAPPROC_UNSET: u32 = 2;
+pub const PMAPPROC_GETPORT: u32 = 3;
+pub const PMAPPROC_DUMP: u32 = 4;
+pub const PMAPPROC_CALLIT: u32 = 5;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Mapping {
+    pub prog: u32,
+    pub vers: u32,
+    pub prot: u32,
+    pub port: u32,
+}
+
+// `pmaplist *`, the linked list returned by PMAPPROC_DUMP
+pub type PmapList = List<Mapping>;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CallArgs {
+    pub prog: u32,
+    pub vers: u32,
+    pub proc: u32,
+    pub args: Opaque,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CallResult {
+    pub port: u32,
+    pub res: Opaque,
+}
+
+pub struct PortmapClient {
+    client: RpcClient,
+}
+
+impl PortmapClient {
+    pub fn new(client: RpcClient) -> Self {
+        PortmapClient { client }
+    }
+
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        RpcClient::connect_tcp(addr).map(PortmapClient::new)
+    }
+
+    pub fn connect_udp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        RpcClient::connect_udp(addr).map(PortmapClient::new)
+    }
+
+    pub fn null(&mut self) -> RpcResult<()> {
+        self.client.call(PMAP_PROG, PMAP_VERS, PMAPPROC_NULL, &())
+    }
+
+    pub fn set(&mut self, mapping: &Mapping) -> RpcResult<bool> {
+        self.client
+            .call(PMAP_PROG, PMAP_VERS, PMAPPROC_SET, mapping)
+    }
+
+    pub fn unset(&mut self, mapping: &Mapping) -> RpcResult<bool> {
+        self.client
+            .call(PMAP_PROG, PMAP_VERS, PMAPPROC_UNSET, mapping)
+    }
+
+    // Returns 0 when the program isn't registered
+    pub fn getport(&mut self, prog: u32, vers: u32, prot: u32) -> RpcResult<u16> {
+        let mapping = Mapping {
+            prog,
+            vers,
+            prot,
+            port: 0,
+        };
+        let port: u32 = self
+            .client
+            .call(PMAP_PROG, PMAP_VERS, PMAPPROC_GETPORT, &mapping)?;
+        Ok(port as u16)
+    }
+
+    pub fn dump(&mut self) -> RpcResult<Vec<Mapping>> {
+        let list: PmapList = self.client.call(PMAP_PROG, PMAP_VERS, PMAPPROC_DUMP, &())?;
+        Ok(list.0)
+    }
+
+    pub fn callit(&mut self, args: &CallArgs) -> RpcResult<CallResult> {
+        self.client
+            .call(PMAP_PROG, PMAP_VERS, PMAPPROC_CALLIT, args)
+    }
+}
+
+// Asks the port mapper on `host` which port `prog`/`vers` is listening on for `prot`
+pub fn getport(host: &str, prog: u32, vers: u32, prot: u32) -> RpcResult<u16> {
+    PortmapClient::connect_tcp((host, PMAP_PORT))?.getport(prog, vers, prot)
+}
+
+// An in-process port mapper. Clones share the same table, so mappings can be added
+// directly while the server is running.
+#[derive(Clone, Debug, Default)]
+pub struct Portmapper {
+    mappings: Arc<Mutex<Vec<Mapping>>>,
+}
+
+impl Portmapper {
+    pub fn new() -> Self {
+        Portmapper::default()
+    }
+
+    // Refuses to replace an existing mapping for the same program, version and protocol
+    pub fn set(&self, mapping: Mapping) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        if mappings
+            .iter()
+            .any(|m| m.prog == mapping.prog && m.vers == mapping.vers && m.prot == mapping.prot)
+        {
+            return false;
+        }
+        mappings.push(mapping);
+        true
+    }
+
+    // Removes every protocol's mapping for the program and version
+    pub fn unset(&self, prog: u32, vers: u32) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        let before = mappings.len();
+        mappings.retain(|m| !(m.prog == prog && m.vers == vers));
+        mappings.len() != before
+    }
+
+    pub fn getport(&self, prog: u32, vers: u32, prot: u32) -> u32 {
+        self.mappings
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|m| m.prog == prog && m.vers == vers && m.prot == prot)
+            .map(|m| m.port)
+            .unwrap_or(0)
+    }
+
+    pub fn mappings(&self) -> Vec<Mapping> {
+        self.mappings.lock().unwrap().clone()
+    }
+
+    // Serves over TCP and UDP on the same port, which can be 0 to pick a free one
+    pub fn spawn<A: ToSocketAddrs>(&self, addr: A) -> io::Result<PortmapperHandle> {
+        let mut server = RpcServer::new();
+        server.register(self.clone());
+        let tcp = server.spawn_tcp(addr)?;
+        let udp = server.spawn_udp(tcp.local_addr())?;
+        let port = tcp.local_addr().port() as u32;
+        for &prot in &[IPPROTO_TCP, IPPROTO_UDP] {
+            self.set(Mapping {
+                prog: PMAP_PROG,
+                vers: PMAP_VERS,
+                prot,
+                port,
+            });
+        }
+        Ok(PortmapperHandle { tcp, udp })
+    }
+}
+
+impl RpcProgram for Portmapper {
+    fn program(&self) -> u32 {
+        PMAP_PROG
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (PMAP_VERS, PMAP_VERS)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        match call.proc() {
+            PMAPPROC_NULL => Ok(()),
+            PMAPPROC_SET => {
+                let mapping: Mapping = call.args()?;
+                call.reply(&self.set(mapping))
+            }
+            PMAPPROC_UNSET => {
+                let mapping: Mapping = call.args()?;
+                call.reply(&self.unset(mapping.prog, mapping.vers))
+            }
+            PMAPPROC_GETPORT => {
+                let mapping: Mapping = call.args()?;
+                call.reply(&self.getport(mapping.prog, mapping.vers, mapping.prot))
+            }
+            PMAPPROC_DUMP => call.reply(&List(self.mappings())),
+            // Forwarding calls isn't supported by the embedded port mapper
+            _ => Err(ProcError::ProcUnavail),
+        }
+    }
+}
+
+pub struct PortmapperHandle {
+    tcp: ServerHandle,
+    udp: ServerHandle,
+}
+
+impl PortmapperHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.tcp.local_addr()
+    }
+
+    pub fn shutdown(self) {
+        self.tcp.shutdown();
+        self.udp.shutdown();
+    }
+}
@@ -0,0 +1,323 @@
+// ONC RPC record marking (RFC 5531 section 11) for stream transports like TCP.
+//
+// Each record goes out as one or more fragments, every fragment prefixed by a 4 byte big-endian
+// header whose top bit flags the last fragment and whose low 31 bits give the fragment length.
+
+use crate::errors::{DecoderResult, EncoderResult};
+use crate::{Deserializer, Serializer};
+
+use byteorder::{BigEndian, WriteBytesExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+pub const LAST_FRAGMENT: u32 = 0x8000_0000;
+pub const MAX_FRAGMENT_SIZE: usize = 0x7FFF_FFFF;
+pub const DEFAULT_MAX_RECORD_SIZE: usize = 4 * 1024 * 1024;
+
+pub fn fragment_header(len: usize, last: bool) -> u32 {
+    let mut header = len as u32;
+    if last {
+        header |= LAST_FRAGMENT;
+    }
+    header
+}
+
+pub fn parse_fragment_header(header: u32) -> (usize, bool) {
+    (
+        (header & !LAST_FRAGMENT) as usize,
+        header & LAST_FRAGMENT != 0,
+    )
+}
+
+pub struct RecordWriter<W: Write> {
+    writer: W,
+    max_fragment: usize,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(writer: W) -> Self {
+        RecordWriter {
+            writer,
+            max_fragment: MAX_FRAGMENT_SIZE,
+        }
+    }
+
+    // Clamped to 1..=MAX_FRAGMENT_SIZE, since the header only has 31 bits for the length
+    pub fn max_fragment_size(mut self, size: usize) -> Self {
+        self.max_fragment = size.clamp(1, MAX_FRAGMENT_SIZE);
+        self
+    }
+
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let mut chunks = record.chunks(self.max_fragment).peekable();
+        if chunks.peek().is_none() {
+            self.writer
+                .write_u32::<BigEndian>(fragment_header(0, true))?;
+        }
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            self.writer
+                .write_u32::<BigEndian>(fragment_header(chunk.len(), last))?;
+            self.writer.write_all(chunk)?;
+        }
+        self.writer.flush()
+    }
+
+    pub fn serialize<T>(&mut self, value: &T) -> EncoderResult<()>
+    where
+        T: Serialize,
+    {
+        let mut ser = Serializer::new(Vec::new());
+        value.serialize(&mut ser)?;
+        self.write_record(&ser.into_inner()).map_err(From::from)
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct RecordReader<R: Read> {
+    reader: R,
+    max_record: usize,
+    buf: Vec<u8>,
+    // Where read_record left off, so a read that times out partway through a record can pick
+    // up again without losing the bytes already taken from the stream
+    header: [u8; 4],
+    header_len: usize,
+    fragment: Option<(usize, bool)>,
+    in_record: bool,
+    returned: bool,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        RecordReader {
+            reader,
+            max_record: DEFAULT_MAX_RECORD_SIZE,
+            buf: Vec::new(),
+            header: [0; 4],
+            header_len: 0,
+            fragment: None,
+            in_record: false,
+            returned: false,
+        }
+    }
+
+    // Records whose fragments add up to more than this are rejected before they're buffered
+    pub fn max_record_size(mut self, size: usize) -> Self {
+        self.max_record = size;
+        self
+    }
+
+    // Reassembles the next record. Returns None if the stream ends cleanly between records.
+    //
+    // If the underlying reader fails partway through, say with a read timeout, whatever was read
+    // is kept and the next call carries on with the same record.
+    pub fn read_record(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.returned {
+            self.buf.clear();
+            self.returned = false;
+        }
+        loop {
+            let (left, last) = match self.fragment {
+                Some(fragment) => fragment,
+                None => {
+                    if !self.read_header()? {
+                        return Ok(None);
+                    }
+                    let (len, last) = parse_fragment_header(u32::from_be_bytes(self.header));
+                    if self.buf.len() + len > self.max_record {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "record exceeds the maximum size of {} bytes",
+                                self.max_record
+                            ),
+                        ));
+                    }
+                    self.in_record = true;
+                    (len, last)
+                }
+            };
+
+            // read_to_end keeps what it read even when it fails
+            let before = self.buf.len();
+            let res = (&mut self.reader)
+                .take(left as u64)
+                .read_to_end(&mut self.buf);
+            let left = left - (self.buf.len() - before);
+            self.fragment = Some((left, last));
+            res?;
+            if left != 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.fragment = None;
+            if last {
+                self.in_record = false;
+                self.returned = true;
+                return Ok(Some(&self.buf));
+            }
+        }
+    }
+
+    // False if the stream ended cleanly before a new record started
+    fn read_header(&mut self) -> io::Result<bool> {
+        while self.header_len < 4 {
+            match self.reader.read(&mut self.header[self.header_len..]) {
+                Ok(0) if self.header_len == 0 && !self.in_record => return Ok(false),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => self.header_len += read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.header_len = 0;
+        Ok(true)
+    }
+
+    // Reads the next record and hands it to a Deserializer bounded to that record
+    pub fn deserializer(&mut self) -> io::Result<Option<Deserializer<&[u8]>>> {
+        Ok(self.read_record()?.map(Deserializer::new))
+    }
+
+    pub fn deserialize<T>(&mut self) -> DecoderResult<Option<T>>
+    where
+        T: DeserializeOwned,
+    {
+        match self.deserializer()? {
+            Some(mut de) => Ok(Some(Deserialize::deserialize(&mut de)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+// tokio_util codec for the same framing. Decoding yields each reassembled record; encoding writes a
+// record as fragments of at most `max_fragment_size` bytes.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct RecordCodec {
+    max_record: usize,
+    max_fragment: usize,
+    record: bytes::BytesMut,
+}
+
+#[cfg(feature = "tokio")]
+impl Default for RecordCodec {
+    fn default() -> Self {
+        RecordCodec::new()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl RecordCodec {
+    pub fn new() -> Self {
+        RecordCodec {
+            max_record: DEFAULT_MAX_RECORD_SIZE,
+            max_fragment: MAX_FRAGMENT_SIZE,
+            record: bytes::BytesMut::new(),
+        }
+    }
+
+    pub fn max_record_size(mut self, size: usize) -> Self {
+        self.max_record = size;
+        self
+    }
+
+    pub fn max_fragment_size(mut self, size: usize) -> Self {
+        self.max_fragment = size.clamp(1, MAX_FRAGMENT_SIZE);
+        self
+    }
+
+    fn encode_record(&self, record: &[u8], dst: &mut bytes::BytesMut) {
+        use bytes::BufMut;
+
+        let fragments = record.len().div_ceil(self.max_fragment).max(1);
+        dst.reserve(record.len() + 4 * fragments);
+        if record.is_empty() {
+            dst.put_u32(fragment_header(0, true));
+        }
+        let mut chunks = record.chunks(self.max_fragment).peekable();
+        while let Some(chunk) = chunks.next() {
+            dst.put_u32(fragment_header(chunk.len(), chunks.peek().is_none()));
+            dst.put_slice(chunk);
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Decoder for RecordCodec {
+    type Item = bytes::BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> io::Result<Option<bytes::BytesMut>> {
+        use bytes::Buf;
+
+        loop {
+            if src.len() < 4 {
+                return Ok(None);
+            }
+            let header = u32::from_be_bytes([src[0], src[1], src[2], src[3]]);
+            let (len, last) = parse_fragment_header(header);
+            if self.record.len() + len > self.max_record {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "record exceeds the maximum size of {} bytes",
+                        self.max_record
+                    ),
+                ));
+            }
+            if src.len() < 4 + len {
+                src.reserve(4 + len - src.len());
+                return Ok(None);
+            }
+            src.advance(4);
+            self.record.extend_from_slice(&src.split_to(len));
+            if last {
+                return Ok(Some(self.record.split()));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut bytes::BytesMut) -> io::Result<Option<bytes::BytesMut>> {
+        match self.decode(src)? {
+            Some(record) => Ok(Some(record)),
+            None if src.is_empty() && self.record.is_empty() => Ok(None),
+            None => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Encoder<&[u8]> for RecordCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, record: &[u8], dst: &mut bytes::BytesMut) -> io::Result<()> {
+        self.encode_record(record, dst);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Encoder<Vec<u8>> for RecordCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, record: Vec<u8>, dst: &mut bytes::BytesMut) -> io::Result<()> {
+        self.encode_record(&record, dst);
+        Ok(())
+    }
+}
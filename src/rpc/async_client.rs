@@ -0,0 +1,183 @@
+// Async ONC RPC client over TCP. Concurrent calls share one connection: each goes out under its own
+// xid and a background task hands every reply to the call waiting on that xid.
+
+use crate::errors::EncoderResult;
+use crate::record::RecordCodec;
+use crate::rpc::client::{initial_xid, reply_xid, DEFAULT_TIMEOUT};
+use crate::rpc::{
+    call_to_bytes, reply_from_bytes, Auth, CallBody, OpaqueAuth, RpcError, RpcResult,
+};
+
+use bytes::BytesMut;
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+// Calls waiting on a reply, keyed by xid. None once the connection has gone away.
+type Pending = Arc<Mutex<Option<HashMap<u32, oneshot::Sender<BytesMut>>>>>;
+
+struct Connection {
+    writer: tokio::sync::Mutex<FramedWrite<OwnedWriteHalf, RecordCodec>>,
+    pending: Pending,
+    xid: AtomicU32,
+    reader: JoinHandle<()>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+// Clones share the connection but keep their own credentials and timeout
+#[derive(Clone)]
+pub struct AsyncRpcClient {
+    conn: Arc<Connection>,
+    timeout: Duration,
+    cred: OpaqueAuth,
+}
+
+impl AsyncRpcClient {
+    pub async fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        AsyncRpcClient::from_tcp(TcpStream::connect(addr).await?)
+    }
+
+    // Spawns the reply reader, so this has to be called from within a tokio runtime
+    pub fn from_tcp(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let (read, write) = stream.into_split();
+        let pending: Pending = Arc::new(Mutex::new(Some(HashMap::new())));
+        let reader = tokio::spawn(route_replies(
+            FramedRead::new(read, RecordCodec::new()),
+            pending.clone(),
+        ));
+        let conn = Connection {
+            writer: tokio::sync::Mutex::new(FramedWrite::new(write, RecordCodec::new())),
+            pending,
+            xid: AtomicU32::new(initial_xid()),
+            reader,
+        };
+        Ok(AsyncRpcClient {
+            conn: Arc::new(conn),
+            timeout: DEFAULT_TIMEOUT,
+            cred: OpaqueAuth::none(),
+        })
+    }
+
+    // Default timeout for calls made with `call`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn set_auth(&mut self, cred: &Auth) -> EncoderResult<()> {
+        self.cred = cred.to_opaque()?;
+        Ok(())
+    }
+
+    pub async fn call<Args, Res>(
+        &self,
+        prog: u32,
+        vers: u32,
+        proc: u32,
+        args: &Args,
+    ) -> RpcResult<Res>
+    where
+        Args: Serialize,
+        Res: DeserializeOwned,
+    {
+        self.call_timeout(prog, vers, proc, args, self.timeout)
+            .await
+    }
+
+    pub async fn call_timeout<Args, Res>(
+        &self,
+        prog: u32,
+        vers: u32,
+        proc: u32,
+        args: &Args,
+        timeout: Duration,
+    ) -> RpcResult<Res>
+    where
+        Args: Serialize,
+        Res: DeserializeOwned,
+    {
+        let xid = self
+            .conn
+            .xid
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
+        let mut call = CallBody::new(prog, vers, proc);
+        call.cred = self.cred.clone();
+        let mut request = Vec::new();
+        call_to_bytes(xid, &call, args, &mut request)?;
+
+        let (tx, rx) = oneshot::channel();
+        match self.conn.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(xid, tx),
+            None => return Err(closed().into()),
+        };
+        // Forget the call if it times out or the caller drops this future
+        let _waiting = Waiting {
+            pending: &self.conn.pending,
+            xid,
+        };
+
+        let exchange = async {
+            self.conn.writer.lock().await.send(request).await?;
+            rx.await.map_err(|_| closed())
+        };
+        let reply = match tokio::time::timeout(timeout, exchange).await {
+            Ok(reply) => reply?,
+            Err(_) => return Err(RpcError::Timeout),
+        };
+        let (res, _) = reply_from_bytes(xid, &reply)?;
+        Ok(res)
+    }
+}
+
+struct Waiting<'a> {
+    pending: &'a Pending,
+    xid: u32,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&self.xid);
+        }
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof)
+}
+
+// Replies nobody is waiting for any more (e.g. after a timeout) are dropped. When the connection
+// ends every outstanding call fails.
+async fn route_replies(mut frames: FramedRead<OwnedReadHalf, RecordCodec>, pending: Pending) {
+    while let Some(Ok(record)) = frames.next().await {
+        let waiter = match reply_xid(&record) {
+            Some(xid) => pending
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|p| p.remove(&xid)),
+            None => None,
+        };
+        if let Some(tx) = waiter {
+            let _ = tx.send(record);
+        }
+    }
+    pending.lock().unwrap().take();
+}
@@ -0,0 +1,133 @@
+// Authentication flavors from RFC 5531 section 8 and appendix A
+
+use crate::errors::{DecoderResult, EncoderError, EncoderResult};
+use crate::rpc::{auth_too_long, OpaqueAuth, MAX_AUTH_BYTES};
+use crate::{from_bytes, to_bytes, Opaque};
+
+use serde::{de, ser, Deserialize, Serialize};
+
+pub const AUTH_NONE: u32 = 0;
+pub const AUTH_SYS: u32 = 1;
+pub const AUTH_SHORT: u32 = 2;
+pub const AUTH_DH: u32 = 3;
+pub const RPCSEC_GSS: u32 = 6;
+
+pub const MAX_MACHINE_NAME: usize = 255;
+pub const MAX_GIDS: usize = 16;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuthSysParms {
+    pub stamp: u32,
+    pub machinename: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub gids: Vec<u32>,
+}
+
+impl AuthSysParms {
+    fn check_bounds(&self) -> Result<(), String> {
+        if self.machinename.len() > MAX_MACHINE_NAME {
+            return Err(format!(
+                "AUTH_SYS machinename is {} bytes, at most {} allowed",
+                self.machinename.len(),
+                MAX_MACHINE_NAME
+            ));
+        }
+        if self.gids.len() > MAX_GIDS {
+            return Err(format!(
+                "AUTH_SYS has {} gids, at most {} allowed",
+                self.gids.len(),
+                MAX_GIDS
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Credentials and verifiers with the opaque body decoded according to the flavor.
+// Flavors this crate doesn't know about keep their body as raw bytes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Auth {
+    #[default]
+    None,
+    Sys(AuthSysParms),
+    Short(Opaque),
+    Unknown {
+        flavor: u32,
+        body: Opaque,
+    },
+}
+
+impl Auth {
+    pub fn flavor(&self) -> u32 {
+        match *self {
+            Auth::None => AUTH_NONE,
+            Auth::Sys(_) => AUTH_SYS,
+            Auth::Short(_) => AUTH_SHORT,
+            Auth::Unknown { flavor, .. } => flavor,
+        }
+    }
+
+    pub fn to_opaque(&self) -> EncoderResult<OpaqueAuth> {
+        let body = match *self {
+            Auth::None => Opaque::default(),
+            Auth::Sys(ref parms) => {
+                parms.check_bounds().map_err(EncoderError::Unknown)?;
+                let mut buf = Vec::new();
+                to_bytes(parms, &mut buf)?;
+                Opaque(buf)
+            }
+            Auth::Short(ref body) | Auth::Unknown { ref body, .. } => body.clone(),
+        };
+        if body.0.len() > MAX_AUTH_BYTES {
+            return Err(EncoderError::Unknown(auth_too_long(body.0.len())));
+        }
+        Ok(OpaqueAuth {
+            flavor: self.flavor(),
+            body,
+        })
+    }
+
+    pub fn from_opaque(auth: &OpaqueAuth) -> DecoderResult<Auth> {
+        Ok(match auth.flavor {
+            AUTH_NONE => Auth::None,
+            AUTH_SYS => {
+                let (parms, consumed): (AuthSysParms, usize) = from_bytes(&auth.body.0)?;
+                if consumed != auth.body.0.len() {
+                    return Err(EncoderError::Unknown(format!(
+                        "AUTH_SYS body has {} trailing bytes",
+                        auth.body.0.len() - consumed
+                    )));
+                }
+                parms.check_bounds().map_err(EncoderError::Unknown)?;
+                Auth::Sys(parms)
+            }
+            AUTH_SHORT => Auth::Short(auth.body.clone()),
+            flavor => Auth::Unknown {
+                flavor,
+                body: auth.body.clone(),
+            },
+        })
+    }
+}
+
+impl Serialize for Auth {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        self.to_opaque()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Auth {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let auth = OpaqueAuth::deserialize(deserializer)?;
+        Auth::from_opaque(&auth).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,220 @@
+// Blocking ONC RPC client for TCP (with record marking) and UDP
+
+use crate::errors::EncoderResult;
+use crate::record::{RecordReader, RecordWriter};
+use crate::rpc::{
+    call_to_bytes, reply_from_bytes, Auth, CallBody, OpaqueAuth, RpcError, RpcResult,
+};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(25);
+pub const DEFAULT_RETRANSMIT: Duration = Duration::from_millis(500);
+const MAX_DATAGRAM: usize = 65535;
+
+enum Transport {
+    Tcp {
+        reader: RecordReader<TcpStream>,
+        writer: RecordWriter<TcpStream>,
+    },
+    Udp(UdpSocket),
+}
+
+pub struct RpcClient {
+    transport: Transport,
+    xid: u32,
+    timeout: Duration,
+    retransmit: Duration,
+    cred: OpaqueAuth,
+}
+
+impl RpcClient {
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        RpcClient::from_tcp(TcpStream::connect(addr)?)
+    }
+
+    pub fn from_tcp(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let transport = Transport::Tcp {
+            reader: RecordReader::new(stream.try_clone()?),
+            writer: RecordWriter::new(stream),
+        };
+        Ok(RpcClient::new(transport))
+    }
+
+    pub fn connect_udp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addr = resolve(addr)?;
+        let local: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        Ok(RpcClient::new(Transport::Udp(socket)))
+    }
+
+    fn new(transport: Transport) -> Self {
+        RpcClient {
+            transport,
+            xid: initial_xid(),
+            timeout: DEFAULT_TIMEOUT,
+            retransmit: DEFAULT_RETRANSMIT,
+            cred: OpaqueAuth::none(),
+        }
+    }
+
+    // Default timeout for calls made with `call`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // Initial UDP retransmit interval, doubled after every unanswered attempt
+    pub fn retransmit(mut self, interval: Duration) -> Self {
+        self.retransmit = interval;
+        self
+    }
+
+    pub fn set_auth(&mut self, cred: &Auth) -> EncoderResult<()> {
+        self.cred = cred.to_opaque()?;
+        Ok(())
+    }
+
+    pub fn call<Args, Res>(
+        &mut self,
+        prog: u32,
+        vers: u32,
+        proc: u32,
+        args: &Args,
+    ) -> RpcResult<Res>
+    where
+        Args: Serialize,
+        Res: DeserializeOwned,
+    {
+        let timeout = self.timeout;
+        self.call_timeout(prog, vers, proc, args, timeout)
+    }
+
+    pub fn call_timeout<Args, Res>(
+        &mut self,
+        prog: u32,
+        vers: u32,
+        proc: u32,
+        args: &Args,
+        timeout: Duration,
+    ) -> RpcResult<Res>
+    where
+        Args: Serialize,
+        Res: DeserializeOwned,
+    {
+        self.xid = self.xid.wrapping_add(1);
+        let xid = self.xid;
+        let mut call = CallBody::new(prog, vers, proc);
+        call.cred = self.cred.clone();
+        let mut request = Vec::new();
+        call_to_bytes(xid, &call, args, &mut request)?;
+
+        let deadline = Instant::now() + timeout;
+        let reply = match self.transport {
+            Transport::Tcp {
+                ref mut reader,
+                ref mut writer,
+            } => {
+                writer.write_record(&request)?;
+                tcp_reply(reader, xid, deadline)?
+            }
+            Transport::Udp(ref socket) => {
+                udp_reply(socket, &request, xid, deadline, self.retransmit)?
+            }
+        };
+        let (res, _) = reply_from_bytes(xid, &reply)?;
+        Ok(res)
+    }
+}
+
+// Seeded from the clock so a restarted client doesn't reuse recent xids
+pub(crate) fn initial_xid() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() ^ d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+fn resolve<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
+    addr.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to"))
+}
+
+fn remaining(deadline: Instant) -> RpcResult<Duration> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(left) if left > Duration::from_millis(0) => Ok(left),
+        _ => Err(RpcError::Timeout),
+    }
+}
+
+pub(crate) fn reply_xid(reply: &[u8]) -> Option<u32> {
+    if reply.len() < 4 {
+        return None;
+    }
+    Some(u32::from_be_bytes([reply[0], reply[1], reply[2], reply[3]]))
+}
+
+// Replies to earlier calls that timed out can still turn up, so skip anything not for `xid`.
+// A timeout partway through a record leaves the partial record in the reader, so the next call
+// finishes reading it instead of picking up the stream mid-fragment.
+fn tcp_reply(
+    reader: &mut RecordReader<TcpStream>,
+    xid: u32,
+    deadline: Instant,
+) -> RpcResult<Vec<u8>> {
+    loop {
+        reader
+            .get_mut()
+            .set_read_timeout(Some(remaining(deadline)?))?;
+        match reader.read_record()? {
+            Some(record) if reply_xid(record) == Some(xid) => return Ok(record.to_vec()),
+            Some(_) => continue,
+            None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+}
+
+fn udp_reply(
+    socket: &UdpSocket,
+    request: &[u8],
+    xid: u32,
+    deadline: Instant,
+    retransmit: Duration,
+) -> RpcResult<Vec<u8>> {
+    let mut buf = vec![0; MAX_DATAGRAM];
+    let mut interval = retransmit;
+    loop {
+        socket.send(request)?;
+        let resend_at = Instant::now() + interval;
+        loop {
+            let wait = remaining(deadline)?.min(remaining(resend_at).unwrap_or_default());
+            if wait == Duration::from_millis(0) {
+                break;
+            }
+            socket.set_read_timeout(Some(wait))?;
+            match socket.recv(&mut buf) {
+                Ok(len) if reply_xid(&buf[..len]) == Some(xid) => return Ok(buf[..len].to_vec()),
+                Ok(_) => continue,
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        interval *= 2;
+    }
+}
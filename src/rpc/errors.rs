@@ -0,0 +1,97 @@
+// Errors from making RPC calls, on top of the encoding errors in crate::errors
+
+use crate::errors::EncoderError;
+use crate::rpc::AuthStat;
+
+use std::fmt::{self, Display};
+use std::{error, io};
+
+#[derive(Debug)]
+pub enum RpcError {
+    Xdr(EncoderError),
+    XidMismatch { expected: u32, found: u32 },
+    NotAReply,
+    ProgUnavail,
+    ProgMismatch { low: u32, high: u32 },
+    ProcUnavail,
+    GarbageArgs,
+    SystemErr,
+    RpcMismatch { low: u32, high: u32 },
+    AuthError(AuthStat),
+    Timeout,
+}
+
+impl From<EncoderError> for RpcError {
+    fn from(err: EncoderError) -> RpcError {
+        RpcError::Xdr(err)
+    }
+}
+
+impl From<io::Error> for RpcError {
+    fn from(err: io::Error) -> RpcError {
+        match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => RpcError::Timeout,
+            _ => RpcError::Xdr(EncoderError::Io(err)),
+        }
+    }
+}
+
+impl error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            RpcError::Xdr(ref inner) => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RpcError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RpcError::Xdr(ref error) => fmt::Display::fmt(error, fmt),
+            RpcError::XidMismatch { expected, found } => {
+                write!(
+                    fmt,
+                    "reply xid {:#x} does not match call xid {:#x}",
+                    found, expected
+                )
+            }
+            RpcError::NotAReply => write!(fmt, "expected an RPC reply, got a call"),
+            RpcError::ProgUnavail => write!(fmt, "program unavailable"),
+            RpcError::ProgMismatch { low, high } => {
+                write!(
+                    fmt,
+                    "program version mismatch, server supports {}-{}",
+                    low, high
+                )
+            }
+            RpcError::ProcUnavail => write!(fmt, "procedure unavailable"),
+            RpcError::GarbageArgs => write!(fmt, "server could not decode the arguments"),
+            RpcError::SystemErr => write!(fmt, "server system error"),
+            RpcError::RpcMismatch { low, high } => {
+                write!(
+                    fmt,
+                    "RPC version mismatch, server supports {}-{}",
+                    low, high
+                )
+            }
+            RpcError::AuthError(stat) => write!(fmt, "authentication error: {:?}", stat),
+            RpcError::Timeout => write!(fmt, "timed out waiting for a reply"),
+        }
+    }
+}
+
+// For callers working in terms of the base error type. Timeouts stay recognisable as io errors.
+impl From<RpcError> for EncoderError {
+    fn from(err: RpcError) -> EncoderError {
+        match err {
+            RpcError::Xdr(inner) => inner,
+            RpcError::Timeout => {
+                EncoderError::Io(io::Error::new(io::ErrorKind::TimedOut, err.to_string()))
+            }
+            other => EncoderError::Unknown(other.to_string()),
+        }
+    }
+}
+
+pub type RpcResult<T> = Result<T, RpcError>;
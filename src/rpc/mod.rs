@@ -0,0 +1,526 @@
+// ONC RPC message types from RFC 5531, encoded through this crate's Serializer and Deserializer.
+//
+// Procedure arguments and results aren't part of these types: on the wire they simply follow the
+// call header or a successful reply, so they're written and read straight after it.
+
+#[cfg(feature = "tokio")]
+pub mod async_client;
+pub mod auth;
+pub mod client;
+mod errors;
+pub mod server;
+
+#[cfg(feature = "tokio")]
+pub use self::async_client::AsyncRpcClient;
+pub use self::auth::{Auth, AuthSysParms};
+pub use self::client::RpcClient;
+pub use self::errors::{RpcError, RpcResult};
+pub use self::server::{Call, ProcError, RpcProgram, RpcServer, ServerHandle};
+
+use self::auth::AUTH_NONE;
+use crate::errors::EncoderResult;
+use crate::{xdr_enum, Deserializer, Opaque, Serializer};
+
+use serde::de::{self, DeserializeOwned, SeqAccess, Visitor};
+use serde::ser::{self, SerializeStruct};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Read;
+
+pub const RPC_VERSION: u32 = 2;
+pub const MAX_AUTH_BYTES: usize = 400;
+
+xdr_enum!(MsgType {
+    Call = 0,
+    Reply = 1,
+});
+
+xdr_enum!(ReplyStat {
+    MsgAccepted = 0,
+    MsgDenied = 1,
+});
+
+xdr_enum!(AcceptStat {
+    Success = 0,
+    ProgUnavail = 1,
+    ProgMismatch = 2,
+    ProcUnavail = 3,
+    GarbageArgs = 4,
+    SystemErr = 5,
+});
+
+xdr_enum!(RejectStat {
+    RpcMismatch = 0,
+    AuthError = 1,
+});
+
+xdr_enum!(AuthStat {
+    AuthOk = 0,
+    AuthBadcred = 1,
+    AuthRejectedcred = 2,
+    AuthBadverf = 3,
+    AuthRejectedverf = 4,
+    AuthTooweak = 5,
+    AuthInvalidresp = 6,
+    AuthFailed = 7,
+    AuthKerbGeneric = 8,
+    AuthTimeexpire = 9,
+    AuthTktFile = 10,
+    AuthDecode = 11,
+    AuthNetAddr = 12,
+    RpcsecGssCredproblem = 13,
+    RpcsecGssCtxproblem = 14,
+});
+
+// The body is limited to MAX_AUTH_BYTES both ways
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpaqueAuth {
+    pub flavor: u32,
+    pub body: Opaque,
+}
+
+impl OpaqueAuth {
+    pub fn none() -> Self {
+        OpaqueAuth {
+            flavor: AUTH_NONE,
+            body: Opaque::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MismatchInfo {
+    pub low: u32,
+    pub high: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RpcMsg {
+    pub xid: u32,
+    pub body: MsgBody,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MsgBody {
+    Call(CallBody),
+    Reply(ReplyBody),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CallBody {
+    pub rpcvers: u32,
+    pub prog: u32,
+    pub vers: u32,
+    pub proc: u32,
+    pub cred: OpaqueAuth,
+    pub verf: OpaqueAuth,
+}
+
+impl CallBody {
+    pub fn new(prog: u32, vers: u32, proc: u32) -> Self {
+        CallBody {
+            rpcvers: RPC_VERSION,
+            prog,
+            vers,
+            proc,
+            cred: OpaqueAuth::none(),
+            verf: OpaqueAuth::none(),
+        }
+    }
+
+    pub fn with_cred(mut self, cred: &Auth) -> EncoderResult<Self> {
+        self.cred = cred.to_opaque()?;
+        Ok(self)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReplyBody {
+    Accepted(AcceptedReply),
+    Denied(RejectedReply),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AcceptedReply {
+    pub verf: OpaqueAuth,
+    pub reply_data: ReplyData,
+}
+
+// On SUCCESS the procedure results follow the reply header directly
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReplyData {
+    Success,
+    ProgUnavail,
+    ProgMismatch(MismatchInfo),
+    ProcUnavail,
+    GarbageArgs,
+    SystemErr,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RejectedReply {
+    RpcMismatch(MismatchInfo),
+    AuthError(AuthStat),
+}
+
+impl RpcMsg {
+    pub fn call(xid: u32, body: CallBody) -> Self {
+        RpcMsg {
+            xid,
+            body: MsgBody::Call(body),
+        }
+    }
+
+    pub fn reply(xid: u32, body: ReplyBody) -> Self {
+        RpcMsg {
+            xid,
+            body: MsgBody::Reply(body),
+        }
+    }
+
+    pub fn accepted(xid: u32, reply_data: ReplyData) -> Self {
+        RpcMsg::reply(
+            xid,
+            ReplyBody::Accepted(AcceptedReply {
+                verf: OpaqueAuth::none(),
+                reply_data,
+            }),
+        )
+    }
+
+    pub fn denied(xid: u32, reply: RejectedReply) -> Self {
+        RpcMsg::reply(xid, ReplyBody::Denied(reply))
+    }
+}
+
+// Writes the call header for `xid` followed by the procedure arguments
+pub fn call_to_bytes<T>(xid: u32, call: &CallBody, args: &T, buf: &mut Vec<u8>) -> EncoderResult<()>
+where
+    T: Serialize,
+{
+    let mut ser = Serializer::new(buf);
+    RpcMsg::call(xid, call.clone()).serialize(&mut ser)?;
+    args.serialize(&mut ser)
+}
+
+// Reads the reply to the call `xid`, and on success the procedure results that follow it.
+// Returns the results along with the number of bytes consumed.
+pub fn reply_from_reader<T, R>(xid: u32, reader: R) -> RpcResult<(T, usize)>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    let mut de = Deserializer::new(reader);
+    let msg: RpcMsg = Deserialize::deserialize(&mut de)?;
+    if msg.xid != xid {
+        return Err(RpcError::XidMismatch {
+            expected: xid,
+            found: msg.xid,
+        });
+    }
+    let reply = match msg.body {
+        MsgBody::Reply(reply) => reply,
+        MsgBody::Call(_) => return Err(RpcError::NotAReply),
+    };
+    match reply {
+        ReplyBody::Accepted(accepted) => match accepted.reply_data {
+            ReplyData::Success => {
+                let value = Deserialize::deserialize(&mut de)?;
+                Ok((value, de.get_bytes_consumed()))
+            }
+            ReplyData::ProgUnavail => Err(RpcError::ProgUnavail),
+            ReplyData::ProgMismatch(MismatchInfo { low, high }) => {
+                Err(RpcError::ProgMismatch { low, high })
+            }
+            ReplyData::ProcUnavail => Err(RpcError::ProcUnavail),
+            ReplyData::GarbageArgs => Err(RpcError::GarbageArgs),
+            ReplyData::SystemErr => Err(RpcError::SystemErr),
+        },
+        ReplyBody::Denied(RejectedReply::RpcMismatch(MismatchInfo { low, high })) => {
+            Err(RpcError::RpcMismatch { low, high })
+        }
+        ReplyBody::Denied(RejectedReply::AuthError(stat)) => Err(RpcError::AuthError(stat)),
+    }
+}
+
+pub fn reply_from_bytes<T>(xid: u32, bytes: &[u8]) -> RpcResult<(T, usize)>
+where
+    T: DeserializeOwned,
+{
+    reply_from_reader(xid, bytes)
+}
+
+// Unions go out as a struct of the discriminant followed by the arm, if the arm isn't void
+
+fn next_arm<'de, A, T>(seq: &mut A, union: &str) -> Result<T, A::Error>
+where
+    A: SeqAccess<'de>,
+    T: Deserialize<'de>,
+{
+    seq.next_element()?
+        .ok_or_else(|| de::Error::custom(format!("{} ended early", union)))
+}
+
+pub(crate) fn auth_too_long(len: usize) -> String {
+    format!(
+        "auth body is {} bytes, at most {} allowed",
+        len, MAX_AUTH_BYTES
+    )
+}
+
+impl Serialize for OpaqueAuth {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.body.0.len() > MAX_AUTH_BYTES {
+            return Err(ser::Error::custom(auth_too_long(self.body.0.len())));
+        }
+        let mut state = serializer.serialize_struct("OpaqueAuth", 2)?;
+        state.serialize_field("flavor", &self.flavor)?;
+        state.serialize_field("body", &self.body)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for OpaqueAuth {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct OpaqueAuthVisitor;
+
+        impl<'de> Visitor<'de> for OpaqueAuthVisitor {
+            type Value = OpaqueAuth;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("opaque_auth")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<OpaqueAuth, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let flavor = next_arm(&mut seq, "OpaqueAuth")?;
+                let body: Opaque = next_arm(&mut seq, "OpaqueAuth")?;
+                if body.0.len() > MAX_AUTH_BYTES {
+                    return Err(de::Error::custom(auth_too_long(body.0.len())));
+                }
+                Ok(OpaqueAuth { flavor, body })
+            }
+        }
+
+        deserializer.deserialize_struct("OpaqueAuth", &["flavor", "body"], OpaqueAuthVisitor)
+    }
+}
+
+impl Serialize for MsgBody {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("MsgBody", 2)?;
+        match *self {
+            MsgBody::Call(ref cbody) => {
+                state.serialize_field("mtype", &MsgType::Call)?;
+                state.serialize_field("cbody", cbody)?;
+            }
+            MsgBody::Reply(ref rbody) => {
+                state.serialize_field("mtype", &MsgType::Reply)?;
+                state.serialize_field("rbody", rbody)?;
+            }
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for MsgBody {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct MsgBodyVisitor;
+
+        impl<'de> Visitor<'de> for MsgBodyVisitor {
+            type Value = MsgBody;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("rpc_msg body")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<MsgBody, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                match next_arm(&mut seq, "MsgBody")? {
+                    MsgType::Call => Ok(MsgBody::Call(next_arm(&mut seq, "MsgBody")?)),
+                    MsgType::Reply => Ok(MsgBody::Reply(next_arm(&mut seq, "MsgBody")?)),
+                }
+            }
+        }
+
+        deserializer.deserialize_struct("MsgBody", &["mtype", "body"], MsgBodyVisitor)
+    }
+}
+
+impl Serialize for ReplyBody {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("ReplyBody", 2)?;
+        match *self {
+            ReplyBody::Accepted(ref areply) => {
+                state.serialize_field("stat", &ReplyStat::MsgAccepted)?;
+                state.serialize_field("areply", areply)?;
+            }
+            ReplyBody::Denied(ref rreply) => {
+                state.serialize_field("stat", &ReplyStat::MsgDenied)?;
+                state.serialize_field("rreply", rreply)?;
+            }
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ReplyBody {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ReplyBodyVisitor;
+
+        impl<'de> Visitor<'de> for ReplyBodyVisitor {
+            type Value = ReplyBody;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("reply_body")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<ReplyBody, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                match next_arm(&mut seq, "ReplyBody")? {
+                    ReplyStat::MsgAccepted => {
+                        Ok(ReplyBody::Accepted(next_arm(&mut seq, "ReplyBody")?))
+                    }
+                    ReplyStat::MsgDenied => Ok(ReplyBody::Denied(next_arm(&mut seq, "ReplyBody")?)),
+                }
+            }
+        }
+
+        deserializer.deserialize_struct("ReplyBody", &["stat", "reply"], ReplyBodyVisitor)
+    }
+}
+
+impl Serialize for ReplyData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("ReplyData", 2)?;
+        match *self {
+            ReplyData::Success => state.serialize_field("stat", &AcceptStat::Success)?,
+            ReplyData::ProgUnavail => state.serialize_field("stat", &AcceptStat::ProgUnavail)?,
+            ReplyData::ProgMismatch(ref mismatch_info) => {
+                state.serialize_field("stat", &AcceptStat::ProgMismatch)?;
+                state.serialize_field("mismatch_info", mismatch_info)?;
+            }
+            ReplyData::ProcUnavail => state.serialize_field("stat", &AcceptStat::ProcUnavail)?,
+            ReplyData::GarbageArgs => state.serialize_field("stat", &AcceptStat::GarbageArgs)?,
+            ReplyData::SystemErr => state.serialize_field("stat", &AcceptStat::SystemErr)?,
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ReplyData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ReplyDataVisitor;
+
+        impl<'de> Visitor<'de> for ReplyDataVisitor {
+            type Value = ReplyData;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("accepted_reply reply_data")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<ReplyData, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                Ok(match next_arm(&mut seq, "ReplyData")? {
+                    AcceptStat::Success => ReplyData::Success,
+                    AcceptStat::ProgUnavail => ReplyData::ProgUnavail,
+                    AcceptStat::ProgMismatch => {
+                        ReplyData::ProgMismatch(next_arm(&mut seq, "ReplyData")?)
+                    }
+                    AcceptStat::ProcUnavail => ReplyData::ProcUnavail,
+                    AcceptStat::GarbageArgs => ReplyData::GarbageArgs,
+                    AcceptStat::SystemErr => ReplyData::SystemErr,
+                })
+            }
+        }
+
+        deserializer.deserialize_struct("ReplyData", &["stat", "mismatch_info"], ReplyDataVisitor)
+    }
+}
+
+impl Serialize for RejectedReply {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("RejectedReply", 2)?;
+        match *self {
+            RejectedReply::RpcMismatch(ref mismatch_info) => {
+                state.serialize_field("stat", &RejectStat::RpcMismatch)?;
+                state.serialize_field("mismatch_info", mismatch_info)?;
+            }
+            RejectedReply::AuthError(ref stat) => {
+                state.serialize_field("stat", &RejectStat::AuthError)?;
+                state.serialize_field("auth_stat", stat)?;
+            }
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RejectedReply {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct RejectedReplyVisitor;
+
+        impl<'de> Visitor<'de> for RejectedReplyVisitor {
+            type Value = RejectedReply;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("rejected_reply")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<RejectedReply, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                match next_arm(&mut seq, "RejectedReply")? {
+                    RejectStat::RpcMismatch => Ok(RejectedReply::RpcMismatch(next_arm(
+                        &mut seq,
+                        "RejectedReply",
+                    )?)),
+                    RejectStat::AuthError => Ok(RejectedReply::AuthError(next_arm(
+                        &mut seq,
+                        "RejectedReply",
+                    )?)),
+                }
+            }
+        }
+
+        deserializer.deserialize_struct("RejectedReply", &["stat", "reply"], RejectedReplyVisitor)
+    }
+}
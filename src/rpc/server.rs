@@ -0,0 +1,375 @@
+// ONC RPC server that dispatches calls to registered programs by program, version and procedure.
+//
+// The server answers PROG_UNAVAIL, PROG_MISMATCH and RPC_MISMATCH itself. Programs report
+// PROC_UNAVAIL, GARBAGE_ARGS and SYSTEM_ERR through ProcError, and Call::args turns a failed
+// decode of the arguments into GARBAGE_ARGS so handlers can simply use `?`.
+
+use crate::record::{RecordReader, RecordWriter};
+use crate::rpc::{CallBody, MismatchInfo, MsgBody, RejectedReply, ReplyData, RpcMsg, RPC_VERSION};
+use crate::{Deserializer, Serializer};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+const MAX_DATAGRAM: usize = 65535;
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProcError {
+    ProcUnavail,
+    GarbageArgs,
+    SystemErr,
+}
+
+pub struct Call<'a> {
+    pub header: CallBody,
+    pub peer: SocketAddr,
+    args: Deserializer<&'a [u8]>,
+    results: Serializer<Vec<u8>>,
+}
+
+impl<'a> Call<'a> {
+    pub fn vers(&self) -> u32 {
+        self.header.vers
+    }
+
+    pub fn proc(&self) -> u32 {
+        self.header.proc
+    }
+
+    pub fn args<T>(&mut self) -> Result<T, ProcError>
+    where
+        T: DeserializeOwned,
+    {
+        T::deserialize(&mut self.args).map_err(|_| ProcError::GarbageArgs)
+    }
+
+    pub fn reply<T>(&mut self, results: &T) -> Result<(), ProcError>
+    where
+        T: Serialize,
+    {
+        results
+            .serialize(&mut self.results)
+            .map_err(|_| ProcError::SystemErr)
+    }
+}
+
+pub trait RpcProgram: Send + Sync {
+    fn program(&self) -> u32;
+
+    // Lowest and highest version supported, reported back on PROG_MISMATCH
+    fn versions(&self) -> (u32, u32);
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError>;
+}
+
+#[derive(Clone)]
+pub struct RpcServer {
+    // Each version of a program can be served by a separate RpcProgram
+    programs: Arc<HashMap<u32, Vec<Arc<dyn RpcProgram>>>>,
+    max_connections: usize,
+}
+
+impl Default for RpcServer {
+    fn default() -> Self {
+        RpcServer {
+            programs: Arc::default(),
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+        }
+    }
+}
+
+impl RpcServer {
+    pub fn new() -> Self {
+        RpcServer::default()
+    }
+
+    // Each TCP connection is served by a thread of its own. Connections accepted while this many
+    // are open are closed straight away, so a flood of them can't run the process out of threads.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max;
+        self
+    }
+
+    pub fn register<P>(&mut self, program: P)
+    where
+        P: RpcProgram + 'static,
+    {
+        // Replaces whatever served the same versions before, other versions are kept
+        let (low, high) = program.versions();
+        let versions = Arc::make_mut(&mut self.programs)
+            .entry(program.program())
+            .or_default();
+        versions.retain(|p| {
+            let (l, h) = p.versions();
+            h < low || l > high
+        });
+        versions.push(Arc::new(program));
+    }
+
+    // Handles one call message and returns the encoded reply. Messages that can't be decoded
+    // as a call get no reply at all, since there's no trustworthy xid to answer with.
+    pub fn handle(&self, request: &[u8], peer: SocketAddr) -> Option<Vec<u8>> {
+        let mut args = Deserializer::new(request);
+        let msg = RpcMsg::deserialize(&mut args).ok()?;
+        let header = match msg.body {
+            MsgBody::Call(header) => header,
+            MsgBody::Reply(_) => return None,
+        };
+
+        let (reply, results) = if header.rpcvers != RPC_VERSION {
+            let mismatch = MismatchInfo {
+                low: RPC_VERSION,
+                high: RPC_VERSION,
+            };
+            (
+                RpcMsg::denied(msg.xid, RejectedReply::RpcMismatch(mismatch)),
+                None,
+            )
+        } else {
+            match self.programs.get(&header.prog) {
+                None => (RpcMsg::accepted(msg.xid, ReplyData::ProgUnavail), None),
+                Some(versions) => {
+                    let serving = versions.iter().find(|p| {
+                        let (low, high) = p.versions();
+                        header.vers >= low && header.vers <= high
+                    });
+                    if let Some(program) = serving {
+                        let mut call = Call {
+                            header,
+                            peer,
+                            args,
+                            results: Serializer::new(Vec::new()),
+                        };
+                        match program.dispatch(&mut call) {
+                            Ok(()) => (
+                                RpcMsg::accepted(msg.xid, ReplyData::Success),
+                                Some(call.results.into_inner()),
+                            ),
+                            Err(ProcError::ProcUnavail) => {
+                                (RpcMsg::accepted(msg.xid, ReplyData::ProcUnavail), None)
+                            }
+                            Err(ProcError::GarbageArgs) => {
+                                (RpcMsg::accepted(msg.xid, ReplyData::GarbageArgs), None)
+                            }
+                            Err(ProcError::SystemErr) => {
+                                (RpcMsg::accepted(msg.xid, ReplyData::SystemErr), None)
+                            }
+                        }
+                    } else {
+                        let low = versions.iter().map(|p| p.versions().0).min();
+                        let high = versions.iter().map(|p| p.versions().1).max();
+                        let mismatch = MismatchInfo {
+                            low: low.unwrap_or(0),
+                            high: high.unwrap_or(0),
+                        };
+                        (
+                            RpcMsg::accepted(msg.xid, ReplyData::ProgMismatch(mismatch)),
+                            None,
+                        )
+                    }
+                }
+            }
+        };
+
+        let mut buf = Vec::new();
+        reply.serialize(&mut Serializer::new(&mut buf)).ok()?;
+        if let Some(results) = results {
+            buf.extend(results);
+        }
+        Some(buf)
+    }
+
+    pub fn serve_tcp(&self, listener: TcpListener) -> io::Result<()> {
+        self.accept_loop(listener, &Arc::new(Shared::default()))
+    }
+
+    pub fn serve_udp(&self, socket: UdpSocket) -> io::Result<()> {
+        self.recv_loop(socket, &AtomicBool::new(false))
+    }
+
+    // Serves on a background thread until the returned handle is shut down or dropped
+    pub fn spawn_tcp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<ServerHandle> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+        let server = self.clone();
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || {
+            let _ = server.accept_loop(listener, &thread_shared);
+        });
+        Ok(ServerHandle {
+            local_addr,
+            udp: false,
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn spawn_udp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<ServerHandle> {
+        let socket = UdpSocket::bind(addr)?;
+        let local_addr = socket.local_addr()?;
+        let shared = Arc::new(Shared::default());
+        let server = self.clone();
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || {
+            let _ = server.recv_loop(socket, &thread_shared.stop);
+        });
+        Ok(ServerHandle {
+            local_addr,
+            udp: true,
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    fn accept_loop(&self, listener: TcpListener, shared: &Arc<Shared>) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if shared.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            // A failed accept only loses that one connection
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let mut connections = shared.connections.lock().unwrap();
+            if connections.len() >= self.max_connections {
+                continue;
+            }
+            // Kept for shutdown, which can't reach connections it doesn't know about
+            let clone = match stream.try_clone() {
+                Ok(clone) => clone,
+                Err(_) => continue,
+            };
+            let id = shared.next_id.fetch_add(1, Ordering::SeqCst);
+            connections.insert(id, clone);
+            drop(connections);
+            let server = self.clone();
+            let connection = Connection {
+                shared: shared.clone(),
+                id,
+            };
+            thread::spawn(move || {
+                let _connection = connection;
+                let _ = server.serve_connection(stream);
+            });
+        }
+        Ok(())
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let peer = stream.peer_addr()?;
+        let mut reader = RecordReader::new(stream.try_clone()?);
+        let mut writer = RecordWriter::new(stream);
+        while let Some(request) = reader.read_record()? {
+            if let Some(reply) = self.handle(request, peer) {
+                writer.write_record(&reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn recv_loop(&self, socket: UdpSocket, stop: &AtomicBool) -> io::Result<()> {
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            let received = socket.recv_from(&mut buf);
+            if stop.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            // Errors here are about one datagram or one peer, so drop it and carry on
+            let (len, peer) = match received {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            if let Some(reply) = self.handle(&buf[..len], peer) {
+                let _ = socket.send_to(&reply, peer);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    stop: AtomicBool,
+    next_id: AtomicUsize,
+    connections: Mutex<HashMap<usize, TcpStream>>,
+}
+
+// Forgets the connection when its thread finishes, even if a program panicked, so the stream
+// held for shutdown doesn't keep the socket open
+struct Connection {
+    shared: Arc<Shared>,
+    id: usize,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Ok(mut connections) = self.shared.connections.lock() {
+            connections.remove(&self.id);
+        }
+    }
+}
+
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    udp: bool,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return,
+        };
+        self.shared.stop.store(true, Ordering::SeqCst);
+
+        // Wake the blocked accept or recv so the thread sees the stop flag
+        let mut wake = self.local_addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(if wake.is_ipv4() {
+                [127, 0, 0, 1].into()
+            } else {
+                [0, 0, 0, 0, 0, 0, 0, 1].into()
+            });
+        }
+        if self.udp {
+            let local: SocketAddr = if wake.is_ipv4() {
+                ([127, 0, 0, 1], 0).into()
+            } else {
+                ([0, 0, 0, 0, 0, 0, 0, 1], 0).into()
+            };
+            if let Ok(socket) = UdpSocket::bind(local) {
+                let _ = socket.send_to(&[], wake);
+            }
+        } else {
+            let _ = TcpStream::connect(wake);
+        }
+        for connection in self.shared.connections.lock().unwrap().values() {
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+        let _ = thread.join();
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,174 @@
+// rpcbind versions 3 and 4 (RFC 1833 section 2), plus universal address conversions.
+//
+// Universal addresses are the textual transport addresses rpcbind hands out, e.g.
+// "127.0.0.1.0.111" for port 111 on IPv4 loopback: the host followed by the high and low
+// bytes of the port.
+
+use crate::rpc::{RpcClient, RpcResult};
+use crate::{List, Opaque};
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+pub const RPCB_PORT: u16 = 111;
+pub const RPCBPROG: u32 = 100000;
+pub const RPCBVERS: u32 = 3;
+pub const RPCBVERS4: u32 = 4;
+
+pub const RPCBPROC_NULL: u32 = 0;
+pub const RPCBPROC_SET: u32 = 1;
+pub const RPCBPROC_UNSET: u32 = 2;
+pub const RPCBPROC_GETADDR: u32 = 3;
+pub const RPCBPROC_DUMP: u32 = 4;
+pub const RPCBPROC_CALLIT: u32 = 5;
+pub const RPCBPROC_BCAST: u32 = 5;
+pub const RPCBPROC_GETTIME: u32 = 6;
+pub const RPCBPROC_UADDR2TADDR: u32 = 7;
+pub const RPCBPROC_TADDR2UADDR: u32 = 8;
+pub const RPCBPROC_GETVERSADDR: u32 = 9;
+pub const RPCBPROC_INDIRECT: u32 = 10;
+pub const RPCBPROC_GETADDRLIST: u32 = 11;
+pub const RPCBPROC_GETSTAT: u32 = 12;
+
+pub const NC_TPI_CLTS: u32 = 1;
+pub const NC_TPI_COTS: u32 = 2;
+pub const NC_TPI_COTS_ORD: u32 = 3;
+pub const NC_TPI_RAW: u32 = 4;
+
+pub const NETID_TCP: &str = "tcp";
+pub const NETID_UDP: &str = "udp";
+pub const NETID_TCP6: &str = "tcp6";
+pub const NETID_UDP6: &str = "udp6";
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Rpcb {
+    pub r_prog: u32,
+    pub r_vers: u32,
+    pub r_netid: String,
+    pub r_addr: String,
+    pub r_owner: String,
+}
+
+// `rpcblist_ptr`, the linked list returned by RPCBPROC_DUMP
+pub type RpcbList = List<Rpcb>;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RpcbRmtcallargs {
+    pub prog: u32,
+    pub vers: u32,
+    pub proc: u32,
+    pub args: Opaque,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RpcbRmtcallres {
+    pub addr: String,
+    pub results: Opaque,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RpcbEntry {
+    pub r_maddr: String,
+    pub r_nc_netid: String,
+    pub r_nc_semantics: u32,
+    pub r_nc_protofmly: String,
+    pub r_nc_proto: String,
+}
+
+// `rpcb_entry_list_ptr`, returned by RPCBPROC_GETADDRLIST
+pub type RpcbEntryList = List<RpcbEntry>;
+
+pub fn to_uaddr(addr: &SocketAddr) -> String {
+    let port = addr.port();
+    format!("{}.{}.{}", addr.ip(), port >> 8, port & 0xff)
+}
+
+// Returns None for anything that isn't an IPv4 or IPv6 universal address
+pub fn from_uaddr(uaddr: &str) -> Option<SocketAddr> {
+    let mut parts = uaddr.rsplitn(3, '.');
+    let low: u8 = parts.next()?.parse().ok()?;
+    let high: u8 = parts.next()?.parse().ok()?;
+    let ip: IpAddr = parts.next()?.parse().ok()?;
+    Some(SocketAddr::new(ip, (u16::from(high) << 8) | u16::from(low)))
+}
+
+pub fn netid_for(addr: &SocketAddr, tcp: bool) -> &'static str {
+    match (addr.is_ipv4(), tcp) {
+        (true, true) => NETID_TCP,
+        (true, false) => NETID_UDP,
+        (false, true) => NETID_TCP6,
+        (false, false) => NETID_UDP6,
+    }
+}
+
+pub struct RpcbClient {
+    client: RpcClient,
+    vers: u32,
+}
+
+impl RpcbClient {
+    // `vers` picks RPCBVERS or RPCBVERS4 for the procedures both versions share
+    pub fn new(client: RpcClient, vers: u32) -> Self {
+        RpcbClient { client, vers }
+    }
+
+    pub fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        RpcClient::connect_tcp(addr).map(|client| RpcbClient::new(client, RPCBVERS4))
+    }
+
+    pub fn connect_udp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        RpcClient::connect_udp(addr).map(|client| RpcbClient::new(client, RPCBVERS4))
+    }
+
+    pub fn null(&mut self) -> RpcResult<()> {
+        self.client.call(RPCBPROG, self.vers, RPCBPROC_NULL, &())
+    }
+
+    pub fn set(&mut self, rpcb: &Rpcb) -> RpcResult<bool> {
+        self.client.call(RPCBPROG, self.vers, RPCBPROC_SET, rpcb)
+    }
+
+    pub fn unset(&mut self, rpcb: &Rpcb) -> RpcResult<bool> {
+        self.client.call(RPCBPROG, self.vers, RPCBPROC_UNSET, rpcb)
+    }
+
+    // The universal address of the service, empty when it isn't registered
+    pub fn getaddr(&mut self, rpcb: &Rpcb) -> RpcResult<String> {
+        self.client
+            .call(RPCBPROG, self.vers, RPCBPROC_GETADDR, rpcb)
+    }
+
+    pub fn dump(&mut self) -> RpcResult<Vec<Rpcb>> {
+        let list: RpcbList = self.client.call(RPCBPROG, self.vers, RPCBPROC_DUMP, &())?;
+        Ok(list.0)
+    }
+
+    // Seconds since the epoch on the server
+    pub fn gettime(&mut self) -> RpcResult<u32> {
+        self.client.call(RPCBPROG, self.vers, RPCBPROC_GETTIME, &())
+    }
+
+    pub fn getversaddr(&mut self, rpcb: &Rpcb) -> RpcResult<String> {
+        self.client
+            .call(RPCBPROG, RPCBVERS4, RPCBPROC_GETVERSADDR, rpcb)
+    }
+
+    pub fn getaddrlist(&mut self, rpcb: &Rpcb) -> RpcResult<Vec<RpcbEntry>> {
+        let list: RpcbEntryList =
+            self.client
+                .call(RPCBPROG, RPCBVERS4, RPCBPROC_GETADDRLIST, rpcb)?;
+        Ok(list.0)
+    }
+
+    // Looks up `prog`/`vers` on `netid` and converts the answer to a socket address
+    pub fn lookup(&mut self, prog: u32, vers: u32, netid: &str) -> RpcResult<Option<SocketAddr>> {
+        let rpcb = Rpcb {
+            r_prog: prog,
+            r_vers: vers,
+            r_netid: netid.to_string(),
+            ..Rpcb::default()
+        };
+        Ok(from_uaddr(&self.getaddr(&rpcb)?))
+    }
+}
@@ -4,21 +4,29 @@ use byteorder::{BigEndian, WriteBytesExt};
 use serde::ser;
 use std::io;
 
-macro_rules! not_implemented {
-    ($($name:ident($($arg:ident: $ty:ty,)*);)*) => {
-        $(fn $name<>(self, $($arg: $ty,)*) -> EncoderResult<()> {
-            Err(EncoderError::Unknown(format!("Serialize Not Implemented for {}", stringify!($name))))
-        })*
-    }
+// Number of zero bytes needed to bring a variable-length item up to a multiple of 4
+pub(crate) fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
 }
 
 pub struct Serializer<W> {
     writer: W,
+    sort_map_keys: bool,
 }
 
 impl<W: io::Write> Serializer<W> {
     pub fn new(writer: W) -> Self {
-        Serializer { writer }
+        Serializer {
+            writer,
+            sort_map_keys: false,
+        }
+    }
+
+    /// Maps are written in iteration order by default, which for a HashMap differs from run to
+    /// run. Sorting orders the entries by their encoded key bytes so equal maps encode equally.
+    pub fn sort_map_keys(mut self, sort: bool) -> Self {
+        self.sort_map_keys = sort;
+        self
     }
 
     pub fn into_inner(self) -> W {
@@ -26,12 +34,6 @@ impl<W: io::Write> Serializer<W> {
     }
 }
 
-#[derive(Debug)]
-pub struct MapState {
-    size: usize,
-    slots: Vec<u8>,
-}
-
 impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     type Error = EncoderError;
     type Ok = ();
@@ -44,12 +46,17 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     type SerializeStruct = Compound<'a, W>;
     type SerializeStructVariant = Compound<'a, W>;
 
-    not_implemented!(
-        serialize_f32(_val: f32,);
-        serialize_f64(_val: f64,);
-        serialize_none();
-        serialize_unit_struct(_name: &'static str,);
-    );
+    fn serialize_f32(self, value: f32) -> EncoderResult<()> {
+        self.writer
+            .write_f32::<BigEndian>(value)
+            .map_err(From::from)
+    }
+
+    fn serialize_f64(self, value: f64) -> EncoderResult<()> {
+        self.writer
+            .write_f64::<BigEndian>(value)
+            .map_err(From::from)
+    }
 
     fn serialize_i8(self, value: i8) -> EncoderResult<()> {
         self.writer.write_i8(value).map_err(From::from)
@@ -73,6 +80,12 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
             .map_err(From::from)
     }
 
+    fn serialize_i128(self, value: i128) -> EncoderResult<()> {
+        self.writer
+            .write_i128::<BigEndian>(value)
+            .map_err(From::from)
+    }
+
     fn serialize_u8(self, value: u8) -> EncoderResult<()> {
         self.writer.write_u8(value).map_err(From::from)
     }
@@ -95,8 +108,19 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
             .map_err(From::from)
     }
 
-    fn serialize_bytes(self, _val: &[u8]) -> EncoderResult<()> {
-        Err(EncoderError::Unknown(String::from("Not yet implemented")))
+    fn serialize_u128(self, value: u128) -> EncoderResult<()> {
+        self.writer
+            .write_u128::<BigEndian>(value)
+            .map_err(From::from)
+    }
+
+    // Variable-length opaque data, laid out like a string
+    fn serialize_bytes(self, val: &[u8]) -> EncoderResult<()> {
+        self.serialize_u32(val.len() as u32)?;
+        self.writer.write_all(val)?;
+        self.writer
+            .write_all(&[0; 3][..padding(val.len())])
+            .map_err(From::from)
     }
 
     fn serialize_char(self, val: char) -> EncoderResult<()> {
@@ -104,39 +128,46 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_str(self, val: &str) -> EncoderResult<()> {
-        self.serialize_u32(val.len() as u32).unwrap();
-        let extra_bytes = 4 - val.len() % 4;
-        for c in val.chars() {
-            self.serialize_char(c).unwrap();
-        }
+        self.serialize_u32(val.len() as u32)?;
+        self.writer.write_all(val.as_bytes())?;
         // Spec needs padding to multiple of 4
-        for _ in 0..extra_bytes {
-            self.serialize_u8(0 as u8).unwrap();
-        }
-        Ok(())
-    }
-    fn serialize_bool(self, v: bool) -> EncoderResult<()> {
         self.writer
-            .write_u8(if v { 1 } else { 0 })
+            .write_all(&[0; 3][..padding(val.len())])
             .map_err(From::from)
     }
 
+    // XDR bools are an enum of FALSE = 0 and TRUE = 1, so a full 4 bytes
+    fn serialize_bool(self, v: bool) -> EncoderResult<()> {
+        self.serialize_u32(if v { 1 } else { 0 })
+    }
+
     fn serialize_unit(self) -> EncoderResult<()> {
         Ok(())
     }
 
-    fn serialize_some<T>(self, _value: &T) -> EncoderResult<()>
+    fn serialize_unit_struct(self, _name: &'static str) -> EncoderResult<()> {
+        self.serialize_unit()
+    }
+
+    // Optional-data is a bool followed by the value when present
+    fn serialize_none(self) -> EncoderResult<()> {
+        self.serialize_bool(false)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> EncoderResult<()>
     where
         T: ser::Serialize + ?Sized,
     {
-        Err(EncoderError::Unknown(String::from("Not yet implemented")))
+        self.serialize_bool(true)?;
+        value.serialize(self)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> EncoderResult<()>
+    // Newtypes are transparent on the wire, matching deserialize_newtype_struct
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> EncoderResult<()>
     where
         T: ser::Serialize + ?Sized,
     {
-        Err(EncoderError::Unknown(String::from("Not yet implemented")))
+        value.serialize(self)
     }
 
     fn serialize_newtype_variant<T>(
@@ -162,16 +193,28 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_struct(
         self,
         _name: &'static str,
-        len: usize,
+        _len: usize,
     ) -> EncoderResult<Self::SerializeStruct> {
-        Ok(Compound {
-            ser: self,
-            size: Some(len),
-        })
+        Ok(Compound::new(self))
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> EncoderResult<Self::SerializeMap> {
-        Err(EncoderError::Unknown(String::from("Not yet implemented")))
+    // XDR has no map type, so maps go out as a variable-length array of key/value pairs
+    fn serialize_map(self, len: Option<usize>) -> EncoderResult<Self::SerializeMap> {
+        if self.sort_map_keys {
+            return Ok(Compound {
+                ser: self,
+                map: Some(MapState::default()),
+            });
+        }
+        match len {
+            Some(len) => {
+                self.serialize_u32(len as u32)?;
+                Ok(Compound::new(self))
+            }
+            None => Err(EncoderError::Unknown(String::from(
+                "XDR maps need their length up front",
+            ))),
+        }
     }
 
     fn serialize_unit_variant(
@@ -185,14 +228,13 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     fn serialize_seq(self, len: Option<usize>) -> EncoderResult<Self::SerializeSeq> {
         self.serialize_u32(len.unwrap() as u32).unwrap();
-        Ok(Compound {
-            ser: self,
-            size: len,
-        })
+        Ok(Compound::new(self))
     }
 
-    fn serialize_tuple(self, len: usize) -> EncoderResult<Self::SerializeTuple> {
-        self.serialize_seq(Some(len))
+    // Tuples and tuple structs have a length known to both sides, so like XDR fixed-length arrays
+    // they go out without one
+    fn serialize_tuple(self, _len: usize) -> EncoderResult<Self::SerializeTuple> {
+        Ok(Compound::new(self))
     }
 
     fn serialize_tuple_struct(
@@ -200,7 +242,7 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         _name: &'static str,
         len: usize,
     ) -> EncoderResult<Self::SerializeTupleStruct> {
-        self.serialize_seq(Some(len))
+        self.serialize_tuple(len)
     }
 
     fn serialize_tuple_variant(
@@ -224,25 +266,39 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         match descr_idx {
             Ok(idx) => {
                 self.serialize_u32(idx).unwrap();
-                Ok(Compound {
-                    ser: self,
-                    size: Some(idx as usize),
-                })
+                Ok(Compound::new(self))
             }
             Err(_) => {
-                self.serialize_u32((variant_idx + 1) as u32).unwrap();
-                Ok(Compound {
-                    ser: self,
-                    size: Some((variant_idx + 1) as usize),
-                })
+                self.serialize_u32(variant_idx + 1).unwrap();
+                Ok(Compound::new(self))
             }
         }
     }
 }
 
+#[derive(Debug, Default)]
+pub struct MapState {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
 pub struct Compound<'a, W: 'a> {
     ser: &'a mut Serializer<W>,
-    size: Option<usize>,
+    map: Option<MapState>,
+}
+
+impl<'a, W: io::Write> Compound<'a, W> {
+    fn new(ser: &'a mut Serializer<W>) -> Self {
+        Compound { ser, map: None }
+    }
+
+    fn buffer<T>(&self, value: &T) -> EncoderResult<Vec<u8>>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let mut ser = Serializer::new(Vec::new()).sort_map_keys(self.ser.sort_map_keys);
+        value.serialize(&mut ser)?;
+        Ok(ser.into_inner())
+    }
 }
 
 impl<'a, W> ser::SerializeSeq for Compound<'a, W>
@@ -290,15 +346,15 @@ where
     type Ok = ();
     type Error = EncoderError;
 
-    fn serialize_element<T>(&mut self, _value: &T) -> EncoderResult<()>
+    fn serialize_element<T>(&mut self, value: &T) -> EncoderResult<()>
     where
         T: ser::Serialize + ?Sized,
     {
-        Err(EncoderError::Unknown(String::from("Not Implemented")))
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> EncoderResult<()> {
-        Err(EncoderError::Unknown(String::from("Not Implemented")))
+        Ok(())
     }
 }
 
@@ -309,21 +365,43 @@ where
     type Ok = ();
     type Error = EncoderError;
 
-    fn serialize_key<T>(&mut self, _value: &T) -> EncoderResult<()>
+    fn serialize_key<T>(&mut self, value: &T) -> EncoderResult<()>
     where
         T: ser::Serialize + ?Sized,
     {
-        Err(EncoderError::Unknown(String::from("Not Implemented")))
+        if self.map.is_some() {
+            let key = self.buffer(value)?;
+            if let Some(map) = self.map.as_mut() {
+                map.entries.push((key, Vec::new()));
+            }
+            return Ok(());
+        }
+        value.serialize(&mut *self.ser)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> EncoderResult<()>
     where
         T: ser::Serialize + ?Sized,
     {
+        if self.map.is_some() {
+            let bytes = self.buffer(value)?;
+            if let Some((_, slot)) = self.map.as_mut().and_then(|map| map.entries.last_mut()) {
+                *slot = bytes;
+            }
+            return Ok(());
+        }
         value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> EncoderResult<()> {
+        if let Some(mut map) = self.map {
+            map.entries.sort();
+            ser::Serializer::serialize_u32(&mut *self.ser, map.entries.len() as u32)?;
+            for (key, value) in map.entries {
+                self.ser.writer.write_all(&key)?;
+                self.ser.writer.write_all(&value)?;
+            }
+        }
         Ok(())
     }
 }
@@ -373,14 +451,14 @@ where
     type Ok = ();
     type Error = EncoderError;
 
-    fn serialize_field<T>(&mut self, _value: &T) -> EncoderResult<()>
+    fn serialize_field<T>(&mut self, value: &T) -> EncoderResult<()>
     where
         T: ser::Serialize + ?Sized,
     {
-        Err(EncoderError::Unknown(String::from("Not Implemented")))
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> EncoderResult<()> {
-        Err(EncoderError::Unknown(String::from("Not Implemented")))
+        Ok(())
     }
 }
@@ -0,0 +1,167 @@
+// A record of every primitive read while decoding, for working out which bytes went where when a
+// message doesn't decode the way it should:
+//
+//     let mut de = Deserializer::new(&bytes[..]).trace(true);
+//     let resp = DeviceReadResp::deserialize(&mut de);
+//     print!("{}", de.take_trace().unwrap());
+//
+// Offsets come from the same count as Deserializer::get_bytes_consumed, so they line up with the
+// input exactly. When decoding fails, the path of the field being read is kept and any bytes read
+// for it are shown as incomplete. value::trace_with_schema does the same for a decode driven by a
+// schema, which is what `serde-xdr hexdump` prints.
+
+use crate::errors::DecoderResult;
+use crate::Deserializer;
+
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceEntry {
+    pub offset: usize,
+    pub len: usize,
+    pub bytes: Vec<u8>,
+    // The serde type read, or `length`, `padding` or `skipped` for the parts of XDR serde
+    // doesn't see
+    pub type_name: &'static str,
+    // Reads like a Rust field access: `link.device`, `params[2].name`
+    pub path: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+    path: Vec<String>,
+    pending: Vec<u8>,
+}
+
+// Decodes `T` with tracing on, handing back the trace whether or not decoding succeeded
+pub fn trace_from_bytes<'a, T>(v: &'a [u8]) -> (DecoderResult<(T, usize)>, Trace)
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::new(v).trace(true);
+    let res = T::deserialize(&mut de).map(|value| (value, de.get_bytes_consumed()));
+    (res, de.take_trace().unwrap_or_default())
+}
+
+impl Trace {
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    // Bytes read for a primitive that never finished, because the input ran out or was invalid
+    pub fn incomplete(&self) -> &[u8] {
+        &self.pending
+    }
+
+    // The trace followed by whatever of `input` decoding didn't get to, labelled as trailing
+    pub fn dump(&self, input: &[u8]) -> String {
+        let mut out = self.to_string();
+        let end = self.end() + self.pending.len();
+        if let Some(rest) = input.get(end..) {
+            // Writing to a String can't fail
+            let _ = lines(&mut out, end, rest, "(trailing)", "(trailing)");
+        }
+        out
+    }
+
+    fn end(&self) -> usize {
+        self.entries
+            .last()
+            .map_or(0, |entry| entry.offset + entry.len)
+    }
+
+    fn path(&self) -> String {
+        self.path.concat().trim_start_matches('.').to_string()
+    }
+
+    pub(crate) fn enter(&mut self, segment: String) {
+        self.path.push(segment);
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    pub(crate) fn read(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn record(&mut self, offset: usize, len: usize, type_name: &'static str) {
+        let bytes = std::mem::take(&mut self.pending);
+        if len == 0 {
+            return;
+        }
+        let path = self.path();
+        self.entries.push(TraceEntry {
+            offset,
+            len,
+            bytes,
+            type_name,
+            path,
+        });
+    }
+
+    // The entry just recorded turned out to be part of something with a better name, like the
+    // u32 under a bool
+    pub(crate) fn relabel(&mut self, type_name: &'static str) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.type_name = type_name;
+        }
+    }
+}
+
+// A line per word, labelled `first` and then `rest` once the bytes run past the first word
+fn lines<W: fmt::Write>(
+    f: &mut W,
+    offset: usize,
+    bytes: &[u8],
+    first: &str,
+    rest: &str,
+) -> fmt::Result {
+    for (i, word) in bytes.chunks(4).enumerate() {
+        let hex: Vec<String> = word.iter().map(|b| format!("{:02x}", b)).collect();
+        let label = if i == 0 { first } else { rest };
+        writeln!(
+            f,
+            "{:08x}  {:<11}  {}",
+            offset + i * 4,
+            hex.join(" "),
+            label
+        )?;
+    }
+    Ok(())
+}
+
+fn labelled<W: fmt::Write>(f: &mut W, offset: usize, bytes: &[u8], label: &str) -> fmt::Result {
+    lines(f, offset, bytes, label, &format!("{} (cont.)", label))
+}
+
+fn label(path: &str, type_name: &str) -> String {
+    if path.is_empty() {
+        type_name.to_string()
+    } else {
+        format!("{}: {}", path, type_name)
+    }
+}
+
+// One line per word, so anything longer than a word continues on lines marked `(cont.)`
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            labelled(
+                f,
+                entry.offset,
+                &entry.bytes,
+                &label(&entry.path, entry.type_name),
+            )?;
+        }
+        labelled(
+            f,
+            self.end(),
+            &self.pending,
+            &label(&self.path(), "incomplete"),
+        )
+    }
+}
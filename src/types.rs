@@ -0,0 +1,547 @@
+use crate::errors::{EncoderError, EncoderResult};
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, SerializeTuple, Serializer};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+const F64_MANTISSA_BITS: u32 = 52;
+const F64_EXPONENT_BIAS: i32 = 1023;
+const F128_MANTISSA_BITS: u32 = 112;
+const F128_EXPONENT_BIAS: i32 = 16383;
+const F128_EXPONENT_MAX: u128 = 0x7FFF;
+const MANTISSA_SHIFT: u32 = F128_MANTISSA_BITS - F64_MANTISSA_BITS;
+
+/// XDR `void`, which encodes as zero bytes. Handy for void RPC arguments and union arms.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Void;
+
+/// XDR variable-length `opaque<>`. A plain `Vec<u8>` goes out as an array of single bytes
+/// instead, so use this wherever the `.x` definition says opaque.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Opaque(pub Vec<u8>);
+
+impl Opaque {
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for Opaque {
+    fn from(bytes: Vec<u8>) -> Self {
+        Opaque(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Opaque {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Serialize for Opaque {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Opaque {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OpaqueVisitor;
+
+        impl<'de> Visitor<'de> for OpaqueVisitor {
+            type Value = Opaque;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("opaque bytes")
+            }
+
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<Opaque, E>
+            where
+                E: de::Error,
+            {
+                Ok(Opaque(value.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Opaque, E>
+            where
+                E: de::Error,
+            {
+                Ok(Opaque(value))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(OpaqueVisitor)
+    }
+}
+
+/// A variable-length XDR value with a maximum size: `string<MAX>`, `opaque<MAX>` or `T<MAX>`
+/// around a `String`, `Opaque` or `Vec<T>`. The bound is checked on construction, and again
+/// when encoding and decoding.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Bounded<T, const MAX: u32>(T);
+
+// What Bounded measures against its bound: bytes for strings and opaques, elements for arrays
+pub trait BoundedLen {
+    fn bounded_len(&self) -> usize;
+}
+
+impl BoundedLen for String {
+    fn bounded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl BoundedLen for Opaque {
+    fn bounded_len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<T> BoundedLen for Vec<T> {
+    fn bounded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+fn check_bound(len: usize, max: u32) -> Result<(), String> {
+    if len > max as usize {
+        Err(format!("length {} is over the bound of {}", len, max))
+    } else {
+        Ok(())
+    }
+}
+
+impl<T: BoundedLen, const MAX: u32> Bounded<T, MAX> {
+    pub fn new(value: T) -> EncoderResult<Self> {
+        check_bound(value.bounded_len(), MAX).map_err(EncoderError::Unknown)?;
+        Ok(Bounded(value))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, const MAX: u32> Deref for Bounded<T, MAX> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<const MAX: u32> TryFrom<&str> for Bounded<String, MAX> {
+    type Error = EncoderError;
+
+    fn try_from(value: &str) -> EncoderResult<Self> {
+        Bounded::new(value.to_string())
+    }
+}
+
+impl<T: BoundedLen + Serialize, const MAX: u32> Serialize for Bounded<T, MAX> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        check_bound(self.0.bounded_len(), MAX).map_err(ser::Error::custom)?;
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: BoundedLen + Deserialize<'de>, const MAX: u32> Deserialize<'de> for Bounded<T, MAX> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        check_bound(value.bounded_len(), MAX).map_err(de::Error::custom)?;
+        Ok(Bounded(value))
+    }
+}
+
+/// XDR fixed-length array `T name[N]`, which unlike `T name<>` has no length on the wire.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FixedArray<T, const N: usize>(pub [T; N]);
+
+impl<T, const N: usize> Deref for FixedArray<T, N> {
+    type Target = [T; N];
+
+    fn deref(&self) -> &[T; N] {
+        &self.0
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for FixedArray<T, N> {
+    fn from(array: [T; N]) -> Self {
+        FixedArray(array)
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for FixedArray<T, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for element in self.0.iter() {
+            tuple.serialize_element(element)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for FixedArray<T, N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FixedArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for FixedArrayVisitor<T, N> {
+            type Value = FixedArray<T, N>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an array of {} elements", N)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<FixedArray<T, N>, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut elements = Vec::with_capacity(N);
+                while elements.len() < N {
+                    match seq.next_element()? {
+                        Some(element) => elements.push(element),
+                        None => return Err(de::Error::invalid_length(elements.len(), &self)),
+                    }
+                }
+                match <[T; N]>::try_from(elements) {
+                    Ok(array) => Ok(FixedArray(array)),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+
+        deserializer.deserialize_tuple(N, FixedArrayVisitor(PhantomData))
+    }
+}
+
+/// An XDR linked list, `struct node { T item; node *next; }` reached through a `node *`, held as
+/// a Vec. Each entry goes out as TRUE followed by the entry and the list ends with FALSE, the same
+/// bytes as nested `Option<Box<node>>` but without recursing once per entry on long lists.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct List<T>(pub Vec<T>);
+
+// Empty, whether or not T has a default
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List(Vec::new())
+    }
+}
+
+impl<T> Deref for List<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+impl<T> From<Vec<T>> for List<T> {
+    fn from(entries: Vec<T>) -> Self {
+        List(entries)
+    }
+}
+
+impl<T: Serialize> Serialize for List<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(2 * self.0.len() + 1)?;
+        for entry in self.0.iter() {
+            tuple.serialize_element(&true)?;
+            tuple.serialize_element(entry)?;
+        }
+        tuple.serialize_element(&false)?;
+        tuple.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for List<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ListVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for ListVisitor<T> {
+            type Value = List<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a linked list")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<List<T>, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut entries = Vec::new();
+                loop {
+                    match seq.next_element::<bool>()? {
+                        Some(true) => {}
+                        Some(false) => return Ok(List(entries)),
+                        None => return Err(de::Error::invalid_length(2 * entries.len(), &self)),
+                    }
+                    match seq.next_element()? {
+                        Some(entry) => entries.push(entry),
+                        None => {
+                            return Err(de::Error::invalid_length(2 * entries.len() + 1, &self))
+                        }
+                    }
+                }
+            }
+        }
+
+        // The tuple length is only an upper bound, the FALSE at the end is what stops the list
+        deserializer.deserialize_tuple(usize::MAX, ListVisitor(PhantomData))
+    }
+}
+
+/// XDR fixed-length `opaque name[N]`: exactly N bytes, padded to a multiple of 4.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FixedOpaque<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Default for FixedOpaque<N> {
+    fn default() -> Self {
+        FixedOpaque([0; N])
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for FixedOpaque<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for FixedOpaque<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        FixedOpaque(bytes)
+    }
+}
+
+// The padding goes out as part of the same tuple of raw bytes
+impl<const N: usize> Serialize for FixedOpaque<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let pad = crate::serializer::padding(N);
+        let mut tuple = serializer.serialize_tuple(N + pad)?;
+        for byte in self.0.iter().chain([0; 3][..pad].iter()) {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedOpaque<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FixedOpaqueVisitor<const N: usize>;
+
+        impl<'de, const N: usize> Visitor<'de> for FixedOpaqueVisitor<N> {
+            type Value = FixedOpaque<N>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "{} bytes of opaque data", N)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<FixedOpaque<N>, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut bytes = [0; N];
+                let total = N + crate::serializer::padding(N);
+                for i in 0..total {
+                    let byte: u8 = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                    if let Some(slot) = bytes.get_mut(i) {
+                        *slot = byte;
+                    }
+                }
+                Ok(FixedOpaque(bytes))
+            }
+        }
+
+        let total = N + crate::serializer::padding(N);
+        deserializer.deserialize_tuple(total, FixedOpaqueVisitor)
+    }
+}
+
+/// XDR `quadruple`: an IEEE-754 binary128 value kept as its 16 raw big-endian bytes.
+///
+/// Rust has no native 128-bit float, so the bytes are carried through unchanged and
+/// can be converted to and from `f64` when an approximate value is good enough.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Quadruple(pub [u8; 16]);
+
+impl Quadruple {
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Quadruple(bytes)
+    }
+
+    pub fn to_bytes(self) -> [u8; 16] {
+        self.0
+    }
+
+    pub fn from_bits(bits: u128) -> Self {
+        Quadruple(bits.to_be_bytes())
+    }
+
+    pub fn to_bits(self) -> u128 {
+        u128::from_be_bytes(self.0)
+    }
+
+    // Every f64 is exactly representable as a binary128, so this direction never loses precision.
+    pub fn from_f64(value: f64) -> Self {
+        let bits = value.to_bits();
+        let sign = u128::from(bits >> 63) << 127;
+        let exponent = ((bits >> F64_MANTISSA_BITS) & 0x7FF) as i32;
+        let mantissa = u128::from(bits & ((1 << F64_MANTISSA_BITS) - 1));
+
+        let rest = match exponent {
+            0 if mantissa == 0 => 0,
+            0 => {
+                // Subnormal f64, normalise it since binary128 has the exponent range to spare
+                let top = 127 - mantissa.leading_zeros();
+                let exponent = top as i32 - F64_EXPONENT_BIAS - F64_MANTISSA_BITS as i32 + 1;
+                let fraction = (mantissa << (F128_MANTISSA_BITS - top)) & f128_mantissa_mask();
+                (((exponent + F128_EXPONENT_BIAS) as u128) << F128_MANTISSA_BITS) | fraction
+            }
+            0x7FF => (F128_EXPONENT_MAX << F128_MANTISSA_BITS) | (mantissa << MANTISSA_SHIFT),
+            _ => {
+                let exponent = (exponent - F64_EXPONENT_BIAS + F128_EXPONENT_BIAS) as u128;
+                (exponent << F128_MANTISSA_BITS) | (mantissa << MANTISSA_SHIFT)
+            }
+        };
+        Quadruple::from_bits(sign | rest)
+    }
+
+    // Rounds to nearest, ties to even. Out of range values become infinities or zeroes.
+    pub fn to_f64(self) -> f64 {
+        let bits = self.to_bits();
+        let sign = ((bits >> 127) as u64) << 63;
+        let exponent = ((bits >> F128_MANTISSA_BITS) & F128_EXPONENT_MAX) as i32;
+        let mantissa = bits & f128_mantissa_mask();
+
+        let rest = if exponent == F128_EXPONENT_MAX as i32 {
+            let payload = (mantissa >> MANTISSA_SHIFT) as u64;
+            if mantissa != 0 && payload == 0 {
+                // NaN whose payload lives only in the low bits, keep it a NaN
+                0x7FF8_0000_0000_0000
+            } else {
+                0x7FF0_0000_0000_0000 | payload
+            }
+        } else if exponent == 0 {
+            // Zero, or a binary128 subnormal which is far below the smallest f64
+            0
+        } else {
+            let unbiased = exponent - F128_EXPONENT_BIAS;
+            let significand = mantissa | (1 << F128_MANTISSA_BITS);
+            if unbiased > F64_EXPONENT_BIAS {
+                0x7FF0_0000_0000_0000
+            } else if unbiased >= 1 - F64_EXPONENT_BIAS {
+                // Adding the rounded significand (implicit bit included) lets a rounding
+                // carry ripple into the exponent, and up to infinity if need be.
+                let biased = (unbiased + F64_EXPONENT_BIAS - 1) as u64;
+                (biased << F64_MANTISSA_BITS) + round_shift(significand, MANTISSA_SHIFT) as u64
+            } else {
+                let shift = MANTISSA_SHIFT as i32 + (1 - F64_EXPONENT_BIAS - unbiased);
+                round_shift(significand, shift as u32) as u64
+            }
+        };
+        f64::from_bits(sign | rest)
+    }
+}
+
+fn f128_mantissa_mask() -> u128 {
+    (1 << F128_MANTISSA_BITS) - 1
+}
+
+fn round_shift(value: u128, shift: u32) -> u128 {
+    if shift == 0 {
+        return value;
+    }
+    if shift >= 128 {
+        return 0;
+    }
+    let quotient = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if remainder > half || (remainder == half && quotient & 1 == 1) {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+impl From<f64> for Quadruple {
+    fn from(value: f64) -> Self {
+        Quadruple::from_f64(value)
+    }
+}
+
+impl From<Quadruple> for f64 {
+    fn from(value: Quadruple) -> Self {
+        value.to_f64()
+    }
+}
+
+impl Serialize for Quadruple {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u128(self.to_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for Quadruple {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct QuadrupleVisitor;
+
+        impl<'de> Visitor<'de> for QuadrupleVisitor {
+            type Value = Quadruple;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("16 bytes of binary128")
+            }
+
+            fn visit_u128<E>(self, value: u128) -> Result<Quadruple, E>
+            where
+                E: de::Error,
+            {
+                Ok(Quadruple::from_bits(value))
+            }
+        }
+
+        deserializer.deserialize_u128(QuadrupleVisitor)
+    }
+}
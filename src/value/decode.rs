@@ -0,0 +1,158 @@
+// Decoding with a schema goes through the regular Deserializer one primitive at a time, so
+// lengths, padding and byte counts work exactly as they do for typed decoding.
+
+use crate::errors::{DecoderResult, EncoderError};
+use crate::trace::Trace;
+use crate::value::{check_length, union_arm, XdrSchema, XdrType, XdrValue};
+use crate::{Deserializer, Opaque, Quadruple};
+
+use serde::Deserialize;
+use std::io::Read;
+
+// How deeply types may nest while decoding. Each entry of a `.x` linked list is a level or two,
+// and without a limit a long one runs the stack out rather than failing. This much fits in the
+// 2 MB stack of a spawned thread even in a debug build; `List<T>` handles longer lists when
+// there's a Rust type to decode into.
+pub const MAX_DEPTH: usize = 500;
+
+pub fn decode_with_schema(schema: &XdrSchema, bytes: &[u8]) -> DecoderResult<(XdrValue, usize)> {
+    let mut de = Deserializer::new(bytes);
+    let value = schema.decode(&mut de)?;
+    Ok((value, de.get_bytes_consumed()))
+}
+
+// Decodes with tracing on, handing back the trace whether or not decoding succeeded
+pub fn trace_with_schema(
+    schema: &XdrSchema,
+    bytes: &[u8],
+) -> (DecoderResult<(XdrValue, usize)>, Trace) {
+    let mut de = Deserializer::new(bytes).trace(true);
+    let res = schema
+        .decode(&mut de)
+        .map(|value| (value, de.get_bytes_consumed()));
+    (res, de.take_trace().unwrap_or_default())
+}
+
+impl XdrSchema {
+    pub fn decode<R: Read>(&self, de: &mut Deserializer<R>) -> DecoderResult<XdrValue> {
+        self.decode_type(self.root(), de, 0)
+    }
+
+    fn decode_type<R: Read>(
+        &self,
+        ty: &XdrType,
+        de: &mut Deserializer<R>,
+        depth: usize,
+    ) -> DecoderResult<XdrValue> {
+        if depth > MAX_DEPTH {
+            return Err(EncoderError::Unknown(format!(
+                "values nest more than {} levels deep",
+                MAX_DEPTH
+            )));
+        }
+        let depth = depth + 1;
+        let ty = self.resolve(ty)?;
+        Ok(match *ty {
+            XdrType::Array(ref element, max) => {
+                let count = u32::deserialize(&mut *de)?;
+                de.relabel("length");
+                check_length(count as usize, max)?;
+                self.decode_elements(element, count, de, depth)?
+            }
+            XdrType::FixedArray(ref element, len) => {
+                self.decode_elements(element, len, de, depth)?
+            }
+            XdrType::Optional(ref inner) => {
+                let present = bool::deserialize(&mut *de)?;
+                de.relabel("option");
+                if present {
+                    XdrValue::Optional(Some(Box::new(self.decode_type(inner, de, depth)?)))
+                } else {
+                    XdrValue::Optional(None)
+                }
+            }
+            XdrType::Struct(ref fields) => {
+                let mut values = Vec::with_capacity(fields.len());
+                for (name, field) in fields {
+                    de.enter(|| format!(".{}", name));
+                    values.push((name.clone(), self.decode_type(field, de, depth)?));
+                    de.leave();
+                }
+                XdrValue::Struct { fields: values }
+            }
+            XdrType::Union {
+                ref disc,
+                ref arms,
+                ref default,
+            } => {
+                let disc = self.decode_type(disc, de, depth)?;
+                let arm = union_arm(&disc, arms, default.as_deref())?;
+                XdrValue::Union {
+                    disc: Box::new(disc),
+                    arm: Box::new(self.decode_type(arm, de, depth)?),
+                }
+            }
+            _ => decode_leaf(ty, de)?,
+        })
+    }
+
+    // The count comes off the wire, so nothing is reserved up front
+    fn decode_elements<R: Read>(
+        &self,
+        element: &XdrType,
+        count: u32,
+        de: &mut Deserializer<R>,
+        depth: usize,
+    ) -> DecoderResult<XdrValue> {
+        let mut elements = Vec::new();
+        for index in 0..count {
+            de.enter(|| format!("[{}]", index));
+            elements.push(self.decode_type(element, de, depth)?);
+            de.leave();
+        }
+        Ok(XdrValue::Array(elements))
+    }
+}
+
+// Types that don't contain other types. Kept out of decode_type so the frame that recursion
+// repeats stays small.
+fn decode_leaf<R: Read>(ty: &XdrType, de: &mut Deserializer<R>) -> DecoderResult<XdrValue> {
+    Ok(match *ty {
+        XdrType::Int => XdrValue::Int(i32::deserialize(&mut *de)?),
+        XdrType::UInt => XdrValue::UInt(u32::deserialize(&mut *de)?),
+        XdrType::Hyper => XdrValue::Hyper(i64::deserialize(&mut *de)?),
+        XdrType::UHyper => XdrValue::UHyper(u64::deserialize(&mut *de)?),
+        XdrType::Float => XdrValue::Float(f32::deserialize(&mut *de)?),
+        XdrType::Double => XdrValue::Double(f64::deserialize(&mut *de)?),
+        XdrType::Quadruple => XdrValue::Quadruple(Quadruple::deserialize(&mut *de)?),
+        XdrType::Bool => XdrValue::Bool(bool::deserialize(&mut *de)?),
+        XdrType::Void => XdrValue::Void,
+        XdrType::Enum(ref variants) => {
+            let value = i32::deserialize(&mut *de)?;
+            match variants.iter().find(|(_, v)| *v == value) {
+                Some((name, _)) => XdrValue::Enum {
+                    name: name.clone(),
+                    value,
+                },
+                None => {
+                    return Err(EncoderError::Unknown(format!(
+                        "{} isn't one of the enum's values",
+                        value
+                    )))
+                }
+            }
+        }
+        XdrType::String(max) => {
+            let string = String::deserialize(&mut *de)?;
+            check_length(string.len(), max)?;
+            XdrValue::String(string)
+        }
+        XdrType::Opaque(max) => {
+            let Opaque(bytes) = Opaque::deserialize(&mut *de)?;
+            check_length(bytes.len(), max)?;
+            XdrValue::Opaque(bytes)
+        }
+        XdrType::FixedOpaque(len) => XdrValue::Opaque(de.read_fixed(len as usize, "bytes")?),
+        _ => unreachable!(),
+    })
+}
@@ -0,0 +1,146 @@
+// The value is checked against the schema as it's written, so what comes out is exactly what
+// typed code would have written for the same message.
+
+use crate::errors::{EncoderError, EncoderResult};
+use crate::serializer::padding;
+use crate::value::{check_length, union_arm, XdrSchema, XdrType, XdrValue};
+use crate::Serializer;
+
+use serde::ser::{Serialize, Serializer as _};
+use std::io::Write;
+
+pub fn encode_with_schema(schema: &XdrSchema, value: &XdrValue) -> EncoderResult<Vec<u8>> {
+    let mut ser = Serializer::new(Vec::new());
+    schema.encode(value, &mut ser)?;
+    Ok(ser.into_inner())
+}
+
+fn mismatch(ty: &XdrType, value: &XdrValue) -> EncoderError {
+    EncoderError::Unknown(format!(
+        "expected {}, found {}",
+        ty.describe(),
+        value.describe()
+    ))
+}
+
+impl XdrSchema {
+    pub fn encode<W: Write>(&self, value: &XdrValue, ser: &mut Serializer<W>) -> EncoderResult<()> {
+        self.encode_type(self.root(), value, ser)
+    }
+
+    fn encode_type<W: Write>(
+        &self,
+        ty: &XdrType,
+        value: &XdrValue,
+        ser: &mut Serializer<W>,
+    ) -> EncoderResult<()> {
+        let ty = self.resolve(ty)?;
+        match (ty, value) {
+            (XdrType::Int, XdrValue::Int(v)) => v.serialize(&mut *ser),
+            (XdrType::UInt, XdrValue::UInt(v)) => v.serialize(&mut *ser),
+            (XdrType::Hyper, XdrValue::Hyper(v)) => v.serialize(&mut *ser),
+            (XdrType::UHyper, XdrValue::UHyper(v)) => v.serialize(&mut *ser),
+            (XdrType::Float, XdrValue::Float(v)) => v.serialize(&mut *ser),
+            (XdrType::Double, XdrValue::Double(v)) => v.serialize(&mut *ser),
+            (XdrType::Quadruple, XdrValue::Quadruple(v)) => v.serialize(&mut *ser),
+            (XdrType::Bool, XdrValue::Bool(v)) => v.serialize(&mut *ser),
+            (XdrType::Void, XdrValue::Void) => Ok(()),
+            (XdrType::Enum(variants), XdrValue::Enum { name, value }) => {
+                if !variants.iter().any(|(n, v)| n == name && v == value) {
+                    return Err(EncoderError::Unknown(format!(
+                        "`{}` = {} isn't one of the enum's variants",
+                        name, value
+                    )));
+                }
+                value.serialize(&mut *ser)
+            }
+            (XdrType::String(max), XdrValue::String(string)) => {
+                check_length(string.len(), *max)?;
+                string.serialize(&mut *ser)
+            }
+            (XdrType::Opaque(max), XdrValue::Opaque(bytes)) => {
+                check_length(bytes.len(), *max)?;
+                ser.serialize_bytes(bytes)
+            }
+            (XdrType::FixedOpaque(len), XdrValue::Opaque(bytes)) => {
+                if bytes.len() != *len as usize {
+                    return Err(EncoderError::Unknown(format!(
+                        "expected {} bytes of opaque data, found {}",
+                        len,
+                        bytes.len()
+                    )));
+                }
+                for byte in bytes.iter().chain(&[0; 3][..padding(bytes.len())]) {
+                    byte.serialize(&mut *ser)?;
+                }
+                Ok(())
+            }
+            (XdrType::Array(element, max), XdrValue::Array(elements)) => {
+                check_length(elements.len(), *max)?;
+                (elements.len() as u32).serialize(&mut *ser)?;
+                self.encode_elements(element, elements, ser)
+            }
+            (XdrType::FixedArray(element, len), XdrValue::Array(elements)) => {
+                if elements.len() != *len as usize {
+                    return Err(EncoderError::Unknown(format!(
+                        "expected {} elements, found {}",
+                        len,
+                        elements.len()
+                    )));
+                }
+                self.encode_elements(element, elements, ser)
+            }
+            (XdrType::Optional(inner), XdrValue::Optional(value)) => match *value {
+                Some(ref value) => {
+                    true.serialize(&mut *ser)?;
+                    self.encode_type(inner, value, ser)
+                }
+                None => false.serialize(&mut *ser),
+            },
+            (XdrType::Struct(fields), XdrValue::Struct { fields: values }) => {
+                if fields.len() != values.len() {
+                    return Err(EncoderError::Unknown(format!(
+                        "expected {} fields, found {}",
+                        fields.len(),
+                        values.len()
+                    )));
+                }
+                for ((name, field), (value_name, value)) in fields.iter().zip(values) {
+                    if name != value_name {
+                        return Err(EncoderError::Unknown(format!(
+                            "expected field `{}`, found `{}`",
+                            name, value_name
+                        )));
+                    }
+                    self.encode_type(field, value, ser)?;
+                }
+                Ok(())
+            }
+            (
+                XdrType::Union {
+                    disc: disc_ty,
+                    arms,
+                    default,
+                },
+                XdrValue::Union { disc, arm },
+            ) => {
+                self.encode_type(disc_ty, disc, ser)?;
+                let arm_ty = union_arm(disc, arms, default.as_deref())?;
+                self.encode_type(arm_ty, arm, ser)
+            }
+            (ty, value) => Err(mismatch(ty, value)),
+        }
+    }
+
+    fn encode_elements<W: Write>(
+        &self,
+        element: &XdrType,
+        elements: &[XdrValue],
+        ser: &mut Serializer<W>,
+    ) -> EncoderResult<()> {
+        for value in elements {
+            self.encode_type(element, value, ser)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+// A lossless JSON form of XdrValue. Hypers are strings since JSON numbers can't be relied on past
+// 53 bits, opaque data and quadruples are hex strings, enums are their variant names and unions
+// are `{"disc": ..., "arm": ...}`. Going back the other way needs the schema, which also makes it
+// lenient: numbers can be given as strings, enums by value and opaque data as
+// `{"base64": "..."}`.
+
+use crate::errors::{EncoderError, EncoderResult};
+use crate::value::{union_arm, XdrSchema, XdrType, XdrValue};
+use crate::Quadruple;
+
+use serde_json::{Map, Value as Json};
+use std::convert::TryFrom;
+
+pub fn to_json(value: &XdrValue) -> Json {
+    match *value {
+        XdrValue::Int(v) => Json::from(v),
+        XdrValue::UInt(v) => Json::from(v),
+        XdrValue::Hyper(v) => Json::String(v.to_string()),
+        XdrValue::UHyper(v) => Json::String(v.to_string()),
+        // By way of the shortest string for the f32, so 0.1 doesn't become 0.10000000149011612
+        XdrValue::Float(v) => float(v.to_string().parse().unwrap_or_else(|_| f64::from(v))),
+        XdrValue::Double(v) => float(v),
+        XdrValue::Quadruple(v) => Json::String(hex(&v.to_bytes())),
+        XdrValue::Bool(v) => Json::Bool(v),
+        XdrValue::Enum { ref name, .. } => Json::String(name.clone()),
+        XdrValue::String(ref v) => Json::String(v.clone()),
+        XdrValue::Opaque(ref v) => Json::String(hex(v)),
+        XdrValue::Array(ref elements) => Json::Array(elements.iter().map(to_json).collect()),
+        XdrValue::Struct { ref fields } => Json::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), to_json(value)))
+                .collect(),
+        ),
+        XdrValue::Union { ref disc, ref arm } => {
+            let mut union = Map::new();
+            union.insert(String::from("disc"), to_json(disc));
+            union.insert(String::from("arm"), to_json(arm));
+            Json::Object(union)
+        }
+        XdrValue::Optional(Some(ref value)) => to_json(value),
+        XdrValue::Optional(None) | XdrValue::Void => Json::Null,
+    }
+}
+
+// The schema's root type, read from JSON
+pub fn from_json(schema: &XdrSchema, json: &Json) -> EncoderResult<XdrValue> {
+    value(schema, schema.root(), json)
+}
+
+fn float(value: f64) -> Json {
+    match serde_json::Number::from_f64(value) {
+        Some(number) => Json::Number(number),
+        None if value.is_nan() => Json::String(String::from("NaN")),
+        None if value > 0.0 => Json::String(String::from("inf")),
+        None => Json::String(String::from("-inf")),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn mismatch<T>(ty: &XdrType, json: &Json) -> EncoderResult<T> {
+    let found = match *json {
+        Json::Null => "null",
+        Json::Bool(_) => "a bool",
+        Json::Number(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Array(_) => "an array",
+        Json::Object(_) => "an object",
+    };
+    Err(EncoderError::Unknown(format!(
+        "expected {}, found {}",
+        ty.describe(),
+        found
+    )))
+}
+
+fn value(schema: &XdrSchema, ty: &XdrType, json: &Json) -> EncoderResult<XdrValue> {
+    let ty = schema.resolve(ty)?;
+    Ok(match *ty {
+        XdrType::Int => XdrValue::Int(integer(ty, json)?),
+        XdrType::UInt => XdrValue::UInt(integer(ty, json)?),
+        XdrType::Hyper => XdrValue::Hyper(integer(ty, json)?),
+        XdrType::UHyper => XdrValue::UHyper(integer(ty, json)?),
+        XdrType::Float => XdrValue::Float(real(ty, json)? as f32),
+        XdrType::Double => XdrValue::Double(real(ty, json)?),
+        XdrType::Quadruple => {
+            let bytes = opaque(ty, json)?;
+            match <[u8; 16]>::try_from(&bytes[..]) {
+                Ok(bytes) => XdrValue::Quadruple(Quadruple::from_bytes(bytes)),
+                Err(_) => {
+                    return Err(EncoderError::Unknown(String::from(
+                        "a quadruple is 16 bytes of hex",
+                    )))
+                }
+            }
+        }
+        XdrType::Bool => match *json {
+            Json::Bool(v) => XdrValue::Bool(v),
+            _ => return mismatch(ty, json),
+        },
+        XdrType::Void => match *json {
+            Json::Null => XdrValue::Void,
+            _ => return mismatch(ty, json),
+        },
+        XdrType::Enum(ref variants) => {
+            let variant = match *json {
+                Json::String(ref name) => variants.iter().find(|(n, _)| n == name),
+                Json::Number(_) => {
+                    let value: i32 = integer(ty, json)?;
+                    variants.iter().find(|(_, v)| *v == value)
+                }
+                _ => return mismatch(ty, json),
+            };
+            match variant {
+                Some((name, value)) => XdrValue::Enum {
+                    name: name.clone(),
+                    value: *value,
+                },
+                None => {
+                    return Err(EncoderError::Unknown(format!(
+                        "{} isn't one of the enum's variants",
+                        json
+                    )))
+                }
+            }
+        }
+        XdrType::String(_) => match *json {
+            Json::String(ref v) => XdrValue::String(v.clone()),
+            _ => return mismatch(ty, json),
+        },
+        XdrType::Opaque(_) | XdrType::FixedOpaque(_) => XdrValue::Opaque(opaque(ty, json)?),
+        XdrType::Array(ref element, _) | XdrType::FixedArray(ref element, _) => match *json {
+            Json::Array(ref elements) => XdrValue::Array(
+                elements
+                    .iter()
+                    .map(|e| value(schema, element, e))
+                    .collect::<EncoderResult<_>>()?,
+            ),
+            _ => return mismatch(ty, json),
+        },
+        XdrType::Optional(ref inner) => match *json {
+            Json::Null => XdrValue::Optional(None),
+            _ => XdrValue::Optional(Some(Box::new(value(schema, inner, json)?))),
+        },
+        XdrType::Struct(ref fields) => {
+            let object = match *json {
+                Json::Object(ref object) => object,
+                _ => return mismatch(ty, json),
+            };
+            if let Some(unknown) = object.keys().find(|k| fields.iter().all(|(n, _)| n != *k)) {
+                return Err(EncoderError::Unknown(format!(
+                    "unknown field `{}`",
+                    unknown
+                )));
+            }
+            let mut values = Vec::with_capacity(fields.len());
+            for (name, field) in fields {
+                let json = object
+                    .get(name)
+                    .ok_or_else(|| EncoderError::Unknown(format!("missing field `{}`", name)))?;
+                values.push((name.clone(), value(schema, field, json)?));
+            }
+            XdrValue::Struct { fields: values }
+        }
+        XdrType::Union {
+            ref disc,
+            ref arms,
+            ref default,
+        } => {
+            let object = match *json {
+                Json::Object(ref object) => object,
+                _ => return mismatch(ty, json),
+            };
+            let disc = match object.get("disc") {
+                Some(json) => value(schema, disc, json)?,
+                None => return Err(EncoderError::Unknown(String::from("missing field `disc`"))),
+            };
+            let arm_ty = union_arm(&disc, arms, default.as_deref())?;
+            // Void arms can leave the arm out
+            let arm = value(schema, arm_ty, object.get("arm").unwrap_or(&Json::Null))?;
+            XdrValue::Union {
+                disc: Box::new(disc),
+                arm: Box::new(arm),
+            }
+        }
+        XdrType::Named(_) => unreachable!(),
+    })
+}
+
+fn integer<T: TryFrom<i128>>(ty: &XdrType, json: &Json) -> EncoderResult<T> {
+    let wide = match *json {
+        Json::Number(ref number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from)),
+        Json::String(ref string) => string.trim().parse().ok(),
+        _ => return mismatch(ty, json),
+    };
+    wide.and_then(|wide| T::try_from(wide).ok())
+        .ok_or_else(|| EncoderError::Unknown(format!("{} isn't {}", json, ty.describe())))
+}
+
+fn real(ty: &XdrType, json: &Json) -> EncoderResult<f64> {
+    let real = match *json {
+        Json::Number(ref number) => number.as_f64(),
+        Json::String(ref string) => string.trim().parse().ok(),
+        _ => return mismatch(ty, json),
+    };
+    real.ok_or_else(|| EncoderError::Unknown(format!("{} isn't {}", json, ty.describe())))
+}
+
+fn opaque(ty: &XdrType, json: &Json) -> EncoderResult<Vec<u8>> {
+    let bytes = match *json {
+        Json::String(ref hex) => from_hex(hex),
+        Json::Object(ref object) if object.len() == 1 => match object.get("base64") {
+            Some(Json::String(base64)) => from_base64(base64),
+            _ => return mismatch(ty, json),
+        },
+        _ => return mismatch(ty, json),
+    };
+    bytes.ok_or_else(|| EncoderError::Unknown(format!("{} isn't valid hex or base64", json)))
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            if pair.len() != 2 || !pair.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()
+        })
+        .collect()
+}
+
+fn from_base64(base64: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in base64.trim_end_matches('=').bytes() {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | u32::from(sextet);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    // A group can only end after 2 or 3 characters, with the bits past the last byte all zero
+    if count >= 6 || bits & ((1 << count) - 1) != 0 {
+        return None;
+    }
+    Some(bytes)
+}
@@ -0,0 +1,121 @@
+// Values decoded without a Rust type to decode into. XDR isn't self describing, so an XdrSchema
+// describing the layout stands in for the type, and the `.x` file for a message is enough to look
+// inside it.
+
+mod decode;
+mod encode;
+#[cfg(feature = "serde_json")]
+mod json;
+mod schema;
+
+pub use self::decode::{decode_with_schema, trace_with_schema, MAX_DEPTH};
+pub use self::encode::encode_with_schema;
+#[cfg(feature = "serde_json")]
+pub use self::json::{from_json, to_json};
+pub use self::schema::{XdrSchema, XdrType};
+
+use crate::errors::{EncoderError, EncoderResult};
+use crate::Quadruple;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum XdrValue {
+    Int(i32),
+    UInt(u32),
+    Hyper(i64),
+    UHyper(u64),
+    Float(f32),
+    Double(f64),
+    Quadruple(Quadruple),
+    Bool(bool),
+    Enum {
+        name: String,
+        value: i32,
+    },
+    String(String),
+    Opaque(Vec<u8>),
+    Array(Vec<XdrValue>),
+    Struct {
+        fields: Vec<(String, XdrValue)>,
+    },
+    Union {
+        disc: Box<XdrValue>,
+        arm: Box<XdrValue>,
+    },
+    Optional(Option<Box<XdrValue>>),
+    Void,
+}
+
+impl XdrValue {
+    // A struct field by name
+    pub fn field(&self, name: &str) -> Option<&XdrValue> {
+        match *self {
+            XdrValue::Struct { ref fields } => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    // The value a union picks its arm by, for the values that can be discriminants
+    pub fn discriminant(&self) -> Option<i64> {
+        match *self {
+            XdrValue::Int(value) => Some(i64::from(value)),
+            XdrValue::UInt(value) => Some(i64::from(value)),
+            XdrValue::Bool(value) => Some(i64::from(value)),
+            XdrValue::Enum { value, .. } => Some(i64::from(value)),
+            _ => None,
+        }
+    }
+
+    // For error messages
+    pub(crate) fn describe(&self) -> &'static str {
+        match *self {
+            XdrValue::Int(_) => "an int",
+            XdrValue::UInt(_) => "an unsigned int",
+            XdrValue::Hyper(_) => "a hyper",
+            XdrValue::UHyper(_) => "an unsigned hyper",
+            XdrValue::Float(_) => "a float",
+            XdrValue::Double(_) => "a double",
+            XdrValue::Quadruple(_) => "a quadruple",
+            XdrValue::Bool(_) => "a bool",
+            XdrValue::Enum { .. } => "an enum",
+            XdrValue::String(_) => "a string",
+            XdrValue::Opaque(_) => "opaque data",
+            XdrValue::Array(_) => "an array",
+            XdrValue::Struct { .. } => "a struct",
+            XdrValue::Union { .. } => "a union",
+            XdrValue::Optional(_) => "an optional value",
+            XdrValue::Void => "void",
+        }
+    }
+}
+
+fn check_length(length: usize, max: Option<u32>) -> EncoderResult<()> {
+    match max {
+        Some(max) if length > max as usize => Err(EncoderError::Unknown(format!(
+            "length {} is over the maximum of {}",
+            length, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// The arm a union's discriminant picks. Hand-built schemas can switch on types that aren't
+// discriminants, so that's an error here rather than something the schema rules out.
+fn union_arm<'a>(
+    disc: &XdrValue,
+    arms: &'a [(i64, XdrType)],
+    default: Option<&'a XdrType>,
+) -> EncoderResult<&'a XdrType> {
+    let value = disc.discriminant().ok_or_else(|| {
+        EncoderError::Unknown(String::from(
+            "a union discriminant has to be an int, unsigned int, bool or enum",
+        ))
+    })?;
+    arms.iter()
+        .find(|(case, _)| *case == value)
+        .map(|(_, arm)| arm)
+        .or(default)
+        .ok_or_else(|| EncoderError::Unknown(format!("no union arm for discriminant {}", value)))
+}
@@ -0,0 +1,112 @@
+use crate::errors::{DecoderResult, EncoderError};
+use crate::idl::IdlError;
+
+use std::collections::HashMap;
+
+// Lengths are the maximum for variable length types, where None means unbounded
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum XdrType {
+    Int,
+    UInt,
+    Hyper,
+    UHyper,
+    Float,
+    Double,
+    Quadruple,
+    Bool,
+    Void,
+    Enum(Vec<(String, i32)>),
+    String(Option<u32>),
+    Opaque(Option<u32>),
+    FixedOpaque(u32),
+    Array(Box<XdrType>, Option<u32>),
+    FixedArray(Box<XdrType>, u32),
+    Optional(Box<XdrType>),
+    Struct(Vec<(String, XdrType)>),
+    // Arms by case value, void arms being XdrType::Void
+    Union {
+        disc: Box<XdrType>,
+        arms: Vec<(i64, XdrType)>,
+        default: Option<Box<XdrType>>,
+    },
+    // One of the schema's named types, which is how recursive types are described
+    Named(String),
+}
+
+impl XdrType {
+    // For error messages
+    pub(crate) fn describe(&self) -> &'static str {
+        match *self {
+            XdrType::Int => "an int",
+            XdrType::UInt => "an unsigned int",
+            XdrType::Hyper => "a hyper",
+            XdrType::UHyper => "an unsigned hyper",
+            XdrType::Float => "a float",
+            XdrType::Double => "a double",
+            XdrType::Quadruple => "a quadruple",
+            XdrType::Bool => "a bool",
+            XdrType::Void => "void",
+            XdrType::Enum(_) => "an enum",
+            XdrType::String(_) => "a string",
+            XdrType::Opaque(_) | XdrType::FixedOpaque(_) => "opaque data",
+            XdrType::Array(..) | XdrType::FixedArray(..) => "an array",
+            XdrType::Optional(_) => "an optional value",
+            XdrType::Struct(_) => "a struct",
+            XdrType::Union { .. } => "a union",
+            XdrType::Named(_) => "a named type",
+        }
+    }
+}
+
+// The type of a value, along with the named types it refers to
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XdrSchema {
+    root: XdrType,
+    types: HashMap<String, XdrType>,
+}
+
+impl XdrSchema {
+    pub fn new(root: XdrType) -> Self {
+        XdrSchema {
+            root,
+            types: HashMap::new(),
+        }
+    }
+
+    pub fn define(mut self, name: &str, ty: XdrType) -> Self {
+        self.types.insert(name.to_string(), ty);
+        self
+    }
+
+    // The schema for type `name` of a specification, e.g. `Device_ReadResp` from vxi11.x
+    pub fn from_idl(source: &str, name: &str) -> Result<Self, IdlError> {
+        crate::idl::schema(source, name)
+    }
+
+    pub fn root(&self) -> &XdrType {
+        &self.root
+    }
+
+    pub fn get(&self, name: &str) -> Option<&XdrType> {
+        self.types.get(name)
+    }
+
+    // Follows named types to what they stand for, which is never XdrType::Named
+    pub fn resolve<'a>(&'a self, mut ty: &'a XdrType) -> DecoderResult<&'a XdrType> {
+        // Typedefs can't go round in circles for longer than there are types
+        for _ in 0..=self.types.len() {
+            match *ty {
+                XdrType::Named(ref name) => {
+                    ty = self
+                        .types
+                        .get(name)
+                        .ok_or_else(|| EncoderError::Unknown(format!("unknown type `{}`", name)))?;
+                }
+                _ => return Ok(ty),
+            }
+        }
+        Err(EncoderError::Unknown(String::from(
+            "the schema's named types refer to each other in a loop",
+        )))
+    }
+}
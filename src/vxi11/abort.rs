@@ -0,0 +1,76 @@
+// The DEVICE_ASYNC abort channel. Its only procedure, device_abort, interrupts an in-progress
+// call on the core channel, so it lives on a separate connection to the `abort_port` create_link
+// returned.
+
+use crate::rpc::{Call, ProcError, RpcClient, RpcProgram, RpcResult};
+use crate::vxi11::{
+    DeviceError, DeviceErrorCode, DeviceLink, DEVICE_ABORT, DEVICE_ASYNC, DEVICE_ASYNC_VERSION,
+};
+
+use std::io;
+use std::net::{IpAddr, ToSocketAddrs};
+
+pub struct AbortClient {
+    client: RpcClient,
+}
+
+impl AbortClient {
+    pub fn new(client: RpcClient) -> Self {
+        AbortClient { client }
+    }
+
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        RpcClient::connect_tcp(addr).map(AbortClient::new)
+    }
+
+    // Connects to the abort port on the same host as the core channel
+    pub fn connect_port(host: IpAddr, abort_port: u32) -> io::Result<Self> {
+        AbortClient::connect((host, abort_port as u16))
+    }
+
+    pub fn abort(&mut self, lid: DeviceLink) -> RpcResult<DeviceError> {
+        self.client
+            .call(DEVICE_ASYNC, DEVICE_ASYNC_VERSION, DEVICE_ABORT, &lid)
+    }
+}
+
+// Serves device_abort by handing the link id to `handler`, typically to flag the link's
+// in-progress operation so it returns with DeviceErrorCode::ABORT
+pub struct AbortServer<F> {
+    handler: F,
+}
+
+impl<F> AbortServer<F>
+where
+    F: Fn(DeviceLink) -> DeviceErrorCode + Send + Sync,
+{
+    pub fn new(handler: F) -> Self {
+        AbortServer { handler }
+    }
+}
+
+impl<F> RpcProgram for AbortServer<F>
+where
+    F: Fn(DeviceLink) -> DeviceErrorCode + Send + Sync,
+{
+    fn program(&self) -> u32 {
+        DEVICE_ASYNC
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (DEVICE_ASYNC_VERSION, DEVICE_ASYNC_VERSION)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        match call.proc() {
+            0 => Ok(()),
+            DEVICE_ABORT => {
+                let lid: DeviceLink = call.args()?;
+                call.reply(&DeviceError {
+                    error: (self.handler)(lid),
+                })
+            }
+            _ => Err(ProcError::ProcUnavail),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Errors from talking to an instrument: RPC failures, or an error code from the device
+
+use crate::errors::EncoderError;
+use crate::rpc::RpcError;
+use crate::vxi11::DeviceErrorCode;
+
+use std::fmt::{self, Display};
+use std::{error, io};
+
+#[derive(Debug)]
+pub enum Vxi11Error {
+    Rpc(RpcError),
+    Device(DeviceErrorCode),
+    // The port mapper has no TCP mapping for the core channel
+    NotRegistered,
+    // A write the device accepted none of, or a read that returned no data and no END
+    Stalled,
+}
+
+impl From<RpcError> for Vxi11Error {
+    fn from(err: RpcError) -> Vxi11Error {
+        Vxi11Error::Rpc(err)
+    }
+}
+
+impl From<EncoderError> for Vxi11Error {
+    fn from(err: EncoderError) -> Vxi11Error {
+        Vxi11Error::Rpc(err.into())
+    }
+}
+
+impl From<io::Error> for Vxi11Error {
+    fn from(err: io::Error) -> Vxi11Error {
+        Vxi11Error::Rpc(err.into())
+    }
+}
+
+impl error::Error for Vxi11Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Vxi11Error::Rpc(ref inner) => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Vxi11Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Vxi11Error::Rpc(ref error) => fmt::Display::fmt(error, fmt),
+            Vxi11Error::Device(code) => write!(fmt, "device error: {}", code),
+            Vxi11Error::NotRegistered => {
+                write!(
+                    fmt,
+                    "no VXI-11 core channel registered with the port mapper"
+                )
+            }
+            Vxi11Error::Stalled => write!(fmt, "device made no progress on a read or write"),
+        }
+    }
+}
+
+impl From<Vxi11Error> for EncoderError {
+    fn from(err: Vxi11Error) -> EncoderError {
+        match err {
+            Vxi11Error::Rpc(inner) => inner.into(),
+            other => EncoderError::Unknown(other.to_string()),
+        }
+    }
+}
+
+pub type Vxi11Result<T> = Result<T, Vxi11Error>;
@@ -0,0 +1,346 @@
+// Controller side of a VXI-11 link: finds the core channel through the port mapper, creates a
+// link to one device on it and wraps the core procedures, with helpers for SCPI style text I/O.
+
+use crate::portmap::{PortmapClient, IPPROTO_TCP, PMAP_PORT};
+use crate::rpc::{RpcClient, RpcResult};
+use crate::vxi11::*;
+use crate::Opaque;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+pub const DEFAULT_IO_TIMEOUT: Duration = Duration::from_secs(10);
+// Bytes asked for by each device_read; longer responses take several reads
+pub const DEFAULT_READ_SIZE: u32 = 1024 * 1024;
+// Allowed on top of the I/O and lock timeouts for the RPC itself to complete
+const RPC_MARGIN: Duration = Duration::from_secs(5);
+
+pub struct Vxi11Instrument {
+    client: RpcClient,
+    host: IpAddr,
+    lid: DeviceLink,
+    abort_port: u32,
+    max_recv_size: u32,
+    io_timeout: Duration,
+    lock_timeout: Duration,
+    term_char: Option<u8>,
+    read_size: u32,
+    linked: bool,
+}
+
+impl Vxi11Instrument {
+    // Opens `device` (e.g. "inst0" or "gpib0,5") on `host`, looking the core channel up with the
+    // port mapper on port 111
+    pub fn connect(host: &str, device: &str) -> Vxi11Result<Self> {
+        Vxi11Instrument::connect_via((host, PMAP_PORT), device)
+    }
+
+    // Like `connect`, with the port mapper at `portmapper` instead of the standard port
+    pub fn connect_via<A: ToSocketAddrs>(portmapper: A, device: &str) -> Vxi11Result<Self> {
+        let portmapper = portmapper.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to")
+        })?;
+        let port = PortmapClient::connect_tcp(portmapper)?.getport(
+            DEVICE_CORE,
+            DEVICE_CORE_VERSION,
+            IPPROTO_TCP,
+        )?;
+        if port == 0 {
+            return Err(Vxi11Error::NotRegistered);
+        }
+        Vxi11Instrument::connect_core(SocketAddr::new(portmapper.ip(), port), device)
+    }
+
+    // Skips the port mapper and talks to the core channel at `addr` directly
+    pub fn connect_core(addr: SocketAddr, device: &str) -> Vxi11Result<Self> {
+        let mut instrument = Vxi11Instrument {
+            client: RpcClient::connect_tcp(addr)?,
+            host: addr.ip(),
+            lid: 0,
+            abort_port: 0,
+            max_recv_size: 0,
+            io_timeout: DEFAULT_IO_TIMEOUT,
+            lock_timeout: Duration::from_secs(0),
+            term_char: None,
+            read_size: DEFAULT_READ_SIZE,
+            linked: false,
+        };
+        let parms = CreateLinkParms {
+            client_id: std::process::id() as i32,
+            lock_device: false,
+            lock_timeout: 0,
+            device: device.to_string(),
+        };
+        let resp: CreateLinkResp = instrument.call(CREATE_LINK, &parms)?;
+        check(resp.error)?;
+        instrument.lid = resp.lid;
+        instrument.abort_port = resp.abort_port;
+        // The spec requires at least 1024, but don't trust a device into an endless loop
+        instrument.max_recv_size = resp.max_recv_size.max(1);
+        instrument.linked = true;
+        Ok(instrument)
+    }
+
+    pub fn io_timeout(mut self, timeout: Duration) -> Self {
+        self.io_timeout = timeout;
+        self
+    }
+
+    // How long calls wait for a lock held by another link. Zero fails straight away.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    // Also stop reads at `term_char`, not just at END
+    pub fn term_char(mut self, term_char: Option<u8>) -> Self {
+        self.term_char = term_char;
+        self
+    }
+
+    pub fn read_size(mut self, size: u32) -> Self {
+        self.read_size = size.max(1);
+        self
+    }
+
+    pub fn link_id(&self) -> DeviceLink {
+        self.lid
+    }
+
+    pub fn max_recv_size(&self) -> u32 {
+        self.max_recv_size
+    }
+
+    // Writes `data` in chunks of at most max_recv_size, flagging END on the last one.
+    // Returns the number of bytes written, or Stalled if the device stops accepting any.
+    pub fn write(&mut self, data: &[u8]) -> Vxi11Result<usize> {
+        let mut written = 0;
+        loop {
+            let chunk = &data[written..];
+            let chunk = &chunk[..chunk.len().min(self.max_recv_size as usize)];
+            let mut flags = self.flags();
+            if written + chunk.len() == data.len() {
+                flags |= DeviceFlags::END;
+            }
+            let parms = DeviceWriteParms {
+                lid: self.lid,
+                io_timeout: millis(self.io_timeout),
+                lock_timeout: millis(self.lock_timeout),
+                flags,
+                data: Opaque(chunk.to_vec()),
+            };
+            let resp: DeviceWriteResp = self.call(DEVICE_WRITE, &parms)?;
+            check(resp.error)?;
+            // An empty write has nothing to accept, so it's done once the device has the END
+            if data.is_empty() {
+                return Ok(0);
+            }
+            if resp.size == 0 {
+                return Err(Vxi11Error::Stalled);
+            }
+            written += (resp.size as usize).min(chunk.len());
+            if written == data.len() {
+                return Ok(written);
+            }
+        }
+    }
+
+    // Reads until the device signals END, or the termination character if one is set. A read that
+    // ends for any other reason without returning data is an error rather than a retry.
+    pub fn read(&mut self) -> Vxi11Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut flags = self.flags();
+        if self.term_char.is_some() {
+            flags |= DeviceFlags::TERMCHRSET;
+        }
+        loop {
+            let parms = DeviceReadParms {
+                lid: self.lid,
+                request_size: self.read_size,
+                io_timeout: millis(self.io_timeout),
+                lock_timeout: millis(self.lock_timeout),
+                flags,
+                term_char: u32::from(self.term_char.unwrap_or(0)),
+            };
+            let resp: DeviceReadResp = self.call(DEVICE_READ, &parms)?;
+            check(resp.error)?;
+            if resp.reason.contains(ReadReason::END) || resp.reason.contains(ReadReason::CHR) {
+                data.extend_from_slice(resp.data.as_ref());
+                return Ok(data);
+            }
+            // Asking again would only get the same empty answer
+            if resp.data.as_ref().is_empty() {
+                return Err(Vxi11Error::Stalled);
+            }
+            data.extend_from_slice(resp.data.as_ref());
+        }
+    }
+
+    pub fn write_str(&mut self, command: &str) -> Vxi11Result<()> {
+        self.write(command.as_bytes()).map(|_| ())
+    }
+
+    // Sends `command` and reads the response, e.g. `query("*IDN?")`
+    pub fn query_bytes(&mut self, command: &str) -> Vxi11Result<Vec<u8>> {
+        self.write_str(command)?;
+        self.read()
+    }
+
+    // Same as `query_bytes`, as text without the trailing newline
+    pub fn query(&mut self, command: &str) -> Vxi11Result<String> {
+        let response = self.query_bytes(command)?;
+        let response = String::from_utf8_lossy(&response);
+        Ok(response.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    pub fn identify(&mut self) -> Vxi11Result<String> {
+        self.query("*IDN?")
+    }
+
+    pub fn read_stb(&mut self) -> Vxi11Result<u8> {
+        let resp: DeviceReadStbResp = self.call(DEVICE_READSTB, &self.generic())?;
+        check(resp.error)?;
+        Ok(resp.stb as u8)
+    }
+
+    pub fn trigger(&mut self) -> Vxi11Result<()> {
+        self.generic_call(DEVICE_TRIGGER)
+    }
+
+    pub fn clear(&mut self) -> Vxi11Result<()> {
+        self.generic_call(DEVICE_CLEAR)
+    }
+
+    pub fn remote(&mut self) -> Vxi11Result<()> {
+        self.generic_call(DEVICE_REMOTE)
+    }
+
+    pub fn local(&mut self) -> Vxi11Result<()> {
+        self.generic_call(DEVICE_LOCAL)
+    }
+
+    // Waits up to `timeout` for another link to release the lock
+    pub fn lock(&mut self, timeout: Duration) -> Vxi11Result<()> {
+        let parms = DeviceLockParms {
+            lid: self.lid,
+            flags: DeviceFlags::WAITLOCK,
+            lock_timeout: millis(timeout),
+        };
+        let resp: DeviceError = self.call_timeout(DEVICE_LOCK, &parms, timeout + RPC_MARGIN)?;
+        check(resp.error)
+    }
+
+    pub fn unlock(&mut self) -> Vxi11Result<()> {
+        let lid = self.lid;
+        let resp: DeviceError = self.call(DEVICE_UNLOCK, &lid)?;
+        check(resp.error)
+    }
+
+    // Interrupts an in-progress call on the link over the abort channel
+    pub fn abort(&self) -> Vxi11Result<()> {
+        let resp = AbortClient::connect_port(self.host, self.abort_port)?.abort(self.lid)?;
+        check(resp.error)
+    }
+
+    // Asks the device to call device_intr_srq on `func`, see IntrChannel::remote_func
+    pub fn create_intr_chan(&mut self, func: &DeviceRemoteFunc) -> Vxi11Result<()> {
+        let resp: DeviceError = self.call(CREATE_INTR_CHAN, func)?;
+        check(resp.error)
+    }
+
+    pub fn destroy_intr_chan(&mut self) -> Vxi11Result<()> {
+        let resp: DeviceError = self.call(DESTROY_INTR_CHAN, &())?;
+        check(resp.error)
+    }
+
+    // Service requests are reported with `handle` once enabled
+    pub fn enable_srq(&mut self, enable: bool, handle: &[u8]) -> Vxi11Result<()> {
+        let parms = DeviceEnableSrqParms {
+            lid: self.lid,
+            enable,
+            handle: Opaque(handle.to_vec()),
+        };
+        let resp: DeviceError = self.call(DEVICE_ENABLE_SRQ, &parms)?;
+        check(resp.error)
+    }
+
+    // Destroys the link, which otherwise happens (ignoring errors) on drop
+    pub fn close(mut self) -> Vxi11Result<()> {
+        self.destroy_link()
+    }
+
+    fn destroy_link(&mut self) -> Vxi11Result<()> {
+        self.linked = false;
+        let lid = self.lid;
+        let resp: DeviceError = self.call(DESTROY_LINK, &lid)?;
+        check(resp.error)
+    }
+
+    fn flags(&self) -> DeviceFlags {
+        if self.lock_timeout > Duration::from_secs(0) {
+            DeviceFlags::WAITLOCK
+        } else {
+            DeviceFlags::NONE
+        }
+    }
+
+    fn generic(&self) -> DeviceGenericParms {
+        DeviceGenericParms {
+            lid: self.lid,
+            flags: self.flags(),
+            lock_timeout: millis(self.lock_timeout),
+            io_timeout: millis(self.io_timeout),
+        }
+    }
+
+    fn generic_call(&mut self, proc: u32) -> Vxi11Result<()> {
+        let resp: DeviceError = self.call(proc, &self.generic())?;
+        check(resp.error)
+    }
+
+    fn call<Args, Res>(&mut self, proc: u32, args: &Args) -> RpcResult<Res>
+    where
+        Args: Serialize,
+        Res: DeserializeOwned,
+    {
+        let timeout = self.io_timeout + self.lock_timeout + RPC_MARGIN;
+        self.call_timeout(proc, args, timeout)
+    }
+
+    fn call_timeout<Args, Res>(
+        &mut self,
+        proc: u32,
+        args: &Args,
+        timeout: Duration,
+    ) -> RpcResult<Res>
+    where
+        Args: Serialize,
+        Res: DeserializeOwned,
+    {
+        self.client
+            .call_timeout(DEVICE_CORE, DEVICE_CORE_VERSION, proc, args, timeout)
+    }
+}
+
+impl Drop for Vxi11Instrument {
+    fn drop(&mut self) {
+        if self.linked {
+            let _ = self.destroy_link();
+        }
+    }
+}
+
+fn check(error: DeviceErrorCode) -> Vxi11Result<()> {
+    if error.is_ok() {
+        Ok(())
+    } else {
+        Err(Vxi11Error::Device(error))
+    }
+}
+
+fn millis(duration: Duration) -> u32 {
+    duration.as_millis().min(u128::from(u32::MAX)) as u32
+}
@@ -0,0 +1,111 @@
+// The DEVICE_INTR interrupt channel. Roles are reversed here: the controller runs the server and
+// the instrument calls device_intr_srq on it, echoing the handle given to device_enable_srq.
+
+use crate::rpc::{Call, ProcError, RpcClient, RpcProgram, RpcResult, RpcServer, ServerHandle};
+use crate::vxi11::{
+    DeviceAddrFamily, DeviceRemoteFunc, DeviceSrqParms, DEVICE_INTR, DEVICE_INTR_SRQ,
+    DEVICE_INTR_VERSION,
+};
+use crate::Opaque;
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Instrument side of the channel, connected to the address create_intr_chan was given
+pub struct IntrClient {
+    client: RpcClient,
+}
+
+impl IntrClient {
+    pub fn new(client: RpcClient) -> Self {
+        IntrClient { client }
+    }
+
+    pub fn connect(func: &DeviceRemoteFunc) -> io::Result<Self> {
+        let addr = (Ipv4Addr::from(func.host_addr), func.host_port as u16);
+        let client = match func.prog_family {
+            DeviceAddrFamily::Tcp => RpcClient::connect_tcp(addr)?,
+            DeviceAddrFamily::Udp => RpcClient::connect_udp(addr)?,
+        };
+        Ok(IntrClient::new(client))
+    }
+
+    pub fn srq(&mut self, handle: &Opaque) -> RpcResult<()> {
+        let parms = DeviceSrqParms {
+            handle: handle.clone(),
+        };
+        self.client
+            .call(DEVICE_INTR, DEVICE_INTR_VERSION, DEVICE_INTR_SRQ, &parms)
+    }
+}
+
+// Forwards the handle of every device_intr_srq it receives to the paired Receiver
+pub struct IntrServer {
+    srqs: Sender<Opaque>,
+}
+
+impl IntrServer {
+    pub fn new() -> (Self, Receiver<Opaque>) {
+        let (srqs, rx) = mpsc::channel();
+        (IntrServer { srqs }, rx)
+    }
+}
+
+impl RpcProgram for IntrServer {
+    fn program(&self) -> u32 {
+        DEVICE_INTR
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (DEVICE_INTR_VERSION, DEVICE_INTR_VERSION)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        match call.proc() {
+            0 => Ok(()),
+            DEVICE_INTR_SRQ => {
+                let parms: DeviceSrqParms = call.args()?;
+                // Nobody listening any more isn't the instrument's problem
+                let _ = self.srqs.send(parms.handle);
+                Ok(())
+            }
+            _ => Err(ProcError::ProcUnavail),
+        }
+    }
+}
+
+// A running interrupt server, stopped when dropped
+pub struct IntrChannel {
+    handle: ServerHandle,
+}
+
+impl IntrChannel {
+    pub fn spawn<A: ToSocketAddrs>(addr: A) -> io::Result<(Self, Receiver<Opaque>)> {
+        let (program, rx) = IntrServer::new();
+        let mut server = RpcServer::new();
+        server.register(program);
+        let handle = server.spawn_tcp(addr)?;
+        Ok((IntrChannel { handle }, rx))
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.handle.local_addr()
+    }
+
+    // Arguments for create_intr_chan. `host` is the address the instrument should call back on,
+    // which the listening address can't tell when bound to 0.0.0.0.
+    pub fn remote_func(&self, host: Ipv4Addr) -> DeviceRemoteFunc {
+        DeviceRemoteFunc {
+            host_addr: u32::from(host),
+            host_port: u32::from(self.local_addr().port()),
+            prog_num: DEVICE_INTR,
+            prog_vers: DEVICE_INTR_VERSION,
+            prog_family: DeviceAddrFamily::Tcp,
+        }
+    }
+
+    pub fn shutdown(self) {
+        self.handle.shutdown();
+    }
+}
@@ -0,0 +1,297 @@
+// VXI-11 (TCP/IP Instrument Protocol, rev 1.0) channel types, from the RPCL in appendix B.
+//
+// The spec's `unsigned short` and `char` fields are 4 bytes on the wire like every other XDR
+// integer, so they're u32 here since u16 and u8 don't serialize as XDR integers.
+
+pub mod abort;
+mod errors;
+pub mod instrument;
+pub mod intr;
+pub mod server;
+
+pub use self::abort::{AbortClient, AbortServer};
+pub use self::errors::{Vxi11Error, Vxi11Result};
+pub use self::instrument::Vxi11Instrument;
+pub use self::intr::{IntrChannel, IntrClient, IntrServer};
+pub use self::server::{ScriptedDevice, SimulatedDevice, SimulatedServer, SimulatorHandle};
+
+use crate::{xdr_enum, Opaque};
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+pub const DEVICE_CORE: u32 = 0x0607AF;
+pub const DEVICE_CORE_VERSION: u32 = 1;
+
+pub const CREATE_LINK: u32 = 10;
+pub const DEVICE_WRITE: u32 = 11;
+pub const DEVICE_READ: u32 = 12;
+pub const DEVICE_READSTB: u32 = 13;
+pub const DEVICE_TRIGGER: u32 = 14;
+pub const DEVICE_CLEAR: u32 = 15;
+pub const DEVICE_REMOTE: u32 = 16;
+pub const DEVICE_LOCAL: u32 = 17;
+pub const DEVICE_LOCK: u32 = 18;
+pub const DEVICE_UNLOCK: u32 = 19;
+pub const DEVICE_ENABLE_SRQ: u32 = 20;
+pub const DEVICE_DOCMD: u32 = 22;
+pub const DESTROY_LINK: u32 = 23;
+pub const CREATE_INTR_CHAN: u32 = 25;
+pub const DESTROY_INTR_CHAN: u32 = 26;
+
+// The abort channel, served on the port create_link returns in `abort_port`
+pub const DEVICE_ASYNC: u32 = 0x0607B0;
+pub const DEVICE_ASYNC_VERSION: u32 = 1;
+pub const DEVICE_ABORT: u32 = 1;
+
+// The interrupt channel, served by the controller and called by the instrument
+pub const DEVICE_INTR: u32 = 0x0607B1;
+pub const DEVICE_INTR_VERSION: u32 = 1;
+pub const DEVICE_INTR_SRQ: u32 = 30;
+
+// `Device_Link`, the link id handed out by create_link
+pub type DeviceLink = i32;
+
+xdr_enum!(DeviceAddrFamily {
+    Tcp = 0,
+    Udp = 1,
+});
+
+// `Device_Flags`
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct DeviceFlags(pub i32);
+
+impl DeviceFlags {
+    pub const NONE: DeviceFlags = DeviceFlags(0);
+    // Wait for the lock instead of failing when another link holds it
+    pub const WAITLOCK: DeviceFlags = DeviceFlags(0x01);
+    // The data written ends with END
+    pub const END: DeviceFlags = DeviceFlags(0x08);
+    // `term_char` in Device_ReadParms is valid
+    pub const TERMCHRSET: DeviceFlags = DeviceFlags(0x80);
+
+    pub fn contains(self, other: DeviceFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for DeviceFlags {
+    type Output = DeviceFlags;
+
+    fn bitor(self, rhs: DeviceFlags) -> DeviceFlags {
+        DeviceFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for DeviceFlags {
+    fn bitor_assign(&mut self, rhs: DeviceFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for DeviceFlags {
+    type Output = DeviceFlags;
+
+    fn bitand(self, rhs: DeviceFlags) -> DeviceFlags {
+        DeviceFlags(self.0 & rhs.0)
+    }
+}
+
+// Why device_read stopped, the `reason` bits of Device_ReadResp
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct ReadReason(pub i32);
+
+impl ReadReason {
+    pub const NONE: ReadReason = ReadReason(0);
+    // `request_size` bytes were transferred
+    pub const REQCNT: ReadReason = ReadReason(0x01);
+    // The termination character was seen
+    pub const CHR: ReadReason = ReadReason(0x02);
+    // END was seen
+    pub const END: ReadReason = ReadReason(0x04);
+
+    pub fn contains(self, other: ReadReason) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for ReadReason {
+    type Output = ReadReason;
+
+    fn bitor(self, rhs: ReadReason) -> ReadReason {
+        ReadReason(self.0 | rhs.0)
+    }
+}
+
+// `Device_ErrorCode`. Kept open rather than an enum since devices can return codes outside the
+// spec's list.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct DeviceErrorCode(pub i32);
+
+impl DeviceErrorCode {
+    pub const NO_ERROR: DeviceErrorCode = DeviceErrorCode(0);
+    pub const SYNTAX_ERROR: DeviceErrorCode = DeviceErrorCode(1);
+    pub const DEVICE_NOT_ACCESSIBLE: DeviceErrorCode = DeviceErrorCode(3);
+    pub const INVALID_LINK_IDENTIFIER: DeviceErrorCode = DeviceErrorCode(4);
+    pub const PARAMETER_ERROR: DeviceErrorCode = DeviceErrorCode(5);
+    pub const CHANNEL_NOT_ESTABLISHED: DeviceErrorCode = DeviceErrorCode(6);
+    pub const OPERATION_NOT_SUPPORTED: DeviceErrorCode = DeviceErrorCode(8);
+    pub const OUT_OF_RESOURCES: DeviceErrorCode = DeviceErrorCode(9);
+    pub const DEVICE_LOCKED_BY_ANOTHER_LINK: DeviceErrorCode = DeviceErrorCode(11);
+    pub const NO_LOCK_HELD_BY_THIS_LINK: DeviceErrorCode = DeviceErrorCode(12);
+    pub const IO_TIMEOUT: DeviceErrorCode = DeviceErrorCode(15);
+    pub const IO_ERROR: DeviceErrorCode = DeviceErrorCode(17);
+    pub const INVALID_ADDRESS: DeviceErrorCode = DeviceErrorCode(21);
+    pub const ABORT: DeviceErrorCode = DeviceErrorCode(23);
+    pub const CHANNEL_ALREADY_ESTABLISHED: DeviceErrorCode = DeviceErrorCode(29);
+
+    pub fn is_ok(self) -> bool {
+        self == DeviceErrorCode::NO_ERROR
+    }
+
+    pub fn description(self) -> Option<&'static str> {
+        let description = match self.0 {
+            0 => "no error",
+            1 => "syntax error",
+            3 => "device not accessible",
+            4 => "invalid link identifier",
+            5 => "parameter error",
+            6 => "channel not established",
+            8 => "operation not supported",
+            9 => "out of resources",
+            11 => "device locked by another link",
+            12 => "no lock held by this link",
+            15 => "I/O timeout",
+            17 => "I/O error",
+            21 => "invalid address",
+            23 => "abort",
+            29 => "channel already established",
+            _ => return None,
+        };
+        Some(description)
+    }
+}
+
+impl fmt::Display for DeviceErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{} ({})", description, self.0),
+            None => write!(f, "unknown device error ({})", self.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceError {
+    pub error: DeviceErrorCode,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CreateLinkParms {
+    pub client_id: i32,
+    pub lock_device: bool,
+    pub lock_timeout: u32,
+    pub device: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CreateLinkResp {
+    pub error: DeviceErrorCode,
+    pub lid: DeviceLink,
+    pub abort_port: u32,
+    pub max_recv_size: u32,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceWriteParms {
+    pub lid: DeviceLink,
+    pub io_timeout: u32,
+    pub lock_timeout: u32,
+    pub flags: DeviceFlags,
+    pub data: Opaque,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceWriteResp {
+    pub error: DeviceErrorCode,
+    pub size: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceReadParms {
+    pub lid: DeviceLink,
+    pub request_size: u32,
+    pub io_timeout: u32,
+    pub lock_timeout: u32,
+    pub flags: DeviceFlags,
+    pub term_char: u32,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceReadResp {
+    pub error: DeviceErrorCode,
+    pub reason: ReadReason,
+    pub data: Opaque,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceReadStbResp {
+    pub error: DeviceErrorCode,
+    pub stb: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceGenericParms {
+    pub lid: DeviceLink,
+    pub flags: DeviceFlags,
+    pub lock_timeout: u32,
+    pub io_timeout: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceRemoteFunc {
+    pub host_addr: u32,
+    pub host_port: u32,
+    pub prog_num: u32,
+    pub prog_vers: u32,
+    pub prog_family: DeviceAddrFamily,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceEnableSrqParms {
+    pub lid: DeviceLink,
+    pub enable: bool,
+    // At most 40 bytes, echoed back in device_intr_srq
+    pub handle: Opaque,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceLockParms {
+    pub lid: DeviceLink,
+    pub flags: DeviceFlags,
+    pub lock_timeout: u32,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceDocmdParms {
+    pub lid: DeviceLink,
+    pub flags: DeviceFlags,
+    pub io_timeout: u32,
+    pub lock_timeout: u32,
+    pub cmd: i32,
+    pub network_order: bool,
+    pub datasize: i32,
+    pub data_in: Opaque,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceDocmdResp {
+    pub error: DeviceErrorCode,
+    pub data_out: Opaque,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSrqParms {
+    pub handle: Opaque,
+}
@@ -0,0 +1,673 @@
+// An in-process VXI-11 instrument for testing without hardware. Each named device is backed by a
+// SimulatedDevice; the server runs the core and abort channels plus its own port mapper, so
+// Vxi11Instrument::connect_via finds it the same way it finds a real instrument.
+
+use crate::portmap::{Mapping, Portmapper, PortmapperHandle, IPPROTO_TCP};
+use crate::rpc::{Call, ProcError, RpcProgram, RpcServer, ServerHandle};
+use crate::vxi11::*;
+use crate::Opaque;
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::mem;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_MAX_RECV_SIZE: u32 = 64 * 1024;
+// How often a delayed call checks whether it has been aborted
+const ABORT_POLL: Duration = Duration::from_millis(10);
+const MAX_SRQ_HANDLE: usize = 40;
+
+pub trait SimulatedDevice: Send {
+    // A complete message, i.e. everything written up to and including the chunk flagged END
+    fn write(&mut self, message: &[u8]) -> Result<(), DeviceErrorCode>;
+
+    // The next response, asked for once the previous one has been read out. None means there is
+    // nothing to read, which device_read reports as an I/O timeout.
+    fn read(&mut self) -> Result<Option<Vec<u8>>, DeviceErrorCode>;
+
+    fn status_byte(&mut self) -> u8 {
+        0
+    }
+
+    fn trigger(&mut self) -> Result<(), DeviceErrorCode> {
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), DeviceErrorCode> {
+        Ok(())
+    }
+}
+
+// Answers queries from a fixed table, newline terminated. Commands and unknown queries are
+// accepted and produce nothing to read.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedDevice {
+    responses: HashMap<String, String>,
+    pending: Option<String>,
+}
+
+impl ScriptedDevice {
+    pub fn new() -> Self {
+        ScriptedDevice::default()
+    }
+
+    pub fn respond(mut self, query: &str, response: &str) -> Self {
+        self.responses
+            .insert(query.to_string(), response.to_string());
+        self
+    }
+}
+
+impl SimulatedDevice for ScriptedDevice {
+    fn write(&mut self, message: &[u8]) -> Result<(), DeviceErrorCode> {
+        let message = String::from_utf8_lossy(message);
+        self.pending = self
+            .responses
+            .get(message.trim())
+            .map(|response| format!("{}\n", response));
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Option<Vec<u8>>, DeviceErrorCode> {
+        Ok(self.pending.take().map(String::into_bytes))
+    }
+
+    fn clear(&mut self) -> Result<(), DeviceErrorCode> {
+        self.pending = None;
+        Ok(())
+    }
+}
+
+pub struct SimulatedServer {
+    devices: HashMap<String, Box<dyn SimulatedDevice>>,
+    latency: Duration,
+    max_recv_size: u32,
+}
+
+impl Default for SimulatedServer {
+    fn default() -> Self {
+        SimulatedServer::new()
+    }
+}
+
+impl SimulatedServer {
+    pub fn new() -> Self {
+        SimulatedServer {
+            devices: HashMap::new(),
+            latency: Duration::from_secs(0),
+            max_recv_size: DEFAULT_MAX_RECV_SIZE,
+        }
+    }
+
+    // Serves `device` under `name`, e.g. "inst0"
+    pub fn device<D>(mut self, name: &str, device: D) -> Self
+    where
+        D: SimulatedDevice + 'static,
+    {
+        self.devices.insert(name.to_string(), Box::new(device));
+        self
+    }
+
+    // Delay before answering each core channel call, which device_abort can cut short
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    // Writes with more data than this are refused with a parameter error
+    pub fn max_recv_size(mut self, size: u32) -> Self {
+        self.max_recv_size = size;
+        self
+    }
+
+    // Starts the port mapper on `portmapper`, which can use port 0 to pick a free one, and the
+    // core and abort channels on free ports of the same address
+    pub fn spawn<A: ToSocketAddrs>(self, portmapper: A) -> io::Result<SimulatorHandle> {
+        let devices = self
+            .devices
+            .into_iter()
+            .map(|(name, sim)| {
+                let device = Device {
+                    sim,
+                    output: Vec::new(),
+                    lock: None,
+                };
+                (name, device)
+            })
+            .collect();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                devices,
+                links: HashMap::new(),
+                next_lid: 0,
+                intr: HashMap::new(),
+                faults: HashMap::new(),
+                latency: self.latency,
+                abort_port: 0,
+            }),
+            unlocked: Condvar::new(),
+            max_recv_size: self.max_recv_size,
+        });
+
+        let mapper = Portmapper::new();
+        let portmapper = mapper.spawn(portmapper)?;
+        let ip = portmapper.local_addr().ip();
+
+        let mut server = RpcServer::new();
+        server.register(Core(shared.clone()));
+        let core = server.spawn_tcp((ip, 0))?;
+
+        let abort_shared = shared.clone();
+        let mut server = RpcServer::new();
+        server.register(AbortServer::new(move |lid| {
+            match abort_shared.state.lock().unwrap().links.get_mut(&lid) {
+                Some(link) => {
+                    link.aborted = true;
+                    DeviceErrorCode::NO_ERROR
+                }
+                None => DeviceErrorCode::INVALID_LINK_IDENTIFIER,
+            }
+        }));
+        let abort = server.spawn_tcp((ip, 0))?;
+
+        shared.state.lock().unwrap().abort_port = u32::from(abort.local_addr().port());
+        mapper.set(Mapping {
+            prog: DEVICE_CORE,
+            vers: DEVICE_CORE_VERSION,
+            prot: IPPROTO_TCP,
+            port: u32::from(core.local_addr().port()),
+        });
+        Ok(SimulatorHandle {
+            shared,
+            portmapper,
+            core,
+            abort,
+        })
+    }
+}
+
+// A running simulator, stopped when dropped
+pub struct SimulatorHandle {
+    shared: Arc<Shared>,
+    portmapper: PortmapperHandle,
+    core: ServerHandle,
+    abort: ServerHandle,
+}
+
+impl SimulatorHandle {
+    pub fn portmapper_addr(&self) -> SocketAddr {
+        self.portmapper.local_addr()
+    }
+
+    pub fn core_addr(&self) -> SocketAddr {
+        self.core.local_addr()
+    }
+
+    pub fn abort_addr(&self) -> SocketAddr {
+        self.abort.local_addr()
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        self.shared.state.lock().unwrap().latency = latency;
+    }
+
+    // Makes the next call to core procedure `proc` fail with `error`. Errors queue up per
+    // procedure.
+    pub fn inject_error(&self, proc: u32, error: DeviceErrorCode) {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .faults
+            .entry(proc)
+            .or_default()
+            .push_back(error);
+    }
+
+    // Sends device_intr_srq for every link to `device` that has SRQs enabled and whose client
+    // has an interrupt channel. Returns how many were sent.
+    pub fn request_service(&self, device: &str) -> Vxi11Result<usize> {
+        let targets: Vec<(DeviceRemoteFunc, Opaque)> = {
+            let state = self.shared.state.lock().unwrap();
+            state
+                .links
+                .values()
+                .filter(|link| link.device == device)
+                .filter_map(|link| {
+                    let func = state.intr.get(&link.peer)?;
+                    Some((*func, link.srq.clone()?))
+                })
+                .collect()
+        };
+        for (func, handle) in &targets {
+            IntrClient::connect(func)?.srq(handle)?;
+        }
+        Ok(targets.len())
+    }
+
+    pub fn shutdown(self) {
+        self.core.shutdown();
+        self.abort.shutdown();
+        self.portmapper.shutdown();
+    }
+}
+
+struct Device {
+    sim: Box<dyn SimulatedDevice>,
+    // The rest of the current response, handed out over one or more device_reads
+    output: Vec<u8>,
+    lock: Option<DeviceLink>,
+}
+
+struct Link {
+    device: String,
+    peer: SocketAddr,
+    // Chunks written so far of a message that hasn't seen END yet
+    input: Vec<u8>,
+    srq: Option<Opaque>,
+    aborted: bool,
+}
+
+struct State {
+    devices: HashMap<String, Device>,
+    links: HashMap<DeviceLink, Link>,
+    next_lid: DeviceLink,
+    // Interrupt channels, per client connection
+    intr: HashMap<SocketAddr, DeviceRemoteFunc>,
+    faults: HashMap<u32, VecDeque<DeviceErrorCode>>,
+    latency: Duration,
+    abort_port: u32,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    unlocked: Condvar,
+    max_recv_size: u32,
+}
+
+struct Core(Arc<Shared>);
+
+impl Core {
+    // Applies the latency and any injected error for `proc`. Latency is served in steps so an
+    // abort for `lid` ends it early.
+    fn begin(&self, proc: u32, lid: Option<DeviceLink>) -> Result<(), DeviceErrorCode> {
+        let latency = {
+            let mut state = self.0.state.lock().unwrap();
+            if let Some(lid) = lid {
+                let link = state
+                    .links
+                    .get_mut(&lid)
+                    .ok_or(DeviceErrorCode::INVALID_LINK_IDENTIFIER)?;
+                link.aborted = false;
+            }
+            state.latency
+        };
+        let deadline = Instant::now() + latency;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            thread::sleep((deadline - now).min(ABORT_POLL));
+            let mut state = self.0.state.lock().unwrap();
+            let link = lid.and_then(|lid| state.links.get_mut(&lid));
+            if let Some(link) = link.filter(|link| link.aborted) {
+                link.aborted = false;
+                return Err(DeviceErrorCode::ABORT);
+            }
+        }
+
+        let mut state = self.0.state.lock().unwrap();
+        match state.faults.get_mut(&proc).and_then(VecDeque::pop_front) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    // Locks the state once no other link holds the device's lock, waiting up to `lock_timeout`
+    // milliseconds for it if `flags` has WAITLOCK
+    fn acquire(
+        &self,
+        lid: DeviceLink,
+        flags: DeviceFlags,
+        lock_timeout: u32,
+    ) -> Result<MutexGuard<'_, State>, DeviceErrorCode> {
+        let deadline = Instant::now() + Duration::from_millis(u64::from(lock_timeout));
+        let mut state = self.0.state.lock().unwrap();
+        loop {
+            let link = state
+                .links
+                .get(&lid)
+                .ok_or(DeviceErrorCode::INVALID_LINK_IDENTIFIER)?;
+            match state.devices[&link.device].lock {
+                Some(owner) if owner != lid => {}
+                _ => return Ok(state),
+            }
+            let now = Instant::now();
+            if !flags.contains(DeviceFlags::WAITLOCK) || now >= deadline {
+                return Err(DeviceErrorCode::DEVICE_LOCKED_BY_ANOTHER_LINK);
+            }
+            state = self
+                .0
+                .unlocked
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn create_link(
+        &self,
+        parms: &CreateLinkParms,
+        peer: SocketAddr,
+    ) -> Result<CreateLinkResp, DeviceErrorCode> {
+        self.begin(CREATE_LINK, None)?;
+        let lid = {
+            let mut state = self.0.state.lock().unwrap();
+            if !state.devices.contains_key(&parms.device) {
+                return Err(DeviceErrorCode::DEVICE_NOT_ACCESSIBLE);
+            }
+            state.next_lid += 1;
+            let lid = state.next_lid;
+            let link = Link {
+                device: parms.device.clone(),
+                peer,
+                input: Vec::new(),
+                srq: None,
+                aborted: false,
+            };
+            state.links.insert(lid, link);
+            lid
+        };
+        if parms.lock_device {
+            if let Err(error) = self.lock(lid, DeviceFlags::WAITLOCK, parms.lock_timeout) {
+                self.0.state.lock().unwrap().links.remove(&lid);
+                return Err(error);
+            }
+        }
+        Ok(CreateLinkResp {
+            error: DeviceErrorCode::NO_ERROR,
+            lid,
+            abort_port: self.0.state.lock().unwrap().abort_port,
+            max_recv_size: self.0.max_recv_size,
+        })
+    }
+
+    fn write(&self, parms: &DeviceWriteParms) -> Result<u32, DeviceErrorCode> {
+        self.begin(DEVICE_WRITE, Some(parms.lid))?;
+        let data = parms.data.as_ref();
+        if data.len() > self.0.max_recv_size as usize {
+            return Err(DeviceErrorCode::PARAMETER_ERROR);
+        }
+        let mut state = self.acquire(parms.lid, parms.flags, parms.lock_timeout)?;
+        let State { links, devices, .. } = &mut *state;
+        let link = links.get_mut(&parms.lid).unwrap();
+        link.input.extend_from_slice(data);
+        if parms.flags.contains(DeviceFlags::END) {
+            let message = mem::take(&mut link.input);
+            devices.get_mut(&link.device).unwrap().sim.write(&message)?;
+        }
+        Ok(data.len() as u32)
+    }
+
+    fn read(&self, parms: &DeviceReadParms) -> Result<(ReadReason, Vec<u8>), DeviceErrorCode> {
+        self.begin(DEVICE_READ, Some(parms.lid))?;
+        let mut state = self.acquire(parms.lid, parms.flags, parms.lock_timeout)?;
+        let State { links, devices, .. } = &mut *state;
+        let device = devices.get_mut(&links[&parms.lid].device).unwrap();
+        if device.output.is_empty() {
+            device.output = device.sim.read()?.ok_or(DeviceErrorCode::IO_TIMEOUT)?;
+        }
+
+        let mut len = device.output.len().min(parms.request_size as usize);
+        let mut reason = ReadReason::NONE;
+        if parms.flags.contains(DeviceFlags::TERMCHRSET) {
+            let term = device.output[..len]
+                .iter()
+                .position(|&b| u32::from(b) == parms.term_char);
+            if let Some(pos) = term {
+                len = pos + 1;
+                reason = reason | ReadReason::CHR;
+            }
+        }
+        let data: Vec<u8> = device.output.drain(..len).collect();
+        if device.output.is_empty() {
+            reason = reason | ReadReason::END;
+        }
+        if len == parms.request_size as usize {
+            reason = reason | ReadReason::REQCNT;
+        }
+        Ok((reason, data))
+    }
+
+    // Runs `op` on the link's device once any other link's lock is out of the way
+    fn generic<F, T>(
+        &self,
+        proc: u32,
+        parms: &DeviceGenericParms,
+        op: F,
+    ) -> Result<T, DeviceErrorCode>
+    where
+        F: FnOnce(&mut Link, &mut Device) -> Result<T, DeviceErrorCode>,
+    {
+        self.begin(proc, Some(parms.lid))?;
+        let mut state = self.acquire(parms.lid, parms.flags, parms.lock_timeout)?;
+        let State { links, devices, .. } = &mut *state;
+        let link = links.get_mut(&parms.lid).unwrap();
+        let device = devices.get_mut(&link.device).unwrap();
+        op(link, device)
+    }
+
+    fn lock(
+        &self,
+        lid: DeviceLink,
+        flags: DeviceFlags,
+        lock_timeout: u32,
+    ) -> Result<(), DeviceErrorCode> {
+        let mut state = self.acquire(lid, flags, lock_timeout)?;
+        let device = state.links[&lid].device.clone();
+        state.devices.get_mut(&device).unwrap().lock = Some(lid);
+        Ok(())
+    }
+
+    // The link can go away between begin and taking the state lock again, if another call destroys
+    // it during the latency
+    fn unlock(&self, lid: DeviceLink) -> Result<(), DeviceErrorCode> {
+        self.begin(DEVICE_UNLOCK, Some(lid))?;
+        let mut state = self.0.state.lock().unwrap();
+        let device = state
+            .links
+            .get(&lid)
+            .ok_or(DeviceErrorCode::INVALID_LINK_IDENTIFIER)?
+            .device
+            .clone();
+        let device = state.devices.get_mut(&device).unwrap();
+        if device.lock != Some(lid) {
+            return Err(DeviceErrorCode::NO_LOCK_HELD_BY_THIS_LINK);
+        }
+        device.lock = None;
+        self.0.unlocked.notify_all();
+        Ok(())
+    }
+
+    fn destroy_link(&self, lid: DeviceLink) -> Result<(), DeviceErrorCode> {
+        self.begin(DESTROY_LINK, Some(lid))?;
+        let mut state = self.0.state.lock().unwrap();
+        let link = state
+            .links
+            .remove(&lid)
+            .ok_or(DeviceErrorCode::INVALID_LINK_IDENTIFIER)?;
+        let device = state.devices.get_mut(&link.device).unwrap();
+        if device.lock == Some(lid) {
+            device.lock = None;
+            self.0.unlocked.notify_all();
+        }
+        Ok(())
+    }
+
+    fn enable_srq(&self, parms: &DeviceEnableSrqParms) -> Result<(), DeviceErrorCode> {
+        self.begin(DEVICE_ENABLE_SRQ, Some(parms.lid))?;
+        if parms.handle.as_ref().len() > MAX_SRQ_HANDLE {
+            return Err(DeviceErrorCode::PARAMETER_ERROR);
+        }
+        let mut state = self.0.state.lock().unwrap();
+        let link = state
+            .links
+            .get_mut(&parms.lid)
+            .ok_or(DeviceErrorCode::INVALID_LINK_IDENTIFIER)?;
+        link.srq = if parms.enable {
+            Some(parms.handle.clone())
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    fn create_intr_chan(
+        &self,
+        func: &DeviceRemoteFunc,
+        peer: SocketAddr,
+    ) -> Result<(), DeviceErrorCode> {
+        self.begin(CREATE_INTR_CHAN, None)?;
+        let mut state = self.0.state.lock().unwrap();
+        if state.intr.contains_key(&peer) {
+            return Err(DeviceErrorCode::CHANNEL_ALREADY_ESTABLISHED);
+        }
+        state.intr.insert(peer, *func);
+        Ok(())
+    }
+
+    fn destroy_intr_chan(&self, peer: SocketAddr) -> Result<(), DeviceErrorCode> {
+        self.begin(DESTROY_INTR_CHAN, None)?;
+        match self.0.state.lock().unwrap().intr.remove(&peer) {
+            Some(_) => Ok(()),
+            None => Err(DeviceErrorCode::CHANNEL_NOT_ESTABLISHED),
+        }
+    }
+}
+
+impl RpcProgram for Core {
+    fn program(&self) -> u32 {
+        DEVICE_CORE
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (DEVICE_CORE_VERSION, DEVICE_CORE_VERSION)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        let peer = call.peer;
+        match call.proc() {
+            0 => Ok(()),
+            CREATE_LINK => {
+                let parms: CreateLinkParms = call.args()?;
+                let resp = self
+                    .create_link(&parms, peer)
+                    .unwrap_or_else(|error| CreateLinkResp {
+                        error,
+                        ..CreateLinkResp::default()
+                    });
+                call.reply(&resp)
+            }
+            DEVICE_WRITE => {
+                let parms: DeviceWriteParms = call.args()?;
+                let resp = match self.write(&parms) {
+                    Ok(size) => DeviceWriteResp {
+                        error: DeviceErrorCode::NO_ERROR,
+                        size,
+                    },
+                    Err(error) => DeviceWriteResp { error, size: 0 },
+                };
+                call.reply(&resp)
+            }
+            DEVICE_READ => {
+                let parms: DeviceReadParms = call.args()?;
+                let resp = match self.read(&parms) {
+                    Ok((reason, data)) => DeviceReadResp {
+                        error: DeviceErrorCode::NO_ERROR,
+                        reason,
+                        data: Opaque(data),
+                    },
+                    Err(error) => DeviceReadResp {
+                        error,
+                        ..DeviceReadResp::default()
+                    },
+                };
+                call.reply(&resp)
+            }
+            DEVICE_READSTB => {
+                let parms: DeviceGenericParms = call.args()?;
+                let resp = match self.generic(DEVICE_READSTB, &parms, |_, device| {
+                    Ok(device.sim.status_byte())
+                }) {
+                    Ok(stb) => DeviceReadStbResp {
+                        error: DeviceErrorCode::NO_ERROR,
+                        stb: u32::from(stb),
+                    },
+                    Err(error) => DeviceReadStbResp { error, stb: 0 },
+                };
+                call.reply(&resp)
+            }
+            DEVICE_TRIGGER => {
+                let parms: DeviceGenericParms = call.args()?;
+                let result = self.generic(DEVICE_TRIGGER, &parms, |_, device| device.sim.trigger());
+                call.reply(&device_error(result))
+            }
+            DEVICE_CLEAR => {
+                let parms: DeviceGenericParms = call.args()?;
+                let result = self.generic(DEVICE_CLEAR, &parms, |link, device| {
+                    link.input.clear();
+                    device.output.clear();
+                    device.sim.clear()
+                });
+                call.reply(&device_error(result))
+            }
+            proc @ DEVICE_REMOTE | proc @ DEVICE_LOCAL => {
+                let parms: DeviceGenericParms = call.args()?;
+                let result = self.generic(proc, &parms, |_, _| Ok(()));
+                call.reply(&device_error(result))
+            }
+            DEVICE_LOCK => {
+                let parms: DeviceLockParms = call.args()?;
+                let result = self
+                    .begin(DEVICE_LOCK, Some(parms.lid))
+                    .and_then(|_| self.lock(parms.lid, parms.flags, parms.lock_timeout));
+                call.reply(&device_error(result))
+            }
+            DEVICE_UNLOCK => {
+                let lid: DeviceLink = call.args()?;
+                call.reply(&device_error(self.unlock(lid)))
+            }
+            DEVICE_ENABLE_SRQ => {
+                let parms: DeviceEnableSrqParms = call.args()?;
+                call.reply(&device_error(self.enable_srq(&parms)))
+            }
+            DEVICE_DOCMD => {
+                let _: DeviceDocmdParms = call.args()?;
+                call.reply(&DeviceDocmdResp {
+                    error: DeviceErrorCode::OPERATION_NOT_SUPPORTED,
+                    data_out: Opaque::default(),
+                })
+            }
+            DESTROY_LINK => {
+                let lid: DeviceLink = call.args()?;
+                call.reply(&device_error(self.destroy_link(lid)))
+            }
+            CREATE_INTR_CHAN => {
+                let func: DeviceRemoteFunc = call.args()?;
+                call.reply(&device_error(self.create_intr_chan(&func, peer)))
+            }
+            DESTROY_INTR_CHAN => call.reply(&device_error(self.destroy_intr_chan(peer))),
+            _ => Err(ProcError::ProcUnavail),
+        }
+    }
+}
+
+fn device_error(result: Result<(), DeviceErrorCode>) -> DeviceError {
+    DeviceError {
+        error: result.err().unwrap_or_default(),
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::{Read, Write};
+use std::process::{Command, Output, Stdio};
+
+const VXI11: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/codegen-tests/proto/vxi11.x");
+
+// A Device_ReadResp with no error, reason END and data "hi"
+const READ_RESP: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 2, b'h', b'i', 0, 0];
+
+fn run(command: &str, type_name: &str, stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_serde-xdr"))
+        .args([command, "--schema", VXI11, "--type", type_name])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn decode_and_encode() {
+    let output = run("decode", "Device_ReadResp", READ_RESP);
+    assert!(output.status.success());
+    assert!(stdout(&output).ends_with("}\n"));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({ "error": 0, "reason": 4, "data": "6869" })
+    );
+
+    let output = run("encode", "Device_ReadResp", &output.stdout);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, READ_RESP);
+}
+
+#[test]
+fn hexdump() {
+    let output = run("hexdump", "Device_ReadResp", READ_RESP);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "00000000  00 00 00 00  error: i32\n\
+         00000004  00 00 00 04  reason: i32\n\
+         00000008  00 00 00 02  data: length\n\
+         0000000c  68 69        data: bytes\n\
+         0000000e  00 00        data: padding\n"
+    );
+
+    let mut trailing = READ_RESP.to_vec();
+    trailing.push(1);
+    let output = run("hexdump", "Device_ReadResp", &trailing);
+    assert!(stdout(&output)
+        .ends_with("0000000e  00 00        data: padding\n00000010  01           (trailing)\n"));
+
+    // What decodes before the failure keeps its labels, and the failure is still an error
+    let output = run("hexdump", "Device_ReadResp", &READ_RESP[..10]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "00000000  00 00 00 00  error: i32\n\
+         00000004  00 00 00 04  reason: i32\n\
+         00000008  00 00        data: incomplete\n"
+    );
+    assert_eq!(stderr(&output), "error: failed to fill whole buffer\n");
+}
+
+#[test]
+fn validate() {
+    let output = run("validate", "Device_ReadResp", READ_RESP);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "ok: 16 bytes\n");
+
+    let output = run("validate", "Device_Error", READ_RESP);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error: 4 bytes decode as Device_Error, leaving 12 trailing bytes\n"
+    );
+}
+
+#[test]
+fn errors() {
+    let output = run("validate", "Device_Nothing", READ_RESP);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with(&format!("error: {}:", VXI11)));
+
+    // Rejected without waiting on stdin, which is left open here
+    let mut child = Command::new(env!("CARGO_BIN_EXE_serde-xdr"))
+        .args(["frobnicate", "--schema", VXI11, "--type", "Device_ReadResp"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdin = child.stdin.take();
+    let mut message = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut message)
+        .unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(1));
+    assert!(message.starts_with("error: unknown command `frobnicate`"));
+    drop(stdin);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_serde-xdr"))
+        .arg("decode")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("usage: serde-xdr"));
+}
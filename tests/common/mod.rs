@@ -0,0 +1,34 @@
+// Helpers shared by the integration tests. Each test crate uses its own subset.
+#![allow(dead_code)]
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_xdr::{from_bytes, to_bytes};
+
+use std::fmt::Debug;
+
+pub fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    to_bytes(value, &mut buf).unwrap();
+    buf
+}
+
+// Encodes the value, checks it decodes back to itself using every byte, and returns the bytes
+pub fn round_trip<T>(value: &T) -> Vec<u8>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let buf = encode(value);
+    let (decoded, consumed): (T, usize) = from_bytes(&buf).unwrap();
+    assert_eq!(&decoded, value);
+    assert_eq!(consumed, buf.len());
+    buf
+}
+
+// Big-endian XDR words, for spelling out expected bytes
+pub fn words(words: &[u32]) -> Vec<u8> {
+    words
+        .iter()
+        .flat_map(|w| w.to_be_bytes().to_vec())
+        .collect()
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::encode;
+use serde_xdr::{from_bytes, Bounded, Deserializer, FixedArray, FixedOpaque, Opaque};
+
+use std::convert::TryFrom;
+
+#[test]
+fn tuples_and_fixed_arrays_have_no_length() {
+    let pair = (7u32, -1i32);
+    let buf = encode(&pair);
+    assert_eq!(buf, [0, 0, 0, 7, 0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(from_bytes::<(u32, i32)>(&buf).unwrap(), (pair, 8));
+
+    let array = FixedArray([1u32, 2, 3]);
+    let buf = encode(&array);
+    assert_eq!(buf.len(), 12);
+    assert_eq!(from_bytes::<FixedArray<u32, 3>>(&buf).unwrap(), (array, 12));
+    assert!(from_bytes::<FixedArray<u32, 4>>(&buf).is_err());
+}
+
+#[test]
+fn fixed_opaque_is_padded() {
+    let id = FixedOpaque([1, 2, 3, 4, 5]);
+    let buf = encode(&id);
+    assert_eq!(buf, [1, 2, 3, 4, 5, 0, 0, 0]);
+    assert_eq!(from_bytes::<FixedOpaque<5>>(&buf).unwrap(), (id, 8));
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.skip::<FixedOpaque<5>>().unwrap();
+    assert_eq!(de.get_bytes_consumed(), 8);
+}
+
+#[test]
+fn floats() {
+    assert_eq!(encode(&1.5f32), [0x3F, 0xC0, 0, 0]);
+    assert_eq!(encode(&-2.0f64), [0xC0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(from_bytes::<f64>(&encode(&0.1f64)).unwrap(), (0.1, 8));
+}
+
+#[test]
+fn bounded() {
+    let name = Bounded::<String, 4>::try_from("inst").unwrap();
+    assert_eq!(name.as_str(), "inst");
+    assert!(Bounded::<String, 4>::try_from("inst0").is_err());
+    assert!(Bounded::<Opaque, 2>::new(Opaque(vec![0; 3])).is_err());
+
+    // Same encoding as the unbounded value, checked against the bound on the way back in
+    let buf = encode(&name);
+    assert_eq!(buf, encode(&String::from("inst")));
+    assert!(from_bytes::<Bounded<String, 3>>(&buf).is_err());
+    assert_eq!(from_bytes::<Bounded<String, 4>>(&buf).unwrap(), (name, 8));
+}
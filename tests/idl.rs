@@ -0,0 +1,332 @@
+use serde_xdr::idl::ast::*;
+use serde_xdr::idl::{generate, parse};
+
+const SAMPLE: &str = r#"/* A little of everything */
+%#include "extra.h"
+const MAXNAME = 0x40;
+const LEVEL = -3;
+
+typedef string name_t<MAXNAME>;
+typedef opaque handle_t[16];
+typedef int values_t<>;
+
+enum color { RED = 0, GREEN, BLUE = 2 };
+
+struct node {
+    name_t name;
+    node *next;       // optional
+    unsigned hyper ids[4];
+    opaque data<>;
+};
+
+union result switch (color c) {
+case RED:
+case GREEN:
+    int value;
+case BLUE:
+    void;
+default:
+    string message<>;
+};
+
+program EXAMPLE_PROG {
+    version EXAMPLE_VERS {
+        void PING(void) = 0;
+        result LOOKUP(name_t, int) = 1;
+    } = 1;
+} = 0x20000001;
+"#;
+
+fn ident(name: &str) -> Value {
+    Value::Const(Ident {
+        name: name.to_string(),
+        span: Span::default(),
+    })
+}
+
+fn same_value(value: &Value, expected: &Value) -> bool {
+    match (value, expected) {
+        (Value::Int(a, _), Value::Int(b, _)) => a == b,
+        (Value::Const(a), Value::Const(b)) => a.name == b.name,
+        _ => false,
+    }
+}
+
+#[test]
+fn parses_every_construct() {
+    let spec = parse(SAMPLE).unwrap();
+    let defs = &spec.definitions;
+    assert_eq!(defs.len(), 10);
+
+    match defs[0] {
+        Definition::Passthrough(ref p) => {
+            assert_eq!(p.text, "#include \"extra.h\"");
+            assert_eq!(&SAMPLE[p.span.start..p.span.end], "%#include \"extra.h\"");
+        }
+        ref other => panic!("{:?}", other),
+    }
+    match defs[1] {
+        Definition::Const(ref c) => {
+            assert_eq!(c.name.name, "MAXNAME");
+            assert!(same_value(&c.value, &Value::Int(0x40, Span::default())));
+            assert_eq!(&SAMPLE[c.span.start..c.span.end], "const MAXNAME = 0x40;");
+        }
+        ref other => panic!("{:?}", other),
+    }
+    match defs[2] {
+        Definition::Const(ref c) => assert!(same_value(&c.value, &Value::Int(-3, Span::default()))),
+        ref other => panic!("{:?}", other),
+    }
+
+    match defs[3] {
+        Definition::Type(TypeDef {
+            ref name,
+            body: TypeDefBody::Alias(FieldType::String(Some(ref max))),
+            ..
+        }) => {
+            assert_eq!(name.name, "name_t");
+            assert!(same_value(max, &ident("MAXNAME")));
+        }
+        ref other => panic!("{:?}", other),
+    }
+    assert!(matches!(
+        defs[4],
+        Definition::Type(TypeDef {
+            body: TypeDefBody::Alias(FieldType::FixedOpaque(Value::Int(16, _))),
+            ..
+        })
+    ));
+    assert!(matches!(
+        defs[5],
+        Definition::Type(TypeDef {
+            body: TypeDefBody::Alias(FieldType::VarArray(TypeSpec::Int, None)),
+            ..
+        })
+    ));
+
+    match defs[6] {
+        Definition::Type(TypeDef {
+            body: TypeDefBody::Enum(ref body),
+            ..
+        }) => {
+            let names: Vec<_> = body.variants.iter().map(|v| v.name.name.as_str()).collect();
+            assert_eq!(names, ["RED", "GREEN", "BLUE"]);
+            assert!(body.variants[1].value.is_none());
+            assert!(same_value(
+                body.variants[2].value.as_ref().unwrap(),
+                &Value::Int(2, Span::default())
+            ));
+        }
+        ref other => panic!("{:?}", other),
+    }
+
+    match defs[7] {
+        Definition::Type(TypeDef {
+            body: TypeDefBody::Struct(ref body),
+            ..
+        }) => {
+            let fields = &body.fields;
+            assert_eq!(fields.len(), 4);
+            assert!(
+                matches!(fields[0].ty, FieldType::Plain(TypeSpec::Named(ref n)) if n.name == "name_t")
+            );
+            assert!(
+                matches!(fields[1].ty, FieldType::Optional(TypeSpec::Named(ref n)) if n.name == "node")
+            );
+            assert!(matches!(
+                fields[2].ty,
+                FieldType::FixedArray(TypeSpec::UnsignedHyper, Value::Int(4, _))
+            ));
+            assert!(matches!(fields[3].ty, FieldType::VarOpaque(None)));
+            assert_eq!(
+                &SAMPLE[fields[1].span.start..fields[1].span.end],
+                "node *next"
+            );
+        }
+        ref other => panic!("{:?}", other),
+    }
+
+    match defs[8] {
+        Definition::Type(TypeDef {
+            body: TypeDefBody::Union(ref body),
+            ..
+        }) => {
+            assert_eq!(body.discriminant.name.name, "c");
+            assert_eq!(body.arms.len(), 2);
+            assert_eq!(body.arms[0].cases.len(), 2);
+            assert!(
+                matches!(body.arms[0].decl, Declaration::Field(ref f) if f.name.name == "value")
+            );
+            assert!(matches!(body.arms[1].decl, Declaration::Void(_)));
+            assert!(matches!(
+                body.default.as_deref(),
+                Some(Declaration::Field(Field {
+                    ty: FieldType::String(None),
+                    ..
+                }))
+            ));
+        }
+        ref other => panic!("{:?}", other),
+    }
+
+    match defs[9] {
+        Definition::Program(ref prog) => {
+            assert_eq!(prog.name.name, "EXAMPLE_PROG");
+            assert!(same_value(
+                &prog.number,
+                &Value::Int(0x2000_0001, Span::default())
+            ));
+            let vers = &prog.versions[0];
+            assert!(same_value(&vers.number, &Value::Int(1, Span::default())));
+            assert_eq!(vers.procedures.len(), 2);
+            assert_eq!(vers.procedures[0].result, TypeSpec::Void);
+            assert_eq!(vers.procedures[0].args, [TypeSpec::Void]);
+            let lookup = &vers.procedures[1];
+            assert_eq!(lookup.name.name, "LOOKUP");
+            assert!(matches!(lookup.result, TypeSpec::Named(ref n) if n.name == "result"));
+            assert_eq!(lookup.args.len(), 2);
+            assert_eq!(lookup.args[1], TypeSpec::Int);
+        }
+        ref other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn rpcgen_extras() {
+    let spec = parse(
+        "# 1 \"proto.x\"\n\
+         struct s { long a; unsigned b; unsigned short c; struct s *d; };\n\
+         typedef struct s t;",
+    )
+    .unwrap();
+    assert_eq!(spec.definitions.len(), 2);
+}
+
+#[test]
+fn inline_bodies_after_struct_enum_and_union() {
+    // `union switch` is an inline union, not a reference to a union named `switch`
+    let spec = parse(
+        "struct s { union switch (int d) { case 1: int a; default: void; } u; };\n\
+         typedef union switch (int d) { case 1: int a; default: void; } u_t;\n\
+         typedef enum { A = 1 } e_t;\n\
+         typedef struct { int a; } s_t;\n\
+         typedef struct s s2_t;",
+    )
+    .unwrap();
+    assert_eq!(spec.definitions.len(), 5);
+
+    // Other keywords don't name a type either
+    let err = parse("typedef struct int t;").unwrap_err();
+    assert_eq!(err.message, "expected `{`, found `int`");
+}
+
+#[test]
+fn error_positions() {
+    let err = parse("const A = 1;\nstruct s {\n    int;\n};\n").unwrap_err();
+    assert_eq!((err.line, err.column), (3, 8));
+    assert_eq!(err.message, "expected an identifier, found `;`");
+    assert_eq!(
+        err.to_string(),
+        "3:8: expected an identifier, found `;`\n        int;\n           ^"
+    );
+
+    let err = parse("typedef int struct;").unwrap_err();
+    assert_eq!(
+        err.message,
+        "expected an identifier, found keyword `struct`"
+    );
+
+    let err = parse("const X = 1\nconst Y = 2;").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 1));
+    assert_eq!(err.message, "expected `;`, found `const`");
+
+    let err = parse("struct s { int a; } /* never closed").unwrap_err();
+    assert_eq!(err.message, "unterminated comment");
+
+    let err = parse("union u switch (int d) { default: void; };").unwrap_err();
+    assert_eq!(err.message, "a union needs at least one `case` arm");
+
+    let err = parse("const A = 0x1g;").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 11));
+}
+
+#[test]
+fn generated_names() {
+    let code = generate(
+        "const max_len = 8;\n\
+         enum Device_AddrFamily { DEVICE_TCP, DEVICE_UDP = 4 };\n\
+         struct Create_LinkParms { long clientId; string device<max_len>; int type; };\n\
+         program DEVICE_CORE { version DEVICE_CORE_VERSION {\n\
+             void create_link(Create_LinkParms) = 10;\n\
+         } = 1; } = 0x0607AF;",
+    )
+    .unwrap();
+    assert!(code.contains("pub const MAX_LEN: u32 = 8;"));
+    assert!(
+        code.contains("xdr_enum!(DeviceAddrFamily {\n    DeviceTcp = 0,\n    DeviceUdp = 4,\n});")
+    );
+    assert!(code.contains("pub struct CreateLinkParms {"));
+    assert!(code.contains("pub client_id: i32,"));
+    assert!(code.contains("pub device: ::serde_xdr::Bounded<::std::string::String, MAX_LEN>,"));
+    assert!(code.contains("pub r#type: i32,"));
+    assert!(code.contains("pub const DEVICE_CORE: u32 = 0x0607AF;"));
+    assert!(code.contains("pub const CREATE_LINK: u32 = 10;"));
+    assert!(code.contains("pub struct DeviceCoreV1Client {"));
+    assert!(code.contains(
+        "pub fn create_link(&self, args: &CreateLinkParms) -> ::serde_xdr::RpcResult<()> {"
+    ));
+    assert!(code.contains("pub trait DeviceCoreV1: Send + Sync {"));
+    assert!(code.contains(
+        "impl<T: DeviceCoreV1> ::serde_xdr::rpc::RpcProgram for DeviceCoreV1Server<T> {"
+    ));
+}
+
+#[test]
+fn generator_errors() {
+    let message = |source: &str| generate(source).unwrap_err().message;
+    assert_eq!(message("struct s { foo f; };"), "unknown type `foo`");
+    assert_eq!(message("typedef int a<MAX>;"), "unknown constant `MAX`");
+    assert_eq!(
+        message("const A = 1;\nenum e { A };"),
+        "`A` is already defined"
+    );
+    assert_eq!(
+        message("struct s { int a; };\nunion s switch (int d) { case 0: void; };"),
+        "type `s` is already defined"
+    );
+    assert_eq!(
+        message("union u switch (hyper d) { case 0: void; };"),
+        "a union discriminant has to be an int, unsigned int, bool or enum"
+    );
+    assert_eq!(
+        message("typedef a b;\ntypedef b a;\nunion u switch (a d) { case 1: void; };"),
+        "typedef `b` refers back to itself"
+    );
+    assert_eq!(
+        message("typedef c c;\nstruct s { c x; };"),
+        "typedef `c` refers back to itself"
+    );
+    assert_eq!(
+        message("union u switch (int d) { case 1: void; case 1: int x; };"),
+        "duplicate case in union"
+    );
+    assert_eq!(
+        message("enum e { A, B };\nunion u switch (e d) { case 5: void; };"),
+        "`5` isn't a valid case here"
+    );
+    assert_eq!(
+        message("typedef int a[-1];"),
+        "lengths have to fit in an unsigned int"
+    );
+    assert_eq!(
+        message("program P { version V { void A(void) = 1; void B(int) = 1; } = 1; } = 9;"),
+        "duplicate procedure number"
+    );
+    assert_eq!(
+        message("program P { version V { void A(int, void) = 1; } = 1; } = 9;"),
+        "`void` can only be a procedure's only argument"
+    );
+
+    let err = generate("struct s {\n    int a;\n    missing b;\n};").unwrap_err();
+    assert_eq!((err.line, err.column), (3, 5));
+}
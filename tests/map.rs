@@ -0,0 +1,86 @@
+mod common;
+
+use common::{round_trip, words};
+use serde::{Deserialize, Serialize};
+use serde_xdr::{from_bytes, Deserializer, Serializer};
+
+use std::collections::{BTreeMap, HashMap};
+
+fn sorted<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut ser = Serializer::new(Vec::new()).sort_map_keys(true);
+    value.serialize(&mut ser).unwrap();
+    ser.into_inner()
+}
+
+#[test]
+fn maps_are_arrays_of_pairs() {
+    let mut map = BTreeMap::new();
+    map.insert(2u32, 1u32);
+    map.insert(1u32, 0u32);
+    assert_eq!(round_trip(&map), words(&[2, 1, 0, 2, 1]));
+    assert_eq!(round_trip(&BTreeMap::<u32, u32>::new()), words(&[0]));
+
+    let map: HashMap<String, i32> = vec![(String::from("dc"), -1)].into_iter().collect();
+    let mut bytes = words(&[1, 2]);
+    bytes.extend_from_slice(b"dc\0\0");
+    bytes.extend(words(&[0xFFFF_FFFF]));
+    assert_eq!(round_trip(&map), bytes);
+}
+
+#[test]
+fn sorted_keys_encode_the_same_every_time() {
+    let map: HashMap<u32, u32> = (0..32).map(|k| (k * 7 % 32, k)).collect();
+    let bytes = sorted(&map);
+    let mut expected = words(&[32]);
+    for key in 0..32 {
+        expected.extend(words(&[key, (0..32).find(|k| k * 7 % 32 == key).unwrap()]));
+    }
+    assert_eq!(bytes, expected);
+    assert_eq!(from_bytes::<HashMap<u32, u32>>(&bytes).unwrap(), (map, 260));
+
+    // The order is that of the encoded keys, so a string's length comes before its contents
+    let map: HashMap<&str, u32> = vec![("aa", 1), ("b", 2)].into_iter().collect();
+    let mut expected = words(&[2, 1]);
+    expected.extend_from_slice(b"b\0\0\0");
+    expected.extend(words(&[2, 2]));
+    expected.extend_from_slice(b"aa\0\0");
+    expected.extend(words(&[1]));
+    assert_eq!(sorted(&map), expected);
+}
+
+#[test]
+fn sorting_reaches_nested_maps() {
+    #[derive(Serialize)]
+    struct Outer {
+        inner: Vec<HashMap<u32, u32>>,
+    }
+    let inner: HashMap<u32, u32> = vec![(9, 0), (3, 0), (5, 0)].into_iter().collect();
+    let bytes = sorted(&Outer { inner: vec![inner] });
+    assert_eq!(bytes, words(&[1, 3, 3, 0, 5, 0, 9, 0]));
+}
+
+#[test]
+fn duplicate_keys() {
+    let bytes = words(&[2, 7, 1, 7, 2]);
+
+    // By default the last entry wins, as it would inserting into the map
+    let (map, consumed) = from_bytes::<HashMap<u32, u32>>(&bytes).unwrap();
+    assert_eq!(map.len(), 1);
+    assert_eq!(map[&7], 2);
+    assert_eq!(consumed, 20);
+
+    let mut de = Deserializer::new(&bytes[..]).deny_duplicate_keys(true);
+    let err = HashMap::<u32, u32>::deserialize(&mut de).unwrap_err();
+    assert!(err.to_string().contains("duplicate key"), "{}", err);
+
+    // Distinct keys are fine, and everything is still consumed
+    let mut bytes = words(&[2, 1]);
+    bytes.extend_from_slice(b"a\0\0\0");
+    bytes.extend(words(&[0, 1]));
+    bytes.extend_from_slice(b"b\0\0\0");
+    bytes.extend(words(&[0]));
+    let mut de = Deserializer::new(&bytes[..]).deny_duplicate_keys(true);
+    let map = BTreeMap::<String, u32>::deserialize(&mut de).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(de.get_bytes_consumed(), bytes.len());
+}
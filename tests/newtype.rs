@@ -0,0 +1,44 @@
+mod common;
+
+use common::round_trip;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct LinkId(i32);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct DeviceFlags(u32);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Outer(Inner);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Inner(LinkId);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Link {
+    lid: LinkId,
+    flags: DeviceFlags,
+    nested: Outer,
+}
+
+#[test]
+fn newtype_encodes_as_inner_value() {
+    assert_eq!(round_trip(&LinkId(-2)), vec![0xff, 0xff, 0xff, 0xfe]);
+    assert_eq!(round_trip(&DeviceFlags(0x0807)), vec![0, 0, 0x08, 0x07]);
+}
+
+#[test]
+fn nested_newtypes_round_trip() {
+    assert_eq!(round_trip(&Outer(Inner(LinkId(7)))), vec![0, 0, 0, 7]);
+}
+
+#[test]
+fn newtypes_inside_structs_round_trip() {
+    let link = Link {
+        lid: LinkId(1),
+        flags: DeviceFlags(2),
+        nested: Outer(Inner(LinkId(3))),
+    };
+    assert_eq!(round_trip(&link), vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]);
+}
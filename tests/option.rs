@@ -0,0 +1,89 @@
+mod common;
+
+use common::{round_trip, words};
+use serde_xdr::portmap::{Mapping, PmapList};
+use serde_xdr::{from_bytes, List};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Node {
+    value: u32,
+    next: Option<Box<Node>>,
+}
+
+#[test]
+fn bools_are_four_bytes() {
+    assert_eq!(round_trip(&false), words(&[0]));
+    assert_eq!(round_trip(&true), words(&[1]));
+    assert_eq!(round_trip(&(true, 7u32)), words(&[1, 7]));
+}
+
+#[test]
+fn other_bool_values_are_rejected() {
+    let err = from_bytes::<bool>(&words(&[2])).unwrap_err();
+    assert!(err.to_string().contains("0 or 1"), "{}", err);
+    // The old single byte encoding is too short to decode
+    assert!(from_bytes::<bool>(&[1]).is_err());
+}
+
+#[test]
+fn options_are_optional_data() {
+    assert_eq!(round_trip(&None::<u32>), words(&[0]));
+    assert_eq!(round_trip(&Some(9u32)), words(&[1, 9]));
+    assert_eq!(round_trip(&Some(None::<u32>)), words(&[1, 0]));
+    assert_eq!(
+        round_trip(&Some(String::from("ab"))),
+        [0, 0, 0, 1, 0, 0, 0, 2, b'a', b'b', 0, 0]
+    );
+}
+
+#[test]
+fn linked_lists_chain_through_options() {
+    let list = Node {
+        value: 1,
+        next: Some(Box::new(Node {
+            value: 2,
+            next: None,
+        })),
+    };
+    assert_eq!(round_trip(&list), words(&[1, 1, 2, 0]));
+}
+
+#[test]
+fn option_flags_must_be_bools() {
+    let err = from_bytes::<Option<u32>>(&words(&[2, 9])).unwrap_err();
+    assert!(err.to_string().contains("0 or 1"), "{}", err);
+}
+
+#[test]
+fn lists_encode_like_nested_options() {
+    let nested = Some(Box::new(Node {
+        value: 1,
+        next: Some(Box::new(Node {
+            value: 2,
+            next: None,
+        })),
+    }));
+    let bytes = round_trip(&List(vec![1u32, 2]));
+    assert_eq!(bytes, common::encode(&nested));
+    assert_eq!(round_trip(&List::<u32>(Vec::new())), words(&[0]));
+
+    // A list has to end with FALSE
+    assert!(from_bytes::<List<u32>>(&words(&[1, 5, 1, 6])).is_err());
+}
+
+#[test]
+fn long_lists_decode_without_recursing() {
+    let mappings: Vec<Mapping> = (0..200_000)
+        .map(|port| Mapping {
+            prog: 100_000,
+            vers: 2,
+            prot: 6,
+            port,
+        })
+        .collect();
+    let list = PmapList::from(mappings);
+    let bytes = round_trip(&list);
+    assert_eq!(bytes.len(), 200_000 * 20 + 4);
+}
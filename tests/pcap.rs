@@ -0,0 +1,454 @@
+use serde::Serialize;
+use serde_xdr::pcap::{self, Payload, Protocol};
+use serde_xdr::portmap::*;
+use serde_xdr::record::RecordWriter;
+use serde_xdr::rpc::{call_to_bytes, CallBody, ReplyBody, ReplyData, RpcMsg};
+use serde_xdr::vxi11::*;
+use serde_xdr::{to_bytes, Opaque};
+
+use std::net::SocketAddr;
+
+const SYN: u8 = 0x02;
+const ACK: u8 = 0x10;
+
+fn call<T: Serialize>(xid: u32, prog: u32, vers: u32, proc: u32, args: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    call_to_bytes(xid, &CallBody::new(prog, vers, proc), args, &mut buf).unwrap();
+    buf
+}
+
+fn reply<T: Serialize>(xid: u32, results: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    to_bytes(&RpcMsg::accepted(xid, ReplyData::Success), &mut buf).unwrap();
+    to_bytes(results, &mut buf).unwrap();
+    buf
+}
+
+fn record(msg: &[u8], max_fragment: usize) -> Vec<u8> {
+    let mut writer = RecordWriter::new(Vec::new()).max_fragment_size(max_fragment);
+    writer.write_record(msg).unwrap();
+    writer.into_inner()
+}
+
+fn ipv4(src: SocketAddr, dst: SocketAddr, protocol: u8, transport: &[u8]) -> Vec<u8> {
+    let ip = |addr: SocketAddr| match addr {
+        SocketAddr::V4(addr) => addr.ip().octets(),
+        SocketAddr::V6(_) => unreachable!(),
+    };
+    // Ethernet, with a VLAN tag to step over
+    let mut frame = vec![0; 12];
+    frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x05, 0x08, 0x00]);
+    frame.extend_from_slice(&[0x45, 0, 0, 0, 0, 0, 0x40, 0, 64, protocol, 0, 0]);
+    let total = (20 + transport.len()) as u16;
+    frame[20..22].copy_from_slice(&total.to_be_bytes());
+    frame.extend_from_slice(&ip(src));
+    frame.extend_from_slice(&ip(dst));
+    frame.extend_from_slice(transport);
+    // Short frames get padded out, which the IP length has to cut back off
+    frame.resize(frame.len().max(60), 0);
+    frame
+}
+
+fn tcp(src: SocketAddr, dst: SocketAddr, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let mut segment = Vec::new();
+    segment.extend_from_slice(&src.port().to_be_bytes());
+    segment.extend_from_slice(&dst.port().to_be_bytes());
+    segment.extend_from_slice(&seq.to_be_bytes());
+    segment.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xFF, 0xFF, 0, 0, 0, 0]);
+    segment.extend_from_slice(payload);
+    ipv4(src, dst, 6, &segment)
+}
+
+fn udp(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let mut datagram = Vec::new();
+    datagram.extend_from_slice(&src.port().to_be_bytes());
+    datagram.extend_from_slice(&dst.port().to_be_bytes());
+    datagram.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    datagram.extend_from_slice(&[0, 0]);
+    datagram.extend_from_slice(payload);
+    ipv4(src, dst, 17, &datagram)
+}
+
+// Classic pcap, little-endian with microsecond timestamps
+fn pcap_file(frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut file = Vec::new();
+    for word in &[0xA1B2_C3D4u32, 0x0004_0002, 0, 0, 65535, 1] {
+        file.extend_from_slice(&word.to_le_bytes());
+    }
+    for (micros, frame) in frames {
+        for word in &[
+            1_700_000_000,
+            *micros,
+            frame.len() as u32,
+            frame.len() as u32,
+        ] {
+            file.extend_from_slice(&word.to_le_bytes());
+        }
+        file.extend_from_slice(frame);
+    }
+    file
+}
+
+fn block(file: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let len = (12 + body.len()).div_ceil(4) * 4;
+    file.extend_from_slice(&block_type.to_be_bytes());
+    file.extend_from_slice(&(len as u32).to_be_bytes());
+    file.extend_from_slice(body);
+    file.resize(file.len() + len - 12 - body.len(), 0);
+    file.extend_from_slice(&(len as u32).to_be_bytes());
+}
+
+// Big-endian pcapng with nanosecond timestamps
+fn pcapng_file(frames: &[(u64, Vec<u8>)]) -> Vec<u8> {
+    let mut file = Vec::new();
+    let mut section = vec![0x1A, 0x2B, 0x3C, 0x4D, 0, 1, 0, 0];
+    section.extend_from_slice(&[0xFF; 8]);
+    block(&mut file, 0x0A0D_0D0A, &section);
+    // Ethernet, with if_tsresol = 9 and the end of options
+    block(
+        &mut file,
+        1,
+        &[
+            0, 1, 0, 0, 0, 0, 0xFF, 0xFF, 0, 9, 0, 1, 9, 0, 0, 0, 0, 0, 0, 0,
+        ],
+    );
+    for (nanos, frame) in frames {
+        let mut body = vec![0; 4];
+        body.extend_from_slice(&((nanos >> 32) as u32).to_be_bytes());
+        body.extend_from_slice(&(*nanos as u32).to_be_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        body.extend_from_slice(frame);
+        block(&mut file, 6, &body);
+    }
+    file
+}
+
+fn decoded<T: std::fmt::Debug>(value: &T) -> Option<Payload> {
+    Some(Payload::Decoded(format!("{:#?}", value)))
+}
+
+#[test]
+fn reassembles_vxi11_over_tcp() {
+    let client: SocketAddr = "10.0.0.1:900".parse().unwrap();
+    let instrument: SocketAddr = "10.0.0.2:1024".parse().unwrap();
+
+    let parms = CreateLinkParms {
+        client_id: 1,
+        lock_device: false,
+        lock_timeout: 0,
+        device: String::from("inst0"),
+    };
+    let resp = CreateLinkResp {
+        lid: 3,
+        abort_port: 1025,
+        max_recv_size: 1024,
+        ..CreateLinkResp::default()
+    };
+    let write = DeviceWriteParms {
+        lid: 3,
+        flags: DeviceFlags::END,
+        data: Opaque(b"*IDN?\n".to_vec()),
+        ..DeviceWriteParms::default()
+    };
+    let read = DeviceReadParms {
+        lid: 3,
+        request_size: 256,
+        ..DeviceReadParms::default()
+    };
+    let idn = DeviceReadResp {
+        reason: ReadReason::END,
+        data: Opaque(b"ACME,1\n".to_vec()),
+        ..DeviceReadResp::default()
+    };
+
+    let create = record(&call(1, DEVICE_CORE, 1, CREATE_LINK, &parms), 1 << 20);
+    // Two fragments in one record
+    let write_call = record(&call(2, DEVICE_CORE, 1, DEVICE_WRITE, &write), 48);
+    // Two calls in one segment, answered in the other order
+    let mut pipelined = record(&call(3, DEVICE_CORE, 1, DEVICE_READ, &read), 1 << 20);
+    pipelined.extend(record(&call(4, DEVICE_CORE, 1, DESTROY_LINK, &3), 1 << 20));
+
+    // The client's sequence numbers wrap partway through
+    let mut seq = 0xFFFF_FFF0u32;
+    let mut next = |len: usize| {
+        let at = seq;
+        seq = seq.wrapping_add(len as u32);
+        at
+    };
+    let syn = next(1);
+    let (create_seq, write_seq) = (next(create.len()), next(write_call.len()));
+    let pipelined_seq = next(pipelined.len());
+
+    let reply_create = record(&reply(1, &resp), 1 << 20);
+    let reply_write = record(&reply(2, &DeviceWriteResp::default()), 1 << 20);
+    let reply_destroy = record(&reply(4, &DeviceError::default()), 1 << 20);
+    let reply_read = record(&reply(3, &idn), 1 << 20);
+    let mut server_seq = 100;
+    let mut server = |payload: &[u8]| {
+        let frame = tcp(instrument, client, server_seq, ACK, payload);
+        server_seq += payload.len() as u32;
+        frame
+    };
+
+    let frames = vec![
+        (0, tcp(client, instrument, syn, SYN, &[])),
+        (10, tcp(instrument, client, 99, SYN | ACK, &[])),
+        // Out of order, then retransmitted
+        (
+            20,
+            tcp(
+                client,
+                instrument,
+                create_seq.wrapping_add(10),
+                ACK,
+                &create[10..],
+            ),
+        ),
+        (30, tcp(client, instrument, create_seq, ACK, &create[..10])),
+        (40, tcp(client, instrument, create_seq, ACK, &create[..20])),
+        (1000, server(&reply_create)),
+        (1100, tcp(client, instrument, write_seq, ACK, &write_call)),
+        (1500, server(&reply_write)),
+        (
+            2000,
+            tcp(client, instrument, pipelined_seq, ACK, &pipelined),
+        ),
+        (2500, server(&reply_destroy[..6])),
+        (2600, server(&reply_destroy[6..])),
+        (3000, server(&reply_read)),
+    ];
+    let transactions = pcap::transactions(&pcap_file(&frames)).unwrap();
+    assert_eq!(transactions.len(), 4);
+
+    let create = &transactions[0];
+    assert_eq!(
+        (create.protocol, create.client, create.server, create.xid),
+        (Protocol::Tcp, client, instrument, 1)
+    );
+    let call = create.call.as_ref().unwrap();
+    assert_eq!(call.header.proc, CREATE_LINK);
+    assert_eq!(call.time.as_micros(), 30);
+    assert_eq!(Some(call.args.clone()), decoded(&parms));
+    let reply = create.reply.as_ref().unwrap();
+    assert_eq!(reply.time.as_micros(), 1000);
+    assert_eq!(reply.results, decoded(&resp));
+
+    let call = transactions[1].call.as_ref().unwrap();
+    assert_eq!(Some(call.args.clone()), decoded(&write));
+    assert_eq!(
+        transactions[1].reply.as_ref().unwrap().results,
+        decoded(&DeviceWriteResp::default())
+    );
+
+    assert_eq!(transactions[2].xid, 3);
+    assert_eq!(
+        transactions[2].reply.as_ref().unwrap().results,
+        decoded(&idn)
+    );
+    let destroy = &transactions[3];
+    assert_eq!(
+        Some(destroy.call.as_ref().unwrap().args.clone()),
+        decoded(&3)
+    );
+    assert_eq!(destroy.reply.as_ref().unwrap().time.as_micros(), 2600);
+
+    assert_eq!(
+        transactions[3].to_string(),
+        "0.002000 tcp 10.0.0.1:900 -> 10.0.0.2:1024 xid 0x00000004 vxi11_core v1 destroy_link
+  call: 3
+  reply +0.000600: DeviceError {
+      error: DeviceErrorCode(
+          0,
+      ),
+  }
+"
+    );
+}
+
+#[test]
+fn pairs_portmap_over_udp() {
+    let client: SocketAddr = "192.168.1.5:700".parse().unwrap();
+    let portmapper: SocketAddr = "192.168.1.9:111".parse().unwrap();
+    let other: SocketAddr = "192.168.1.5:701".parse().unwrap();
+
+    let mapping = Mapping {
+        prog: DEVICE_CORE,
+        vers: 1,
+        prot: IPPROTO_TCP,
+        port: 0,
+    };
+    let mut unavail = Vec::new();
+    to_bytes(&RpcMsg::accepted(9, ReplyData::ProgUnavail), &mut unavail).unwrap();
+
+    let frames = vec![
+        (
+            1_000,
+            udp(
+                client,
+                portmapper,
+                &call(7, PMAP_PROG, 2, PMAPPROC_GETPORT, &mapping),
+            ),
+        ),
+        // Not RPC at all
+        (
+            1_500,
+            udp(
+                client,
+                "192.168.1.1:53".parse().unwrap(),
+                b"\x12\x34\x01\x00\x00\x01",
+            ),
+        ),
+        // A retransmission of the same call
+        (
+            2_000,
+            udp(
+                client,
+                portmapper,
+                &call(7, PMAP_PROG, 2, PMAPPROC_GETPORT, &mapping),
+            ),
+        ),
+        (5_000, udp(portmapper, client, &reply(7, &1024u32))),
+        // A reply to a call made before the capture started
+        (6_000, udp(portmapper, other, &reply(8, &1u32))),
+        (
+            7_000,
+            udp(
+                client,
+                portmapper,
+                &call(9, 200_000, 1, 3, &Opaque(vec![1, 2])),
+            ),
+        ),
+        (7_250, udp(portmapper, client, &unavail)),
+    ];
+    let capture = pcapng_file(
+        &frames
+            .into_iter()
+            .map(|(micros, frame)| (1_700_000_000_000_000_000 + micros * 1000, frame))
+            .collect::<Vec<_>>(),
+    );
+    let transactions = pcap::transactions(&capture).unwrap();
+    assert_eq!(transactions.len(), 3);
+
+    let getport = &transactions[0];
+    assert_eq!(getport.protocol, Protocol::Udp);
+    assert_eq!(
+        getport.to_string(),
+        "0.000000 udp 192.168.1.5:700 -> 192.168.1.9:111 xid 0x00000007 portmap v2 getport
+  call: Mapping {
+      prog: 395183,
+      vers: 1,
+      prot: 6,
+      port: 0,
+  }
+  reply +0.004000: 1024
+"
+    );
+
+    let orphan = &transactions[1];
+    assert_eq!((orphan.client, orphan.server), (other, portmapper));
+    assert!(orphan.call.is_none());
+    assert_eq!(
+        orphan.reply.as_ref().unwrap().results,
+        Some(Payload::Undecoded(vec![0, 0, 0, 1]))
+    );
+    assert!(orphan.to_string().contains("(call not captured)"));
+
+    let unknown = &transactions[2];
+    assert_eq!(
+        unknown.call.as_ref().unwrap().args,
+        Payload::Undecoded(vec![0, 0, 0, 2, 1, 2, 0, 0])
+    );
+    let reply = unknown.reply.as_ref().unwrap();
+    assert!(reply.results.is_none());
+    assert!(matches!(reply.header, ReplyBody::Accepted(_)));
+    assert_eq!(
+        unknown.to_string(),
+        "0.006000 udp 192.168.1.5:700 -> 192.168.1.9:111 xid 0x00000009 prog 200000 v1 proc 3
+  call: 8 bytes: 00000002 01020000
+  reply +0.000250: ProgUnavail
+"
+    );
+}
+
+#[test]
+fn rejects_other_files() {
+    let err = pcap::transactions(b"GIF89a").unwrap_err();
+    assert_eq!(err.to_string(), "not a pcap or pcapng file");
+
+    let err = pcap::transactions(&pcap_file(&[])[..20]).unwrap_err();
+    assert_eq!(err.to_string(), "capture file is truncated");
+
+    // Block lengths are a multiple of 4
+    let mut misaligned = pcapng_file(&[]);
+    misaligned[7] += 2;
+    let err = pcap::transactions(&misaligned).unwrap_err();
+    assert_eq!(err.to_string(), "bad pcapng block length");
+}
+
+#[test]
+fn truncated_last_packets_are_dropped() {
+    let frames = [(0, vec![1; 60]), (1, vec![2; 60])];
+    let whole = pcap_file(&frames);
+    // Cut partway through the last packet's data, and partway through its header
+    for cut in &[1, 61, 70] {
+        let packets = pcap::read_packets(&whole[..whole.len() - cut]).unwrap();
+        assert_eq!(packets.len(), 1, "{}", cut);
+        assert_eq!(packets[0].data, vec![1; 60]);
+    }
+
+    let whole = pcapng_file(&[(0, vec![1; 60]), (1, vec![2; 60])]);
+    let packets = pcap::read_packets(&whole[..whole.len() - 5]).unwrap();
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].data, vec![1; 60]);
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_prints_transactions() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let client: SocketAddr = "10.1.1.1:800".parse().unwrap();
+    let portmapper: SocketAddr = "10.1.1.2:111".parse().unwrap();
+    let capture = pcap_file(&[
+        (
+            0,
+            udp(
+                client,
+                portmapper,
+                &call(1, PMAP_PROG, 2, PMAPPROC_NULL, &()),
+            ),
+        ),
+        (10, udp(portmapper, client, &reply(1, &()))),
+        (
+            20,
+            udp(
+                client,
+                portmapper,
+                &call(2, PMAP_PROG, 2, PMAPPROC_DUMP, &()),
+            ),
+        ),
+    ]);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_serde-xdr"))
+        .arg("pcap")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&capture).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "0.000000 udp 10.1.1.1:800 -> 10.1.1.2:111 xid 0x00000001 portmap v2 null
+  call: ()
+  reply +0.000010: ()
+
+0.000020 udp 10.1.1.1:800 -> 10.1.1.2:111 xid 0x00000002 portmap v2 dump
+  call: ()
+  (no reply captured)
+"
+    );
+}
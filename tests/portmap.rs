@@ -0,0 +1,52 @@
+use serde_xdr::portmap::{Mapping, PortmapClient, Portmapper, IPPROTO_TCP, IPPROTO_UDP};
+use serde_xdr::rpc::{Call, ProcError, RpcClient, RpcProgram, RpcServer};
+
+const PROG: u32 = 0x2000_0003;
+
+struct Echo;
+
+impl RpcProgram for Echo {
+    fn program(&self) -> u32 {
+        PROG
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (1, 1)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        let value: String = call.args()?;
+        call.reply(&value)
+    }
+}
+
+#[test]
+fn discover_and_connect() {
+    let portmapper = Portmapper::new().spawn("127.0.0.1:0").unwrap();
+    let mut server = RpcServer::new();
+    server.register(Echo);
+    let echo = server.spawn_tcp("127.0.0.1:0").unwrap();
+
+    let mut pmap = PortmapClient::connect_tcp(portmapper.local_addr()).unwrap();
+    let mapping = Mapping {
+        prog: PROG,
+        vers: 1,
+        prot: IPPROTO_TCP,
+        port: echo.local_addr().port() as u32,
+    };
+    assert!(pmap.set(&mapping).unwrap());
+    assert!(!pmap.set(&mapping).unwrap());
+    assert!(pmap.dump().unwrap().contains(&mapping));
+
+    let mut pmap = PortmapClient::connect_udp(portmapper.local_addr()).unwrap();
+    let port = pmap.getport(PROG, 1, IPPROTO_TCP).unwrap();
+    assert_eq!(port, echo.local_addr().port());
+    assert_eq!(pmap.getport(PROG, 1, IPPROTO_UDP).unwrap(), 0);
+
+    let mut client = RpcClient::connect_tcp(("127.0.0.1", port)).unwrap();
+    let reply: String = client.call(PROG, 1, 1, &"*IDN?").unwrap();
+    assert_eq!(reply, "*IDN?");
+
+    assert!(pmap.unset(&mapping).unwrap());
+    assert_eq!(pmap.getport(PROG, 1, IPPROTO_TCP).unwrap(), 0);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::round_trip;
+use serde_xdr::Quadruple;
+
+// Sign, 15 exponent bits and the top 48 of 112 mantissa bits, as a binary128 bit pattern
+fn quad(top: u64) -> u128 {
+    u128::from(top) << 64
+}
+
+#[test]
+fn hyper_128_bit_integers() {
+    let mut minus_two = vec![0xFF; 16];
+    minus_two[15] = 0xFE;
+    assert_eq!(round_trip(&-2i128), minus_two);
+    assert_eq!(round_trip(&u128::MAX), vec![0xFF; 16]);
+    let mut one = vec![0; 16];
+    one[15] = 1;
+    assert_eq!(round_trip(&1u128), one);
+    assert_eq!(round_trip(&i128::MIN)[0], 0x80);
+}
+
+#[test]
+fn quadruple_is_16_raw_bytes() {
+    let value = Quadruple::from_f64(1.0);
+    let mut bytes = vec![0x3F, 0xFF];
+    bytes.resize(16, 0);
+    assert_eq!(round_trip(&value), bytes);
+    assert_eq!(value.to_bytes()[..], bytes[..]);
+}
+
+#[test]
+fn from_f64_known_values() {
+    assert_eq!(
+        Quadruple::from_f64(1.0).to_bits(),
+        quad(0x3FFF_0000_0000_0000)
+    );
+    assert_eq!(
+        Quadruple::from_f64(-2.5).to_bits(),
+        quad(0xC000_4000_0000_0000)
+    );
+    assert_eq!(Quadruple::from_f64(0.0).to_bits(), 0);
+    assert_eq!(Quadruple::from_f64(-0.0).to_bits(), 1 << 127);
+    assert_eq!(
+        Quadruple::from_f64(f64::INFINITY).to_bits(),
+        quad(0x7FFF_0000_0000_0000)
+    );
+    assert_eq!(
+        Quadruple::from_f64(f64::NEG_INFINITY).to_bits(),
+        quad(0xFFFF_0000_0000_0000)
+    );
+    assert_eq!(
+        Quadruple::from_f64(f64::NAN).to_bits(),
+        quad(0x7FFF_8000_0000_0000)
+    );
+
+    // The smallest f64 subnormal, 2^-1074, is a normal binary128 with exponent 16383 - 1074
+    let smallest = f64::from_bits(1);
+    assert_eq!(
+        Quadruple::from_f64(smallest).to_bits(),
+        quad(0x3BCD_0000_0000_0000)
+    );
+    // The largest f64 subnormal keeps all 52 of its bits after normalising
+    let largest = f64::from_bits((1 << 52) - 1);
+    assert_eq!(
+        Quadruple::from_f64(largest).to_bits(),
+        quad(0x3C00_FFFF_FFFF_FFFF) | 0xE000_0000_0000_0000
+    );
+}
+
+#[test]
+fn f64_round_trips_exactly() {
+    for &value in &[
+        1.0,
+        -0.0,
+        0.1,
+        -1.5e300,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+        f64::from_bits(1),
+        f64::from_bits((1 << 52) - 1),
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ] {
+        let back = Quadruple::from_f64(value).to_f64();
+        assert_eq!(back.to_bits(), value.to_bits(), "{:e}", value);
+    }
+    assert!(Quadruple::from_f64(f64::NAN).to_f64().is_nan());
+}
+
+#[test]
+fn to_f64_rounds_to_nearest_even() {
+    let one = Quadruple::from_f64(1.0).to_bits();
+    // Half an f64 ulp above 1.0 is a tie, and 1.0 is the even neighbour
+    assert_eq!(Quadruple::from_bits(one | 1 << 59).to_f64(), 1.0);
+    assert_eq!(
+        Quadruple::from_bits(one | 1 << 59 | 1).to_f64(),
+        1.0 + f64::EPSILON
+    );
+    // Just under half an ulp rounds down
+    assert_eq!(Quadruple::from_bits(one | ((1 << 59) - 1)).to_f64(), 1.0);
+    // A tie above an odd mantissa rounds up
+    let odd = Quadruple::from_f64(1.0 + f64::EPSILON).to_bits();
+    assert_eq!(
+        Quadruple::from_bits(odd | 1 << 59).to_f64(),
+        1.0 + 2.0 * f64::EPSILON
+    );
+}
+
+#[test]
+fn to_f64_overflows_to_infinity() {
+    // 2^1024 is one past the f64 exponent range
+    assert_eq!(
+        Quadruple::from_bits(quad(0x43FF_0000_0000_0000)).to_f64(),
+        f64::INFINITY
+    );
+    assert_eq!(
+        Quadruple::from_bits(quad(0xC3FF_0000_0000_0000)).to_f64(),
+        f64::NEG_INFINITY
+    );
+    // f64::MAX plus half an ulp ties, and the odd mantissa carries all the way into infinity
+    let max = Quadruple::from_f64(f64::MAX).to_bits();
+    assert_eq!(Quadruple::from_bits(max | 1 << 59).to_f64(), f64::INFINITY);
+    assert_eq!(Quadruple::from_bits(max | 1 << 58).to_f64(), f64::MAX);
+}
+
+#[test]
+fn to_f64_underflows_through_subnormals() {
+    // 2^-1075 is half the smallest subnormal, a tie that rounds to the even zero
+    let half = quad(0x3BCC_0000_0000_0000);
+    assert_eq!(Quadruple::from_bits(half).to_f64().to_bits(), 0);
+    // 1.5 * 2^-1075 rounds up to the smallest subnormal
+    let above = quad(0x3BCC_8000_0000_0000);
+    assert_eq!(Quadruple::from_bits(above).to_f64(), f64::from_bits(1));
+    // 3 * 2^-1075 is a tie between 2^-1074 and 2^-1073, and rounds to the even 2^-1073
+    let three = quad(0x3BCD_8000_0000_0000);
+    assert_eq!(Quadruple::from_bits(three).to_f64(), f64::from_bits(2));
+    // Far below f64 range, including binary128 subnormals, the sign is all that's left
+    assert_eq!(Quadruple::from_bits(1).to_f64().to_bits(), 0);
+    let tiny = Quadruple::from_bits(1 << 127 | quad(0x0001_0000_0000_0000));
+    assert_eq!(tiny.to_f64().to_bits(), 1 << 63);
+}
+
+#[test]
+fn to_f64_keeps_nan_a_nan() {
+    assert!(Quadruple::from_bits(quad(0x7FFF_8000_0000_0000))
+        .to_f64()
+        .is_nan());
+    // A payload only in the low bits would look like infinity if it were just truncated
+    let low_payload = Quadruple::from_bits(quad(0x7FFF_0000_0000_0000) | 1);
+    assert!(low_payload.to_f64().is_nan());
+    assert_eq!(
+        Quadruple::from_bits(quad(0x7FFF_0000_0000_0000)).to_f64(),
+        f64::INFINITY
+    );
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::words;
+use serde_xdr::record::{RecordReader, RecordWriter, LAST_FRAGMENT};
+
+use std::io::{self, Read};
+
+// Hands out the stream in the given chunk sizes, timing out between chunks
+struct Stalling<'a> {
+    data: &'a [u8],
+    chunks: Vec<usize>,
+    stalled: bool,
+}
+
+impl Read for Stalling<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.data.is_empty() {
+            return Ok(0);
+        }
+        if !self.stalled {
+            self.stalled = true;
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let chunk = if self.chunks.is_empty() {
+            self.data.len()
+        } else {
+            self.chunks.remove(0)
+        };
+        let len = chunk.min(buf.len()).min(self.data.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        self.stalled = false;
+        Ok(len)
+    }
+}
+
+fn fragment(data: &[u8], last: bool) -> Vec<u8> {
+    let header = data.len() as u32 | if last { LAST_FRAGMENT } else { 0 };
+    let mut bytes = words(&[header]);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+fn writer_splits_records_into_fragments() {
+    let mut writer = RecordWriter::new(Vec::new()).max_fragment_size(3);
+    writer.write_record(b"abcdefgh").unwrap();
+    writer.write_record(b"").unwrap();
+    let mut expected = fragment(b"abc", false);
+    expected.extend(fragment(b"def", false));
+    expected.extend(fragment(b"gh", true));
+    expected.extend(fragment(b"", true));
+    assert_eq!(writer.into_inner(), expected);
+}
+
+#[test]
+fn reader_reassembles_fragments() {
+    let mut stream = fragment(b"one ", false);
+    stream.extend(fragment(b"", false));
+    stream.extend(fragment(b"two ", false));
+    stream.extend(fragment(b"three", true));
+    stream.extend(fragment(b"next", true));
+    stream.extend(fragment(b"", true));
+
+    let mut reader = RecordReader::new(&stream[..]);
+    assert_eq!(reader.read_record().unwrap(), Some(&b"one two three"[..]));
+    assert_eq!(reader.read_record().unwrap(), Some(&b"next"[..]));
+    assert_eq!(reader.read_record().unwrap(), Some(&b""[..]));
+    // A stream that ends between records ends cleanly
+    assert_eq!(reader.read_record().unwrap(), None);
+}
+
+#[test]
+fn writer_output_reads_back() {
+    let mut writer = RecordWriter::new(Vec::new()).max_fragment_size(5);
+    let records: Vec<Vec<u8>> = (0..20u8).map(|n| (0..n).collect()).collect();
+    for record in &records {
+        writer.write_record(record).unwrap();
+    }
+    let stream = writer.into_inner();
+    let mut reader = RecordReader::new(&stream[..]);
+    for record in &records {
+        assert_eq!(reader.read_record().unwrap(), Some(&record[..]));
+    }
+    assert_eq!(reader.read_record().unwrap(), None);
+}
+
+#[test]
+fn records_over_the_limit_are_rejected() {
+    let mut stream = fragment(b"12345", false);
+    stream.extend(fragment(b"678", true));
+    let mut reader = RecordReader::new(&stream[..]).max_record_size(8);
+    assert_eq!(reader.read_record().unwrap(), Some(&b"12345678"[..]));
+
+    // The limit counts every fragment, and trips before the fragment that crosses it is read
+    let mut stream = fragment(b"12345", false);
+    stream.extend(words(&[LAST_FRAGMENT | 0x7FFF_FFFF]));
+    let mut reader = RecordReader::new(&stream[..]).max_record_size(8);
+    let err = reader.read_record().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("maximum size of 8"), "{}", err);
+}
+
+#[test]
+fn truncated_fragments_are_errors() {
+    // Partway through a fragment
+    let stream = fragment(b"abcdefgh", true);
+    let mut reader = RecordReader::new(&stream[..stream.len() - 3]);
+    let err = reader.read_record().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    // After a fragment that said more were coming
+    let stream = fragment(b"abcd", false);
+    let mut reader = RecordReader::new(&stream[..]);
+    let err = reader.read_record().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn reads_resume_after_a_timeout() {
+    let mut stream = fragment(b"abcdef", false);
+    stream.extend(fragment(b"gh", true));
+    stream.extend(fragment(b"next", true));
+    // Splits land inside the first header, inside a fragment body and inside the next header
+    let mut reader = RecordReader::new(Stalling {
+        data: &stream,
+        chunks: vec![2, 5, 5, 2],
+        stalled: false,
+    });
+
+    let mut timeouts = 0;
+    let record = loop {
+        match reader.read_record() {
+            Ok(record) => break record.map(<[u8]>::to_vec),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => timeouts += 1,
+            Err(e) => panic!("{}", e),
+        }
+    };
+    assert_eq!(record, Some(b"abcdefgh".to_vec()));
+    assert!(timeouts >= 4, "{}", timeouts);
+
+    loop {
+        match reader.read_record() {
+            Ok(record) => {
+                assert_eq!(record, Some(&b"next"[..]));
+                break;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+#[test]
+fn a_partial_header_is_not_a_clean_end() {
+    let stream = fragment(b"abcd", true);
+    let mut reader = RecordReader::new(&stream[..2]);
+    let err = reader.read_record().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,108 @@
+use serde_xdr::record::RecordCodec;
+use serde_xdr::rpc::{AsyncRpcClient, MsgBody, ReplyData, RpcMsg};
+use serde_xdr::{to_bytes, Deserializer, RpcError};
+
+use bytes::BytesMut;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+const PROG: u32 = 0x2000_0004;
+
+// Answers with the argument plus one
+fn answer(request: &[u8]) -> Vec<u8> {
+    let mut de = Deserializer::new(request);
+    let msg = RpcMsg::deserialize(&mut de).unwrap();
+    assert!(matches!(msg.body, MsgBody::Call(_)));
+    let arg = u32::deserialize(&mut de).unwrap();
+    let mut reply = Vec::new();
+    to_bytes(&RpcMsg::accepted(msg.xid, ReplyData::Success), &mut reply).unwrap();
+    to_bytes(&(arg + 1), &mut reply).unwrap();
+    reply
+}
+
+// Collects `batch` calls before answering them in reverse order
+async fn server(batch: usize) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut framed = Framed::new(stream, RecordCodec::new().max_fragment_size(8));
+        let mut requests = Vec::new();
+        while let Some(request) = framed.next().await {
+            requests.push(request.unwrap());
+            if requests.len() == batch {
+                for request in requests.drain(..).rev() {
+                    framed.send(answer(&request)).await.unwrap();
+                }
+            }
+        }
+    });
+    port
+}
+
+#[test]
+fn codec_splits_and_reassembles_records() {
+    let mut codec = RecordCodec::new().max_fragment_size(3);
+    let mut buf = BytesMut::new();
+    codec.encode(&[1u8, 2, 3, 4, 5][..], &mut buf).unwrap();
+    codec.encode(Vec::new(), &mut buf).unwrap();
+    #[rustfmt::skip]
+    let expected = [
+        0x00, 0x00, 0x00, 0x03, 1, 2, 3,
+        0x80, 0x00, 0x00, 0x02, 4, 5,
+        0x80, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(&buf[..], &expected[..]);
+
+    // Feed the bytes one at a time to check records only come out once complete
+    let mut codec = RecordCodec::new();
+    let mut src = BytesMut::new();
+    let mut records = Vec::new();
+    for &byte in &expected {
+        src.extend_from_slice(&[byte]);
+        while let Some(record) = codec.decode(&mut src).unwrap() {
+            records.push(record.to_vec());
+        }
+    }
+    assert_eq!(records, vec![vec![1, 2, 3, 4, 5], vec![]]);
+
+    let mut codec = RecordCodec::new().max_record_size(4);
+    let mut src = BytesMut::from(&expected[..]);
+    assert!(codec.decode(&mut src).is_err());
+}
+
+#[tokio::test]
+async fn concurrent_calls_are_matched_by_xid() {
+    let port = server(3).await;
+    let client = AsyncRpcClient::connect_tcp(("127.0.0.1", port))
+        .await
+        .unwrap();
+    let other = client.clone();
+    let (a, b, c) = tokio::join!(
+        client.call::<_, u32>(PROG, 1, 1, &10u32),
+        other.call::<_, u32>(PROG, 1, 1, &20u32),
+        client.call::<_, u32>(PROG, 1, 1, &30u32),
+    );
+    assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (11, 21, 31));
+}
+
+#[tokio::test]
+async fn unanswered_call_times_out() {
+    let port = server(2).await;
+    let client = AsyncRpcClient::connect_tcp(("127.0.0.1", port))
+        .await
+        .unwrap()
+        .timeout(Duration::from_millis(100));
+    let err = client.call::<_, u32>(PROG, 1, 1, &1u32).await.unwrap_err();
+    assert!(matches!(err, RpcError::Timeout));
+
+    // The second call completes the batch, so the stale reply to the first one is discarded
+    let res: u32 = client
+        .call_timeout(PROG, 1, 1, &2u32, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(res, 3);
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::{encode, words};
+use serde::Deserialize;
+use serde_xdr::record::{RecordReader, RecordWriter};
+use serde_xdr::rpc::auth::AUTH_SYS;
+use serde_xdr::rpc::{
+    Auth, AuthSysParms, CallBody, MsgBody, OpaqueAuth, ReplyData, RpcClient, RpcMsg, MAX_AUTH_BYTES,
+};
+use serde_xdr::{from_bytes, to_bytes, Deserializer, EncoderError, Opaque, RpcError};
+
+use std::io;
+use std::net::TcpListener;
+use std::thread;
+
+// Credential and verifier bytes as a Linux NFS client sends them: AUTH_SYS for uid and gid 1000
+// with supplementary groups 1000 and 27, then AUTH_NONE
+#[rustfmt::skip]
+const CAPTURED_AUTH: [u8; 52] = [
+    0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x24,
+    0x65, 0x4f, 0x2a, 0x10,
+    0x00, 0x00, 0x00, 0x07, b'b', b'e', b'n', b'c', b'h', b'0', b'1', 0x00,
+    0x00, 0x00, 0x03, 0xe8,
+    0x00, 0x00, 0x03, 0xe8,
+    0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x1b,
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+];
+
+fn captured_cred() -> Auth {
+    Auth::Sys(AuthSysParms {
+        stamp: 0x654f_2a10,
+        machinename: String::from("bench01"),
+        uid: 1000,
+        gid: 1000,
+        gids: vec![1000, 27],
+    })
+}
+
+#[test]
+fn auth_bodies_are_limited_when_encoding() {
+    let mut opaque = OpaqueAuth {
+        flavor: AUTH_SYS,
+        body: Opaque(vec![0; MAX_AUTH_BYTES]),
+    };
+    assert_eq!(encode(&opaque).len(), 8 + MAX_AUTH_BYTES);
+
+    opaque.body.0.push(0);
+    let mut buf = Vec::new();
+    let err = to_bytes(&opaque, &mut buf).unwrap_err();
+    assert!(err.to_string().contains("at most 400"), "{}", err);
+}
+
+#[test]
+fn auth_bodies_are_limited_when_decoding() {
+    let mut bytes = words(&[AUTH_SYS, MAX_AUTH_BYTES as u32]);
+    bytes.resize(bytes.len() + MAX_AUTH_BYTES, 0);
+    assert!(from_bytes::<OpaqueAuth>(&bytes).is_ok());
+
+    let mut bytes = words(&[AUTH_SYS, MAX_AUTH_BYTES as u32 + 4]);
+    bytes.resize(bytes.len() + MAX_AUTH_BYTES + 4, 0);
+    let err = from_bytes::<OpaqueAuth>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("at most 400"), "{}", err);
+}
+
+#[test]
+fn auth_sys_body_must_be_used_up() {
+    let parms = AuthSysParms {
+        stamp: 1,
+        machinename: String::from("h"),
+        uid: 0,
+        gid: 0,
+        gids: Vec::new(),
+    };
+    let mut body = encode(&parms);
+    let opaque = OpaqueAuth {
+        flavor: AUTH_SYS,
+        body: Opaque(body.clone()),
+    };
+    assert_eq!(Auth::from_opaque(&opaque).unwrap(), Auth::Sys(parms));
+
+    body.extend(words(&[0]));
+    let opaque = OpaqueAuth {
+        flavor: AUTH_SYS,
+        body: Opaque(body),
+    };
+    let err = Auth::from_opaque(&opaque).unwrap_err();
+    assert!(err.to_string().contains("4 trailing bytes"), "{}", err);
+}
+
+#[test]
+fn rpc_errors_convert_to_the_base_error() {
+    let err: EncoderError = RpcError::Xdr(EncoderError::Unknown(String::from("bad"))).into();
+    assert_eq!(err.to_string(), "bad");
+    let err: EncoderError = RpcError::Timeout.into();
+    assert!(matches!(err, EncoderError::Io(ref e) if e.kind() == io::ErrorKind::TimedOut));
+    let err: EncoderError = RpcError::ProcUnavail.into();
+    assert_eq!(err.to_string(), "procedure unavailable");
+}
+
+#[test]
+fn auth_sys_matches_a_capture() {
+    let mut de = Deserializer::new(&CAPTURED_AUTH[..]);
+    assert_eq!(Auth::deserialize(&mut de).unwrap(), captured_cred());
+    assert_eq!(Auth::deserialize(&mut de).unwrap(), Auth::None);
+    assert_eq!(de.get_bytes_consumed(), CAPTURED_AUTH.len());
+
+    let mut bytes = encode(&captured_cred());
+    bytes.extend(encode(&Auth::None));
+    assert_eq!(bytes, CAPTURED_AUTH);
+
+    let opaque = captured_cred().to_opaque().unwrap();
+    assert_eq!(opaque.flavor, AUTH_SYS);
+    assert_eq!(opaque.body.0[..], CAPTURED_AUTH[8..44]);
+}
+
+#[test]
+fn credentials_go_out_with_every_call() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = RecordReader::new(stream.try_clone().unwrap());
+        let mut writer = RecordWriter::new(stream);
+        let mut creds = Vec::new();
+        while let Some(request) = reader.read_record().unwrap() {
+            let (msg, _) = from_bytes::<RpcMsg>(request).unwrap();
+            let call = match msg.body {
+                MsgBody::Call(call) => call,
+                MsgBody::Reply(_) => panic!("server got a reply"),
+            };
+            // The raw credential bytes follow the 24 byte header
+            creds.push((call.cred, request[24..24 + 44].to_vec()));
+            let mut reply = encode(&RpcMsg::accepted(msg.xid, ReplyData::Success));
+            reply.extend(words(&[0]));
+            writer.write_record(&reply).unwrap();
+        }
+        creds
+    });
+
+    let mut client = RpcClient::connect_tcp(("127.0.0.1", port)).unwrap();
+    client.set_auth(&captured_cred()).unwrap();
+    client.call::<_, u32>(0x2000_0001, 1, 1, &()).unwrap();
+    client.call::<_, u32>(0x2000_0001, 1, 2, &()).unwrap();
+    drop(client);
+
+    let creds = server.join().unwrap();
+    assert_eq!(creds.len(), 2);
+    for (cred, raw) in creds {
+        assert_eq!(Auth::from_opaque(&cred).unwrap(), captured_cred());
+        assert_eq!(raw[..], CAPTURED_AUTH[..44]);
+    }
+
+    // CallBody::with_cred sets the same thing for hand-built calls
+    let call = CallBody::new(1, 1, 1).with_cred(&captured_cred()).unwrap();
+    assert_eq!(encode(&call.cred), CAPTURED_AUTH[..44]);
+}
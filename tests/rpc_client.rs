@@ -0,0 +1,154 @@
+use serde_xdr::record::{RecordReader, RecordWriter};
+use serde_xdr::rpc::{MismatchInfo, MsgBody, ReplyData, RpcClient, RpcMsg};
+use serde_xdr::{to_bytes, Deserializer, RpcError};
+
+use serde::Deserialize;
+use std::io::Write;
+use std::net::{TcpListener, UdpSocket};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const PROG: u32 = 0x2000_0001;
+
+// Answers procedure 1 with its argument plus one, and anything else with PROC_UNAVAIL
+fn answer(request: &[u8]) -> Vec<u8> {
+    let mut de = Deserializer::new(request);
+    let msg = RpcMsg::deserialize(&mut de).unwrap();
+    let call = match msg.body {
+        MsgBody::Call(call) => call,
+        MsgBody::Reply(_) => panic!("server got a reply"),
+    };
+    let mut reply = Vec::new();
+    match (call.prog, call.vers, call.proc) {
+        (PROG, 1, 1) => {
+            let arg = u32::deserialize(&mut de).unwrap();
+            to_bytes(&RpcMsg::accepted(msg.xid, ReplyData::Success), &mut reply).unwrap();
+            to_bytes(&(arg + 1), &mut reply).unwrap();
+        }
+        (PROG, 1, _) => to_bytes(
+            &RpcMsg::accepted(msg.xid, ReplyData::ProcUnavail),
+            &mut reply,
+        )
+        .unwrap(),
+        (PROG, _, _) => {
+            let mismatch = ReplyData::ProgMismatch(MismatchInfo { low: 1, high: 1 });
+            to_bytes(&RpcMsg::accepted(msg.xid, mismatch), &mut reply).unwrap()
+        }
+        _ => to_bytes(
+            &RpcMsg::accepted(msg.xid, ReplyData::ProgUnavail),
+            &mut reply,
+        )
+        .unwrap(),
+    }
+    reply
+}
+
+fn tcp_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = RecordReader::new(stream.try_clone().unwrap());
+        let mut writer = RecordWriter::new(stream).max_fragment_size(8);
+        while let Some(request) = reader.read_record().unwrap() {
+            let reply = answer(request);
+            writer.write_record(&reply).unwrap();
+        }
+    });
+    port
+}
+
+// Drops the first `drop` datagrams to exercise retransmission
+fn udp_server(drop: usize) -> u16 {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut buf = [0; 65535];
+        let mut seen = 0;
+        loop {
+            let (len, peer) = socket.recv_from(&mut buf).unwrap();
+            seen += 1;
+            if seen > drop {
+                socket.send_to(&answer(&buf[..len]), peer).unwrap();
+            }
+        }
+    });
+    port
+}
+
+#[test]
+fn tcp_call_returns_results() {
+    let mut client = RpcClient::connect_tcp(("127.0.0.1", tcp_server())).unwrap();
+    let res: u32 = client.call(PROG, 1, 1, &41u32).unwrap();
+    assert_eq!(res, 42);
+    let res: u32 = client.call(PROG, 1, 1, &1u32).unwrap();
+    assert_eq!(res, 2);
+}
+
+#[test]
+fn tcp_call_maps_accept_errors() {
+    let mut client = RpcClient::connect_tcp(("127.0.0.1", tcp_server())).unwrap();
+    let err = client.call::<_, u32>(PROG, 1, 9, &0u32).unwrap_err();
+    assert!(matches!(err, RpcError::ProcUnavail));
+    let err = client.call::<_, u32>(PROG, 3, 1, &0u32).unwrap_err();
+    assert!(matches!(err, RpcError::ProgMismatch { low: 1, high: 1 }));
+    let err = client.call::<_, u32>(PROG + 1, 1, 1, &0u32).unwrap_err();
+    assert!(matches!(err, RpcError::ProgUnavail));
+}
+
+#[test]
+fn tcp_call_times_out_partway_through_a_reply() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (resume, resumed) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = RecordReader::new(stream.try_clone().unwrap());
+        // Half of the first reply, then the rest once the client has given up on it
+        let mut framed = RecordWriter::new(Vec::new());
+        framed
+            .write_record(&answer(reader.read_record().unwrap().unwrap()))
+            .unwrap();
+        let framed = framed.into_inner();
+        stream.write_all(&framed[..10]).unwrap();
+        resumed.recv().unwrap();
+        stream.write_all(&framed[10..]).unwrap();
+
+        let mut writer = RecordWriter::new(stream);
+        while let Some(request) = reader.read_record().unwrap() {
+            writer.write_record(&answer(request)).unwrap();
+        }
+    });
+
+    let mut client = RpcClient::connect_tcp(("127.0.0.1", port)).unwrap();
+    let err = client
+        .call_timeout::<_, u32>(PROG, 1, 1, &1u32, Duration::from_millis(100))
+        .unwrap_err();
+    assert!(matches!(err, RpcError::Timeout));
+    resume.send(()).unwrap();
+
+    // The late reply is read past as a whole record, and the connection stays usable
+    let res: u32 = client.call(PROG, 1, 1, &41u32).unwrap();
+    assert_eq!(res, 42);
+}
+
+#[test]
+fn udp_call_retransmits() {
+    let mut client = RpcClient::connect_udp(("127.0.0.1", udp_server(2)))
+        .unwrap()
+        .retransmit(Duration::from_millis(20));
+    let res: u32 = client.call(PROG, 1, 1, &7u32).unwrap();
+    assert_eq!(res, 8);
+}
+
+#[test]
+fn udp_call_times_out() {
+    let mut client = RpcClient::connect_udp(("127.0.0.1", udp_server(usize::MAX)))
+        .unwrap()
+        .retransmit(Duration::from_millis(10));
+    let err = client
+        .call_timeout::<_, u32>(PROG, 1, 1, &7u32, Duration::from_millis(100))
+        .unwrap_err();
+    assert!(matches!(err, RpcError::Timeout));
+}
@@ -0,0 +1,125 @@
+use serde_xdr::rpc::{Call, ProcError, RpcClient, RpcProgram, RpcServer};
+use serde_xdr::RpcError;
+
+use std::time::Duration;
+
+const PROG: u32 = 0x2000_0002;
+
+struct Adder;
+
+impl RpcProgram for Adder {
+    fn program(&self) -> u32 {
+        PROG
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (1, 2)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        match call.proc() {
+            0 => Ok(()),
+            1 => {
+                let (a, b): (u32, u32) = (call.args()?, call.args()?);
+                call.reply(&(a + b))
+            }
+            9 => panic!("procedure 9 always panics"),
+            _ => Err(ProcError::ProcUnavail),
+        }
+    }
+}
+
+fn server() -> RpcServer {
+    let mut server = RpcServer::new();
+    server.register(Adder);
+    server
+}
+
+#[derive(serde::Serialize)]
+struct Operands {
+    a: u32,
+    b: u32,
+}
+
+#[test]
+fn dispatches_over_tcp_and_udp() {
+    let tcp = server().spawn_tcp("127.0.0.1:0").unwrap();
+    let udp = server().spawn_udp("127.0.0.1:0").unwrap();
+
+    let mut client = RpcClient::connect_tcp(tcp.local_addr()).unwrap();
+    let (): () = client.call(PROG, 1, 0, &()).unwrap();
+    let sum: u32 = client.call(PROG, 2, 1, &Operands { a: 1, b: 2 }).unwrap();
+    assert_eq!(sum, 3);
+
+    let mut client = RpcClient::connect_udp(udp.local_addr()).unwrap();
+    let sum: u32 = client.call(PROG, 1, 1, &Operands { a: 3, b: 4 }).unwrap();
+    assert_eq!(sum, 7);
+
+    tcp.shutdown();
+    udp.shutdown();
+}
+
+#[test]
+fn answers_errors_on_behalf_of_programs() {
+    let handle = server().spawn_tcp("127.0.0.1:0").unwrap();
+    let mut client = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+
+    let err = client.call::<_, ()>(PROG + 1, 1, 0, &()).unwrap_err();
+    assert!(matches!(err, RpcError::ProgUnavail));
+    let err = client.call::<_, ()>(PROG, 3, 0, &()).unwrap_err();
+    assert!(matches!(err, RpcError::ProgMismatch { low: 1, high: 2 }));
+    let err = client.call::<_, ()>(PROG, 1, 7, &()).unwrap_err();
+    assert!(matches!(err, RpcError::ProcUnavail));
+    let err = client.call::<_, u32>(PROG, 1, 1, &1u32).unwrap_err();
+    assert!(matches!(err, RpcError::GarbageArgs));
+}
+
+#[test]
+fn a_panicking_program_closes_only_its_connection() {
+    let handle = server().spawn_tcp("127.0.0.1:0").unwrap();
+    let mut client = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+
+    // The connection is closed rather than left hanging until the call times out
+    let err = client
+        .call_timeout::<_, ()>(PROG, 1, 9, &(), Duration::from_secs(10))
+        .unwrap_err();
+    assert!(!matches!(err, RpcError::Timeout), "{}", err);
+
+    let mut client = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+    let sum: u32 = client.call(PROG, 1, 1, &Operands { a: 5, b: 6 }).unwrap();
+    assert_eq!(sum, 11);
+    handle.shutdown();
+}
+
+#[test]
+fn connections_past_the_limit_are_closed() {
+    let handle = server()
+        .max_connections(1)
+        .spawn_tcp("127.0.0.1:0")
+        .unwrap();
+    let mut first = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+    let (): () = first.call(PROG, 1, 0, &()).unwrap();
+
+    let mut second = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+    let err = second
+        .call_timeout::<_, ()>(PROG, 1, 0, &(), Duration::from_secs(10))
+        .unwrap_err();
+    assert!(!matches!(err, RpcError::Timeout), "{}", err);
+
+    // Closing the first makes room, once its thread has noticed
+    drop(first);
+    let mut served = false;
+    for _ in 0..50 {
+        let mut client = RpcClient::connect_tcp(handle.local_addr()).unwrap();
+        if client
+            .call_timeout::<_, ()>(PROG, 1, 0, &(), Duration::from_secs(10))
+            .is_ok()
+        {
+            served = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(served);
+    handle.shutdown();
+}
@@ -0,0 +1,47 @@
+use serde_xdr::rpcbind::{from_uaddr, to_uaddr, Rpcb, RpcbList};
+use serde_xdr::{from_bytes, to_bytes};
+use std::net::SocketAddr;
+
+#[test]
+fn universal_addresses_round_trip() {
+    let v4: SocketAddr = "127.0.0.1:111".parse().unwrap();
+    assert_eq!(to_uaddr(&v4), "127.0.0.1.0.111");
+    assert_eq!(from_uaddr("127.0.0.1.0.111"), Some(v4));
+
+    let v6: SocketAddr = "[fe80::1]:2049".parse().unwrap();
+    assert_eq!(to_uaddr(&v6), "fe80::1.8.1");
+    assert_eq!(from_uaddr("fe80::1.8.1"), Some(v6));
+    assert_eq!(from_uaddr("::.0.111"), Some("[::]:111".parse().unwrap()));
+
+    assert_eq!(from_uaddr("127.0.0.1.0.256"), None);
+    assert_eq!(from_uaddr("localhost.0.111"), None);
+    assert_eq!(from_uaddr("111"), None);
+}
+
+#[test]
+fn rpcb_list_encoding() {
+    let rpcb = Rpcb {
+        r_prog: 100000,
+        r_vers: 4,
+        r_netid: String::from("tcp"),
+        r_addr: String::from("0.0.0.0.0.111"),
+        r_owner: String::from("superuser"),
+    };
+    let mut buf = Vec::new();
+    to_bytes(&RpcbList::from(vec![rpcb.clone()]), &mut buf).unwrap();
+    #[rustfmt::skip]
+    let expected = vec![
+        0, 0, 0, 1,
+        0, 1, 0x86, 0xa0,
+        0, 0, 0, 4,
+        0, 0, 0, 3, b't', b'c', b'p', 0,
+        0, 0, 0, 13, b'0', b'.', b'0', b'.', b'0', b'.', b'0', b'.', b'0', b'.', b'1', b'1', b'1', 0, 0, 0,
+        0, 0, 0, 9, b's', b'u', b'p', b'e', b'r', b'u', b's', b'e', b'r', 0, 0, 0,
+        0, 0, 0, 0,
+    ];
+    assert_eq!(buf, expected);
+
+    let (list, consumed): (RpcbList, usize) = from_bytes(&buf).unwrap();
+    assert_eq!(consumed, buf.len());
+    assert_eq!(list.0, vec![rpcb]);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{encode, words};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use serde_xdr::{from_bytes, Deserializer, Opaque};
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Header {
+    name: String,
+    blob: Opaque,
+    counts: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Loose {
+    _first: u32,
+    _rest: IgnoredAny,
+}
+
+#[test]
+fn skip_passes_over_a_known_type() {
+    let header = Header {
+        name: String::from("inst0"),
+        blob: Opaque(vec![1, 2]),
+        counts: vec![3, 4],
+    };
+    let mut bytes = encode(&header);
+    bytes.extend(words(&[9]));
+
+    let mut de = Deserializer::new(&bytes[..]);
+    de.skip::<Header>().unwrap();
+    assert_eq!(de.get_bytes_consumed(), 32);
+    assert_eq!(u32::deserialize(&mut de).unwrap(), 9);
+}
+
+#[test]
+fn ignored_any_is_an_error() {
+    let bytes = words(&[1, 2, 3]);
+    let err = from_bytes::<Loose>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("Deserializer::skip"), "{}", err);
+    assert!(from_bytes::<IgnoredAny>(&bytes).is_err());
+}
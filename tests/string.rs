@@ -0,0 +1,50 @@
+mod common;
+
+use common::round_trip;
+use serde::Deserialize;
+use serde_xdr::{from_bytes, Deserializer, Opaque};
+
+#[test]
+fn strings_pad_to_a_multiple_of_four() {
+    assert_eq!(round_trip(&String::new()), [0, 0, 0, 0]);
+    assert_eq!(round_trip(&String::from("a")), [0, 0, 0, 1, b'a', 0, 0, 0]);
+    assert_eq!(
+        round_trip(&String::from("abc")),
+        [0, 0, 0, 3, b'a', b'b', b'c', 0]
+    );
+    // A length that's already a multiple of four takes no padding at all
+    assert_eq!(
+        round_trip(&String::from("abcd")),
+        [0, 0, 0, 4, b'a', b'b', b'c', b'd']
+    );
+    assert_eq!(
+        round_trip(&String::from("abcde")),
+        [0, 0, 0, 5, b'a', b'b', b'c', b'd', b'e', 0, 0, 0]
+    );
+    assert_eq!(
+        round_trip(&Opaque(vec![1, 2, 3, 4])),
+        [0, 0, 0, 4, 1, 2, 3, 4]
+    );
+}
+
+#[test]
+fn strings_are_utf8_counted_in_bytes() {
+    assert_eq!(
+        round_trip(&String::from("µs")),
+        [0, 0, 0, 3, 0xC2, 0xB5, b's', 0]
+    );
+    let err = from_bytes::<String>(&[0, 0, 0, 1, 0xFF, 0, 0, 0]).unwrap_err();
+    assert!(err.to_string().contains("utf-8"), "{}", err);
+}
+
+#[test]
+fn decoding_consumes_the_padding() {
+    let bytes = [0, 0, 0, 2, b'h', b'i', 0, 0, 0, 0, 0, 7];
+    let mut de = Deserializer::new(&bytes[..]);
+    assert_eq!(String::deserialize(&mut de).unwrap(), "hi");
+    assert_eq!(de.get_bytes_consumed(), 8);
+    assert_eq!(u32::deserialize(&mut de).unwrap(), 7);
+
+    // Padding that's missing off the end is a truncated message
+    assert!(from_bytes::<String>(&[0, 0, 0, 2, b'h', b'i']).is_err());
+}
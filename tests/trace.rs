@@ -0,0 +1,108 @@
+mod common;
+
+use common::encode;
+use serde::{Deserialize, Serialize};
+use serde_xdr::trace::{trace_from_bytes, TraceEntry};
+use serde_xdr::vxi11::{CreateLinkParms, DeviceReadResp};
+use serde_xdr::{Deserializer, FixedOpaque};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Reading {
+    id: FixedOpaque<3>,
+    level: Option<i64>,
+    tags: Vec<String>,
+    counts: BTreeMap<u32, bool>,
+}
+
+#[test]
+fn records_every_primitive() {
+    let parms = CreateLinkParms {
+        client_id: 7,
+        lock_device: true,
+        lock_timeout: 0x10,
+        device: String::from("inst0"),
+    };
+    let bytes = encode(&parms);
+    let mut de = Deserializer::new(&bytes[..]).trace(true);
+    CreateLinkParms::deserialize(&mut de).unwrap();
+
+    let trace = de.get_trace().unwrap();
+    let entry = |offset, bytes: &[u8], type_name, path: &str| TraceEntry {
+        offset,
+        len: bytes.len(),
+        bytes: bytes.to_vec(),
+        type_name,
+        path: String::from(path),
+    };
+    assert_eq!(
+        trace.entries(),
+        &[
+            entry(0, &[0, 0, 0, 7], "i32", "client_id"),
+            entry(4, &[0, 0, 0, 1], "bool", "lock_device"),
+            entry(8, &[0, 0, 0, 0x10], "u32", "lock_timeout"),
+            entry(12, &[0, 0, 0, 5], "length", "device"),
+            entry(16, b"inst0", "string", "device"),
+            entry(21, &[0, 0, 0], "padding", "device"),
+        ][..]
+    );
+    let end = trace.entries().last().map(|e| e.offset + e.len);
+    assert_eq!(end, Some(de.get_bytes_consumed()));
+}
+
+#[test]
+fn annotated_dump() {
+    let mut counts = BTreeMap::new();
+    counts.insert(9, false);
+    let reading = Reading {
+        id: FixedOpaque([0xa, 0xb, 0xc]),
+        level: Some(-2),
+        tags: vec![String::from("hot")],
+        counts,
+    };
+    let bytes = encode(&reading);
+    let (res, trace) = trace_from_bytes::<Reading>(&bytes);
+    assert_eq!(res.unwrap().1, bytes.len());
+    assert_eq!(
+        trace.to_string(),
+        "\
+00000000  0a           id[0]: u8
+00000001  0b           id[1]: u8
+00000002  0c           id[2]: u8
+00000003  00           id[3]: u8
+00000004  00 00 00 01  level: option
+00000008  ff ff ff ff  level: i64
+0000000c  ff ff ff fe  level: i64 (cont.)
+00000010  00 00 00 01  tags: length
+00000014  00 00 00 03  tags[0]: length
+00000018  68 6f 74     tags[0]: string
+0000001b  00           tags[0]: padding
+0000001c  00 00 00 01  counts: length
+00000020  00 00 00 09  counts[0].key: u32
+00000024  00 00 00 00  counts[0].value: bool
+"
+    );
+}
+
+#[test]
+fn shows_where_decoding_stopped() {
+    let resp = DeviceReadResp {
+        data: vec![1, 2, 3, 4, 5].into(),
+        ..Default::default()
+    };
+    let bytes = encode(&resp);
+    let (res, trace) = trace_from_bytes::<DeviceReadResp>(&bytes[..14]);
+    assert!(res.is_err());
+    assert_eq!(trace.incomplete(), &[1, 2]);
+    assert!(trace.to_string().ends_with(
+        "00000008  00 00 00 05  data: length\n0000000c  01 02        data: incomplete\n"
+    ));
+
+    // Skipped payloads are still accounted for
+    let mut de = Deserializer::new(&bytes[..]).trace(true);
+    de.skip::<DeviceReadResp>().unwrap();
+    let trace = de.take_trace().unwrap();
+    let last = trace.entries().last().unwrap();
+    assert_eq!((last.offset, last.len, last.type_name), (12, 8, "skipped"));
+    assert!(de.get_trace().is_none());
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::{round_trip, words};
+use serde::{Deserialize, Serialize};
+use serde_xdr::from_bytes;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Point(i32, i32, u32);
+
+#[test]
+fn tuples_have_no_length_prefix() {
+    assert_eq!(round_trip(&(1u32, 2u32)), words(&[1, 2]));
+    assert_eq!(
+        round_trip(&(7u32, true, -1i32)),
+        words(&[7, 1, 0xFFFF_FFFF])
+    );
+    // Arrays are tuples to serde, so they're XDR fixed-length arrays
+    assert_eq!(round_trip(&[5u32, 6, 7]), words(&[5, 6, 7]));
+    // Unlike variable-length arrays
+    assert_eq!(round_trip(&vec![5u32, 6, 7]), words(&[3, 5, 6, 7]));
+}
+
+#[test]
+fn tuple_structs_match_tuples() {
+    let point = Point(-1, 2, 3);
+    assert_eq!(round_trip(&point), round_trip(&(-1i32, 2i32, 3u32)));
+    assert_eq!(round_trip(&point), words(&[0xFFFF_FFFF, 2, 3]));
+}
+
+#[test]
+fn tuples_nest() {
+    let value = ((1u32, 2u32), [3u32, 4], vec![(5u32, 6u32)]);
+    assert_eq!(round_trip(&value), words(&[1, 2, 3, 4, 1, 5, 6]));
+}
+
+#[test]
+fn short_tuples_are_errors() {
+    assert!(from_bytes::<(u32, u32)>(&words(&[1])).is_err());
+}
+
+#[test]
+fn floats_are_ieee_big_endian() {
+    assert_eq!(round_trip(&1.5f32), [0x3F, 0xC0, 0x00, 0x00]);
+    assert_eq!(round_trip(&-2.0f32), [0xC0, 0x00, 0x00, 0x00]);
+    assert_eq!(round_trip(&f32::INFINITY), [0x7F, 0x80, 0x00, 0x00]);
+    assert_eq!(
+        round_trip(&1.0f64),
+        [0x3F, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+    assert_eq!(
+        round_trip(&-0.1f64),
+        [0xBF, 0xB9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A]
+    );
+    assert_eq!(
+        round_trip(&f64::NEG_INFINITY),
+        [0xFF, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn nan_bits_are_kept() {
+    let nan = f64::from_bits(0x7FF8_0000_0000_0001);
+    let bytes = common::encode(&nan);
+    assert_eq!(bytes, 0x7FF8_0000_0000_0001u64.to_be_bytes());
+    let (decoded, _): (f64, usize) = from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.to_bits(), nan.to_bits());
+}
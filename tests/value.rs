@@ -0,0 +1,349 @@
+mod common;
+
+use common::{encode, words};
+use serde_xdr::portmap::{Mapping, PmapList};
+use serde_xdr::value::{
+    decode_with_schema, encode_with_schema, trace_with_schema, XdrSchema, XdrType, XdrValue,
+    MAX_DEPTH,
+};
+use serde_xdr::vxi11::{DeviceErrorCode, DeviceReadResp, ReadReason};
+use serde_xdr::Opaque;
+
+const SPEC: &str = r#"
+const MAXNAME = 8;
+enum color { RED = 1, GREEN = 2, BLUE = 16 };
+typedef string name_t<MAXNAME>;
+
+struct node {
+    name_t name;
+    node *next;
+};
+
+union reading switch (color c) {
+case RED:
+    int level;
+case GREEN:
+    void;
+default:
+    opaque raw[3];
+};
+
+struct sample {
+    unsigned hyper id;
+    bool valid;
+    reading readings<2>;
+    double scale[2];
+};
+"#;
+
+fn string(s: &str) -> XdrValue {
+    XdrValue::String(s.to_string())
+}
+
+#[test]
+fn decodes_what_typed_code_encodes() {
+    let resp = DeviceReadResp {
+        error: DeviceErrorCode::NO_ERROR,
+        reason: ReadReason::END,
+        data: Opaque(b"1.5\n".to_vec()),
+    };
+    let buf = encode(&resp);
+    let schema = XdrSchema::from_idl(
+        include_str!("../codegen-tests/proto/vxi11.x"),
+        "Device_ReadResp",
+    )
+    .unwrap();
+    let (value, consumed) = decode_with_schema(&schema, &buf).unwrap();
+    assert_eq!(consumed, buf.len());
+    assert_eq!(
+        value,
+        XdrValue::Struct {
+            fields: vec![
+                (String::from("error"), XdrValue::Int(0)),
+                (String::from("reason"), XdrValue::Int(4)),
+                (String::from("data"), XdrValue::Opaque(b"1.5\n".to_vec())),
+            ]
+        }
+    );
+}
+
+#[test]
+fn walks_every_kind_of_type() {
+    let schema = XdrSchema::from_idl(SPEC, "node").unwrap();
+    let mut buf = words(&[1]);
+    buf.extend(b"a\0\0\0");
+    buf.extend(words(&[1, 2]));
+    buf.extend(b"bc\0\0");
+    buf.extend(words(&[0]));
+    let (list, consumed) = decode_with_schema(&schema, &buf).unwrap();
+    assert_eq!(consumed, buf.len());
+    assert_eq!(list.field("name"), Some(&string("a")));
+    let next = match list.field("next") {
+        Some(XdrValue::Optional(Some(next))) => next,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(next.field("name"), Some(&string("bc")));
+    assert_eq!(next.field("next"), Some(&XdrValue::Optional(None)));
+
+    let schema = XdrSchema::from_idl(SPEC, "sample").unwrap();
+    let mut buf = words(&[0xFFFF_FFFF, 0xFFFF_FFFE, 1, 2, 1, 0xFFFF_FFFB, 16]);
+    buf.extend(&[9, 8, 7, 0]);
+    buf.extend(&1.5f64.to_be_bytes());
+    buf.extend(&(-2f64).to_be_bytes());
+    let (sample, consumed) = decode_with_schema(&schema, &buf).unwrap();
+    assert_eq!(consumed, buf.len());
+    assert_eq!(sample.field("id"), Some(&XdrValue::UHyper(u64::MAX - 1)));
+    assert_eq!(sample.field("valid"), Some(&XdrValue::Bool(true)));
+    let red = XdrValue::Enum {
+        name: String::from("RED"),
+        value: 1,
+    };
+    assert_eq!(
+        sample.field("readings"),
+        Some(&XdrValue::Array(vec![
+            XdrValue::Union {
+                disc: Box::new(red),
+                arm: Box::new(XdrValue::Int(-5)),
+            },
+            // BLUE has no case of its own and takes the default arm
+            XdrValue::Union {
+                disc: Box::new(XdrValue::Enum {
+                    name: String::from("BLUE"),
+                    value: 16,
+                }),
+                arm: Box::new(XdrValue::Opaque(vec![9, 8, 7])),
+            },
+        ]))
+    );
+    assert_eq!(
+        sample.field("scale"),
+        Some(&XdrValue::Array(vec![
+            XdrValue::Double(1.5),
+            XdrValue::Double(-2.0)
+        ]))
+    );
+}
+
+#[test]
+fn hand_built_schemas() {
+    let schema = XdrSchema::new(XdrType::Named(String::from("pair"))).define(
+        "pair",
+        XdrType::Struct(vec![
+            (String::from("a"), XdrType::Int),
+            (
+                String::from("b"),
+                XdrType::Array(Box::new(XdrType::UInt), None),
+            ),
+        ]),
+    );
+    let (value, _) = decode_with_schema(&schema, &words(&[0xFFFF_FFFF, 1, 3])).unwrap();
+    assert_eq!(
+        value,
+        XdrValue::Struct {
+            fields: vec![
+                (String::from("a"), XdrValue::Int(-1)),
+                (String::from("b"), XdrValue::Array(vec![XdrValue::UInt(3)])),
+            ]
+        }
+    );
+
+    let schema = XdrSchema::new(XdrType::Named(String::from("missing")));
+    assert!(decode_with_schema(&schema, &[]).is_err());
+    let looping = XdrSchema::new(XdrType::Named(String::from("a")))
+        .define("a", XdrType::Named(String::from("b")))
+        .define("b", XdrType::Named(String::from("a")));
+    assert!(decode_with_schema(&looping, &[]).is_err());
+
+    // Encoding and decoding turn down a union that switches on a hyper the same way
+    let hyper_switch = XdrSchema::new(XdrType::Union {
+        disc: Box::new(XdrType::Hyper),
+        arms: vec![(0, XdrType::Void)],
+        default: None,
+    });
+    let expected = "a union discriminant has to be an int, unsigned int, bool or enum";
+    let err = decode_with_schema(&hyper_switch, &words(&[0, 0])).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+    let value = XdrValue::Union {
+        disc: Box::new(XdrValue::Hyper(0)),
+        arm: Box::new(XdrValue::Void),
+    };
+    let err = encode_with_schema(&hyper_switch, &value).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+}
+
+#[test]
+fn rejects_what_the_schema_rules_out() {
+    let name = XdrSchema::from_idl(SPEC, "name_t").unwrap();
+    let mut buf = words(&[9]);
+    buf.extend(b"123456789\0\0\0");
+    assert!(decode_with_schema(&name, &buf).is_err());
+
+    let color = XdrSchema::from_idl(SPEC, "color").unwrap();
+    assert!(decode_with_schema(&color, &words(&[3])).is_err());
+
+    let reading = XdrSchema::from_idl(SPEC, "reading").unwrap();
+    assert!(decode_with_schema(&reading, &words(&[1])).is_err());
+
+    let err = XdrSchema::from_idl(SPEC, "nothing").unwrap_err();
+    assert_eq!(err.message, "unknown type `nothing`");
+    let err = XdrSchema::from_idl("struct s { t x; };", "s").unwrap_err();
+    assert_eq!(err.message, "unknown type `t`");
+    let err = XdrSchema::from_idl("union u switch (bool b) { case 2: void; };", "u").unwrap_err();
+    assert_eq!(err.message, "`2` isn't a valid case here");
+}
+
+#[test]
+fn encodes_back_to_the_same_bytes() {
+    let schema = XdrSchema::from_idl(SPEC, "sample").unwrap();
+    let mut buf = words(&[0, 7, 0, 2, 2, 16]);
+    buf.extend(&[1, 2, 3, 0]);
+    buf.extend(&0.5f64.to_be_bytes());
+    buf.extend(&f64::MAX.to_be_bytes());
+    let (value, _) = decode_with_schema(&schema, &buf).unwrap();
+    assert_eq!(encode_with_schema(&schema, &value).unwrap(), buf);
+
+    let resp = DeviceReadResp {
+        error: DeviceErrorCode::IO_TIMEOUT,
+        reason: ReadReason::CHR,
+        data: Opaque(vec![1, 2, 3, 4, 5]),
+    };
+    let schema = XdrSchema::from_idl(
+        include_str!("../codegen-tests/proto/vxi11.x"),
+        "Device_ReadResp",
+    )
+    .unwrap();
+    let (value, _) = decode_with_schema(&schema, &encode(&resp)).unwrap();
+    assert_eq!(encode_with_schema(&schema, &value).unwrap(), encode(&resp));
+}
+
+#[test]
+fn encoding_checks_values_against_the_schema() {
+    let schema = XdrSchema::from_idl(SPEC, "node").unwrap();
+    let node = |name: &str| XdrValue::Struct {
+        fields: vec![
+            (String::from("name"), string(name)),
+            (String::from("next"), XdrValue::Optional(None)),
+        ],
+    };
+    assert!(encode_with_schema(&schema, &node("12345678")).is_ok());
+    assert!(encode_with_schema(&schema, &node("123456789")).is_err());
+    assert!(encode_with_schema(&schema, &XdrValue::Int(1)).is_err());
+
+    let misnamed = XdrValue::Struct {
+        fields: vec![
+            (String::from("next"), XdrValue::Optional(None)),
+            (String::from("name"), string("a")),
+        ],
+    };
+    let err = encode_with_schema(&schema, &misnamed).unwrap_err();
+    assert_eq!(err.to_string(), "expected field `name`, found `next`");
+
+    let reading = XdrSchema::from_idl(SPEC, "reading").unwrap();
+    let green = XdrValue::Union {
+        disc: Box::new(XdrValue::Enum {
+            name: String::from("GREEN"),
+            value: 2,
+        }),
+        arm: Box::new(XdrValue::Void),
+    };
+    assert_eq!(encode_with_schema(&reading, &green).unwrap(), words(&[2]));
+    let wrong_arm = XdrValue::Union {
+        disc: Box::new(XdrValue::Enum {
+            name: String::from("GREEN"),
+            value: 2,
+        }),
+        arm: Box::new(XdrValue::Int(1)),
+    };
+    let err = encode_with_schema(&reading, &wrong_arm).unwrap_err();
+    assert_eq!(err.to_string(), "expected void, found an int");
+}
+
+#[test]
+fn trace_labels_every_word() {
+    let schema = XdrSchema::from_idl(SPEC, "node").unwrap();
+    let mut bytes = words(&[1, 0x6100_0000, 1, 2, 0x6263_0000, 0]);
+    bytes.extend_from_slice(&[0xde, 0xad]);
+    let (res, trace) = trace_with_schema(&schema, &bytes);
+    assert_eq!(res.unwrap().1, 24);
+    assert_eq!(
+        trace.dump(&bytes),
+        "\
+00000000  00 00 00 01  name: length
+00000004  61           name: string
+00000005  00 00 00     name: padding
+00000008  00 00 00 01  next: option
+0000000c  00 00 00 02  next.name: length
+00000010  62 63        next.name: string
+00000012  00 00        next.name: padding
+00000014  00 00 00 00  next.next: option
+00000018  de ad        (trailing)
+"
+    );
+
+    let (res, trace) = trace_with_schema(&schema, &bytes[..17]);
+    assert!(res.is_err());
+    assert_eq!(
+        trace.dump(&bytes[..17]),
+        "\
+00000000  00 00 00 01  name: length
+00000004  61           name: string
+00000005  00 00 00     name: padding
+00000008  00 00 00 01  next: option
+0000000c  00 00 00 02  next.name: length
+00000010  62           next.name: incomplete
+"
+    );
+
+    let schema = XdrSchema::from_idl(SPEC, "sample").unwrap();
+    let bytes = words(&[0, 7, 1, 1, 16, 0x0102_0300, 0, 0, 0, 0]);
+    let (res, trace) = trace_with_schema(&schema, &bytes);
+    assert_eq!(res.unwrap().1, bytes.len());
+    assert_eq!(
+        trace.to_string(),
+        "\
+00000000  00 00 00 00  id: u64
+00000004  00 00 00 07  id: u64 (cont.)
+00000008  00 00 00 01  valid: bool
+0000000c  00 00 00 01  readings: length
+00000010  00 00 00 10  readings[0]: i32
+00000014  01 02 03     readings[0]: bytes
+00000017  00           readings[0]: padding
+00000018  00 00 00 00  scale[0]: f64
+0000001c  00 00 00 00  scale[0]: f64 (cont.)
+00000020  00 00 00 00  scale[1]: f64
+00000024  00 00 00 00  scale[1]: f64 (cont.)
+"
+    );
+}
+
+#[test]
+fn deeply_nested_values_are_an_error() {
+    let schema = XdrSchema::from_idl(
+        "struct mapping { unsigned prog; unsigned vers; unsigned prot; unsigned port; };\n\
+         struct pmaplist { mapping map; pmaplist *next; };\n\
+         typedef pmaplist *pmaplist_ptr;",
+        "pmaplist_ptr",
+    )
+    .unwrap();
+    let list = |len: u32| {
+        encode(&PmapList::from(
+            (0..len)
+                .map(|port| Mapping {
+                    prog: 100_000,
+                    vers: 2,
+                    prot: 6,
+                    port,
+                })
+                .collect::<Vec<_>>(),
+        ))
+    };
+
+    // Every entry is an optional and a struct, so two levels
+    let short = list(MAX_DEPTH as u32 / 2 - 1);
+    let (value, consumed) = decode_with_schema(&schema, &short).unwrap();
+    assert_eq!(consumed, short.len());
+    assert!(matches!(value, XdrValue::Optional(Some(_))));
+
+    let err = decode_with_schema(&schema, &list(200_000)).unwrap_err();
+    assert!(err.to_string().contains("levels deep"), "{}", err);
+}
@@ -0,0 +1,149 @@
+use serde_json::json;
+use serde_xdr::value::{
+    decode_with_schema, encode_with_schema, from_json, to_json, XdrSchema, XdrType, XdrValue,
+};
+
+const SPEC: &str = r#"
+enum kind { SHORT = 1, LONG = 2 };
+
+union payload switch (kind k) {
+case SHORT:
+    int small;
+case LONG:
+    unsigned hyper big;
+};
+
+struct message {
+    hyper offset;
+    float gain;
+    opaque tag[3];
+    opaque body<>;
+    payload data;
+    payload *extra;
+    bool flags[2];
+    quadruple exact;
+};
+"#;
+
+fn schema() -> XdrSchema {
+    XdrSchema::from_idl(SPEC, "message").unwrap()
+}
+
+#[test]
+fn json_round_trip() {
+    let json = json!({
+        "offset": "-9007199254740993",
+        "gain": 0.1,
+        "tag": "a1b2c3",
+        "body": "",
+        "data": { "disc": "LONG", "arm": "18446744073709551615" },
+        "extra": null,
+        "flags": [true, false],
+        "exact": "3fff0000000000000000000000000000",
+    });
+    let value = from_json(&schema(), &json).unwrap();
+    assert_eq!(to_json(&value), json);
+
+    let bytes = encode_with_schema(&schema(), &value).unwrap();
+    let (decoded, consumed) = decode_with_schema(&schema(), &bytes).unwrap();
+    assert_eq!(consumed, bytes.len());
+    assert_eq!(decoded, value);
+    // Hypers keep every bit, which a JSON number wouldn't
+    assert_eq!(&bytes[..8], &(-9_007_199_254_740_993i64).to_be_bytes());
+    assert_eq!(&bytes[20..24], &[0, 0, 0, 2]);
+    assert_eq!(&bytes[24..32], &[0xFF; 8]);
+}
+
+#[test]
+fn lenient_input() {
+    let json = json!({
+        "offset": 5,
+        "gain": "-inf",
+        "tag": { "base64": "AQID" },
+        "body": { "base64": "aGk=" },
+        "data": { "disc": 1, "arm": "-7" },
+        "extra": { "disc": "SHORT", "arm": 2 },
+        "flags": [false, false],
+        "exact": "00000000000000000000000000000000",
+    });
+    let value = from_json(&schema(), &json).unwrap();
+    assert_eq!(value.field("offset"), Some(&XdrValue::Hyper(5)));
+    assert_eq!(
+        value.field("gain"),
+        Some(&XdrValue::Float(f32::NEG_INFINITY))
+    );
+    assert_eq!(value.field("tag"), Some(&XdrValue::Opaque(vec![1, 2, 3])));
+    assert_eq!(value.field("body"), Some(&XdrValue::Opaque(b"hi".to_vec())));
+    assert_eq!(
+        to_json(&value)["data"],
+        json!({ "disc": "SHORT", "arm": -7 })
+    );
+    assert_eq!(to_json(&value)["gain"], json!("-inf"));
+}
+
+#[test]
+fn json_errors() {
+    let error = |json| from_json(&schema(), &json).unwrap_err().to_string();
+    let mut json = json!({
+        "offset": "0",
+        "gain": 0,
+        "tag": "000000",
+        "body": "",
+        "data": { "disc": "SHORT", "arm": 0 },
+        "extra": null,
+        "flags": [true, true],
+        "exact": "00000000000000000000000000000000",
+    });
+    assert!(from_json(&schema(), &json).is_ok());
+
+    json["offset"] = json!("9223372036854775808");
+    assert_eq!(error(json.clone()), "\"9223372036854775808\" isn't a hyper");
+    json["offset"] = json!(0);
+
+    json["data"]["disc"] = json!("MEDIUM");
+    assert_eq!(
+        error(json.clone()),
+        "\"MEDIUM\" isn't one of the enum's variants"
+    );
+    json["data"]["disc"] = json!("SHORT");
+
+    json["body"] = json!("abc");
+    assert_eq!(error(json.clone()), "\"abc\" isn't valid hex or base64");
+    // Base64 that stops partway through a byte, or has bits left over after the last one
+    for bad in ["A", "AQIDB", "AB==", "AQN="] {
+        json["body"] = json!({ "base64": bad });
+        assert!(
+            error(json.clone()).ends_with("isn't valid hex or base64"),
+            "{}",
+            bad
+        );
+    }
+    for (good, bytes) in [("AQ==", &[1][..]), ("AQI=", &[1, 2]), ("AQID", &[1, 2, 3])] {
+        json["body"] = json!({ "base64": good });
+        let value = from_json(&schema(), &json).unwrap();
+        assert_eq!(value.field("body"), Some(&XdrValue::Opaque(bytes.to_vec())));
+    }
+    json["body"] = json!("");
+
+    // Hand-built schemas can switch on something that isn't a discriminant
+    let hyper_switch = XdrSchema::new(XdrType::Union {
+        disc: Box::new(XdrType::Hyper),
+        arms: vec![(0, XdrType::Void)],
+        default: None,
+    });
+    let err = from_json(&hyper_switch, &json!({ "disc": "0" })).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "a union discriminant has to be an int, unsigned int, bool or enum"
+    );
+
+    json["flags"] = json!("yes");
+    assert_eq!(error(json.clone()), "expected an array, found a string");
+    json["flags"] = json!([true, true]);
+
+    json["extra_field"] = json!(1);
+    assert_eq!(error(json.clone()), "unknown field `extra_field`");
+    json.as_object_mut().unwrap().remove("extra_field");
+    json.as_object_mut().unwrap().remove("gain");
+    assert_eq!(error(json), "missing field `gain`");
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{round_trip, words};
+use serde::{Deserialize, Serialize};
+use serde_xdr::{from_bytes, Void};
+
+use std::marker::PhantomData;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Marker;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Tagged {
+    before: u32,
+    nothing: (),
+    marker: Marker,
+    void: Void,
+    phantom: PhantomData<String>,
+    after: u32,
+}
+
+#[test]
+fn void_values_are_zero_bytes() {
+    assert_eq!(round_trip(&()), Vec::<u8>::new());
+    assert_eq!(round_trip(&Marker), Vec::<u8>::new());
+    assert_eq!(round_trip(&Void), Vec::<u8>::new());
+    assert_eq!(round_trip(&PhantomData::<u64>), Vec::<u8>::new());
+}
+
+#[test]
+fn void_fields_leave_their_neighbours_alone() {
+    let tagged = Tagged {
+        before: 1,
+        nothing: (),
+        marker: Marker,
+        void: Void,
+        phantom: PhantomData,
+        after: 2,
+    };
+    assert_eq!(round_trip(&tagged), words(&[1, 2]));
+}
+
+#[test]
+fn decoding_void_reads_nothing() {
+    let bytes = words(&[7]);
+    assert_eq!(from_bytes::<Void>(&bytes).unwrap(), (Void, 0));
+    assert_eq!(from_bytes::<()>(&bytes).unwrap(), ((), 0));
+    assert_eq!(from_bytes::<(Void, u32)>(&bytes).unwrap(), ((Void, 7), 4));
+    assert_eq!(from_bytes::<Void>(&[]).unwrap(), (Void, 0));
+}
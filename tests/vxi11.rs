@@ -0,0 +1,252 @@
+mod common;
+
+use common::round_trip;
+use serde_xdr::rpc::RpcServer;
+use serde_xdr::vxi11::*;
+use serde_xdr::Opaque;
+
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn create_link() {
+    let parms = CreateLinkParms {
+        client_id: 0x1234,
+        lock_device: false,
+        lock_timeout: 10000,
+        device: String::from("inst0"),
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&parms), [
+        0x00, 0x00, 0x12, 0x34,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x27, 0x10,
+        0x00, 0x00, 0x00, 0x05, b'i', b'n', b's', b't', b'0', 0x00, 0x00, 0x00,
+    ]);
+
+    let resp = CreateLinkResp {
+        error: DeviceErrorCode::NO_ERROR,
+        lid: 1,
+        abort_port: 1025,
+        max_recv_size: 0x0010_0000,
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&resp), [
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x04, 0x01,
+        0x00, 0x10, 0x00, 0x00,
+    ]);
+}
+
+#[test]
+fn write_and_read() {
+    let parms = DeviceWriteParms {
+        lid: 1,
+        io_timeout: 1000,
+        lock_timeout: 0,
+        flags: DeviceFlags::END,
+        data: Opaque(b"*IDN?\n".to_vec()),
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&parms), [
+        0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x03, 0xe8,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x08,
+        0x00, 0x00, 0x00, 0x06, b'*', b'I', b'D', b'N', b'?', b'\n', 0x00, 0x00,
+    ]);
+    assert_eq!(
+        round_trip(&DeviceWriteResp {
+            error: DeviceErrorCode::NO_ERROR,
+            size: 6,
+        }),
+        [0, 0, 0, 0, 0, 0, 0, 6]
+    );
+
+    let parms = DeviceReadParms {
+        lid: 1,
+        request_size: 1024,
+        io_timeout: 1000,
+        lock_timeout: 0,
+        flags: DeviceFlags::TERMCHRSET | DeviceFlags::WAITLOCK,
+        term_char: u32::from(b'\n'),
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&parms), [
+        0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x04, 0x00,
+        0x00, 0x00, 0x03, 0xe8,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x81,
+        0x00, 0x00, 0x00, 0x0a,
+    ]);
+
+    let resp = DeviceReadResp {
+        error: DeviceErrorCode::NO_ERROR,
+        reason: ReadReason::END,
+        data: Opaque(b"ACME,1\n".to_vec()),
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&resp), [
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x04,
+        0x00, 0x00, 0x00, 0x07, b'A', b'C', b'M', b'E', b',', b'1', b'\n', 0x00,
+    ]);
+    assert_eq!(
+        round_trip(&DeviceReadStbResp {
+            error: DeviceErrorCode::NO_ERROR,
+            stb: 0x40,
+        }),
+        [0, 0, 0, 0, 0, 0, 0, 0x40]
+    );
+}
+
+#[test]
+fn generic_lock_and_docmd() {
+    let parms = DeviceGenericParms {
+        lid: 2,
+        flags: DeviceFlags::WAITLOCK,
+        lock_timeout: 5000,
+        io_timeout: 1000,
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&parms), [
+        0x00, 0x00, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x13, 0x88,
+        0x00, 0x00, 0x03, 0xe8,
+    ]);
+
+    let parms = DeviceLockParms {
+        lid: 2,
+        flags: DeviceFlags::NONE,
+        lock_timeout: 5000,
+    };
+    assert_eq!(
+        round_trip(&parms),
+        [0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0x13, 0x88]
+    );
+
+    let parms = DeviceDocmdParms {
+        lid: 2,
+        flags: DeviceFlags::NONE,
+        io_timeout: 1000,
+        lock_timeout: 0,
+        cmd: 0x20000,
+        network_order: true,
+        datasize: 2,
+        data_in: Opaque(vec![0x00, 0x01]),
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&parms), [
+        0x00, 0x00, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x03, 0xe8,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x02, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00,
+    ]);
+    assert_eq!(
+        round_trip(&DeviceDocmdResp {
+            error: DeviceErrorCode::OPERATION_NOT_SUPPORTED,
+            data_out: Opaque(Vec::new()),
+        }),
+        [0, 0, 0, 8, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn errors_and_remote_func() {
+    assert_eq!(
+        round_trip(&DeviceError {
+            error: DeviceErrorCode::IO_TIMEOUT,
+        }),
+        [0, 0, 0, 15]
+    );
+    assert_eq!(
+        DeviceErrorCode::DEVICE_LOCKED_BY_ANOTHER_LINK.to_string(),
+        "device locked by another link (11)"
+    );
+    assert_eq!(DeviceErrorCode(42).description(), None);
+
+    let func = DeviceRemoteFunc {
+        host_addr: 0x7f00_0001,
+        host_port: 40000,
+        prog_num: 0x0607B1,
+        prog_vers: 1,
+        prog_family: DeviceAddrFamily::Tcp,
+    };
+    #[rustfmt::skip]
+    assert_eq!(round_trip(&func), [
+        0x7f, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x9c, 0x40,
+        0x00, 0x06, 0x07, 0xb1,
+        0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00,
+    ]);
+
+    let srq = DeviceEnableSrqParms {
+        lid: 1,
+        enable: true,
+        handle: Opaque(b"h1".to_vec()),
+    };
+    assert_eq!(
+        round_trip(&srq),
+        [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 2, b'h', b'1', 0, 0]
+    );
+}
+
+#[test]
+fn abort_channel() {
+    let aborted = Arc::new(Mutex::new(Vec::new()));
+    let seen = aborted.clone();
+    let mut server = RpcServer::new();
+    server.register(AbortServer::new(move |lid| {
+        seen.lock().unwrap().push(lid);
+        if lid == 1 {
+            DeviceErrorCode::NO_ERROR
+        } else {
+            DeviceErrorCode::INVALID_LINK_IDENTIFIER
+        }
+    }));
+    let handle = server.spawn_tcp("127.0.0.1:0").unwrap();
+
+    let port = u32::from(handle.local_addr().port());
+    let mut client = AbortClient::connect_port(Ipv4Addr::LOCALHOST.into(), port).unwrap();
+    assert!(client.abort(1).unwrap().error.is_ok());
+    assert_eq!(
+        client.abort(7).unwrap().error,
+        DeviceErrorCode::INVALID_LINK_IDENTIFIER
+    );
+    assert_eq!(*aborted.lock().unwrap(), vec![1, 7]);
+}
+
+#[test]
+fn interrupt_channel() {
+    assert_eq!(
+        round_trip(&DeviceSrqParms {
+            handle: Opaque(b"srq".to_vec()),
+        }),
+        [0, 0, 0, 3, b's', b'r', b'q', 0]
+    );
+
+    let (channel, srqs) = IntrChannel::spawn("127.0.0.1:0").unwrap();
+    let func = channel.remote_func(Ipv4Addr::LOCALHOST);
+    assert_eq!(func.host_port, u32::from(channel.local_addr().port()));
+    assert_eq!(func.prog_num, DEVICE_INTR);
+
+    let mut client = IntrClient::connect(&func).unwrap();
+    client.srq(&Opaque(b"dmm".to_vec())).unwrap();
+    client.srq(&Opaque(b"scope".to_vec())).unwrap();
+    let timeout = Duration::from_secs(5);
+    assert_eq!(srqs.recv_timeout(timeout).unwrap(), Opaque(b"dmm".to_vec()));
+    assert_eq!(
+        srqs.recv_timeout(timeout).unwrap(),
+        Opaque(b"scope".to_vec())
+    );
+    channel.shutdown();
+}
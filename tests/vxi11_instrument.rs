@@ -0,0 +1,219 @@
+use serde_xdr::portmap::{Mapping, Portmapper, IPPROTO_TCP};
+use serde_xdr::rpc::{Call, ProcError, RpcProgram, RpcServer};
+use serde_xdr::vxi11::*;
+use serde_xdr::{Opaque, Vxi11Error};
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Default)]
+struct State {
+    next_lid: DeviceLink,
+    // Chunks received by device_write along with their END flag
+    writes: Vec<(Vec<u8>, bool)>,
+    command: Vec<u8>,
+    output: Vec<u8>,
+    lock: Option<DeviceLink>,
+    destroyed: Vec<DeviceLink>,
+    // Accept no written bytes and answer reads with REQCNT and no data
+    stalled: bool,
+}
+
+// Accepts at most 4 bytes per write and hands out responses 3 bytes per read
+#[derive(Clone, Default)]
+struct Fake(Arc<Mutex<State>>);
+
+impl RpcProgram for Fake {
+    fn program(&self) -> u32 {
+        DEVICE_CORE
+    }
+
+    fn versions(&self) -> (u32, u32) {
+        (1, 1)
+    }
+
+    fn dispatch(&self, call: &mut Call) -> Result<(), ProcError> {
+        let mut state = self.0.lock().unwrap();
+        let ok = DeviceError::default();
+        match call.proc() {
+            CREATE_LINK => {
+                let parms: CreateLinkParms = call.args()?;
+                if parms.device != "inst0" {
+                    return call.reply(&CreateLinkResp {
+                        error: DeviceErrorCode::DEVICE_NOT_ACCESSIBLE,
+                        ..CreateLinkResp::default()
+                    });
+                }
+                state.next_lid += 1;
+                call.reply(&CreateLinkResp {
+                    error: DeviceErrorCode::NO_ERROR,
+                    lid: state.next_lid,
+                    abort_port: 0,
+                    max_recv_size: 4,
+                })
+            }
+            DEVICE_WRITE => {
+                let parms: DeviceWriteParms = call.args()?;
+                let data = parms.data.into_inner();
+                let end = parms.flags.contains(DeviceFlags::END);
+                if state.stalled {
+                    return call.reply(&DeviceWriteResp::default());
+                }
+                state.command.extend_from_slice(&data);
+                state.writes.push((data.clone(), end));
+                if end && state.command == b"*IDN?" {
+                    state.output = b"ACME,Fake,0,1.0\n".to_vec();
+                }
+                if end {
+                    state.command.clear();
+                }
+                call.reply(&DeviceWriteResp {
+                    error: DeviceErrorCode::NO_ERROR,
+                    size: data.len() as u32,
+                })
+            }
+            DEVICE_READ => {
+                let _: DeviceReadParms = call.args()?;
+                if state.stalled {
+                    return call.reply(&DeviceReadResp {
+                        reason: ReadReason::REQCNT,
+                        ..DeviceReadResp::default()
+                    });
+                }
+                if state.output.is_empty() {
+                    return call.reply(&DeviceReadResp {
+                        error: DeviceErrorCode::IO_TIMEOUT,
+                        ..DeviceReadResp::default()
+                    });
+                }
+                let len = state.output.len().min(3);
+                let data: Vec<u8> = state.output.drain(..len).collect();
+                let reason = if state.output.is_empty() {
+                    ReadReason::END
+                } else {
+                    ReadReason::NONE
+                };
+                call.reply(&DeviceReadResp {
+                    error: DeviceErrorCode::NO_ERROR,
+                    reason,
+                    data: Opaque(data),
+                })
+            }
+            DEVICE_READSTB => {
+                let _: DeviceGenericParms = call.args()?;
+                call.reply(&DeviceReadStbResp {
+                    error: DeviceErrorCode::NO_ERROR,
+                    stb: 0x40,
+                })
+            }
+            DEVICE_TRIGGER | DEVICE_CLEAR => {
+                let _: DeviceGenericParms = call.args()?;
+                call.reply(&ok)
+            }
+            DEVICE_LOCK => {
+                let parms: DeviceLockParms = call.args()?;
+                let error = match state.lock {
+                    Some(_) => DeviceErrorCode::DEVICE_LOCKED_BY_ANOTHER_LINK,
+                    None => {
+                        state.lock = Some(parms.lid);
+                        DeviceErrorCode::NO_ERROR
+                    }
+                };
+                call.reply(&DeviceError { error })
+            }
+            DEVICE_UNLOCK => {
+                let lid: DeviceLink = call.args()?;
+                let error = if state.lock == Some(lid) {
+                    state.lock = None;
+                    DeviceErrorCode::NO_ERROR
+                } else {
+                    DeviceErrorCode::NO_LOCK_HELD_BY_THIS_LINK
+                };
+                call.reply(&DeviceError { error })
+            }
+            DESTROY_LINK => {
+                let lid: DeviceLink = call.args()?;
+                state.destroyed.push(lid);
+                call.reply(&ok)
+            }
+            _ => Err(ProcError::ProcUnavail),
+        }
+    }
+}
+
+#[test]
+fn instrument_session() {
+    let fake = Fake::default();
+    let mut server = RpcServer::new();
+    server.register(fake.clone());
+    let core = server.spawn_tcp("127.0.0.1:0").unwrap();
+    let portmapper = Portmapper::new();
+    portmapper.set(Mapping {
+        prog: DEVICE_CORE,
+        vers: 1,
+        prot: IPPROTO_TCP,
+        port: u32::from(core.local_addr().port()),
+    });
+    let portmapper = portmapper.spawn("127.0.0.1:0").unwrap();
+
+    let err = Vxi11Instrument::connect_via(portmapper.local_addr(), "inst1").err();
+    assert!(matches!(
+        err,
+        Some(Vxi11Error::Device(DeviceErrorCode::DEVICE_NOT_ACCESSIBLE))
+    ));
+
+    let mut inst = Vxi11Instrument::connect_via(portmapper.local_addr(), "inst0")
+        .unwrap()
+        .io_timeout(Duration::from_secs(1));
+    assert_eq!(inst.max_recv_size(), 4);
+    assert_eq!(inst.identify().unwrap(), "ACME,Fake,0,1.0");
+    assert_eq!(
+        fake.0.lock().unwrap().writes,
+        vec![(b"*IDN".to_vec(), false), (b"?".to_vec(), true)]
+    );
+
+    assert_eq!(inst.write(b"CONF:VOLT").unwrap(), 9);
+    // Goes out as a lone END, which the device accepts none of
+    assert_eq!(inst.write(b"").unwrap(), 0);
+    assert_eq!(
+        fake.0.lock().unwrap().writes.last(),
+        Some(&(Vec::new(), true))
+    );
+    let err = inst.read().unwrap_err();
+    assert!(matches!(
+        err,
+        Vxi11Error::Device(DeviceErrorCode::IO_TIMEOUT)
+    ));
+
+    assert_eq!(inst.read_stb().unwrap(), 0x40);
+    inst.trigger().unwrap();
+    inst.clear().unwrap();
+
+    let mut other = Vxi11Instrument::connect_core(core.local_addr(), "inst0").unwrap();
+    inst.lock(Duration::from_millis(100)).unwrap();
+    let err = other.lock(Duration::from_millis(100)).unwrap_err();
+    assert!(matches!(
+        err,
+        Vxi11Error::Device(DeviceErrorCode::DEVICE_LOCKED_BY_ANOTHER_LINK)
+    ));
+    assert!(other.unlock().is_err());
+    inst.unlock().unwrap();
+
+    let (first, second) = (inst.link_id(), other.link_id());
+    other.close().unwrap();
+    drop(inst);
+    assert_eq!(fake.0.lock().unwrap().destroyed, vec![second, first]);
+}
+
+#[test]
+fn stalled_device_is_an_error() {
+    let fake = Fake::default();
+    fake.0.lock().unwrap().stalled = true;
+    let mut server = RpcServer::new();
+    server.register(fake.clone());
+    let core = server.spawn_tcp("127.0.0.1:0").unwrap();
+
+    let mut inst = Vxi11Instrument::connect_core(core.local_addr(), "inst0").unwrap();
+    assert!(matches!(inst.write(b"*RST"), Err(Vxi11Error::Stalled)));
+    assert!(matches!(inst.read(), Err(Vxi11Error::Stalled)));
+}
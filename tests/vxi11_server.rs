@@ -0,0 +1,216 @@
+use serde_xdr::rpc::RpcClient;
+use serde_xdr::vxi11::*;
+use serde_xdr::{Opaque, Vxi11Error};
+
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Echoes every message back, and reports a status byte of the number of messages seen
+#[derive(Default)]
+struct Echo {
+    pending: Vec<Vec<u8>>,
+    seen: u8,
+}
+
+impl SimulatedDevice for Echo {
+    fn write(&mut self, message: &[u8]) -> Result<(), DeviceErrorCode> {
+        self.seen += 1;
+        self.pending.push(message.to_vec());
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Option<Vec<u8>>, DeviceErrorCode> {
+        Ok(if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.remove(0))
+        })
+    }
+
+    fn status_byte(&mut self) -> u8 {
+        self.seen
+    }
+}
+
+fn simulator() -> SimulatorHandle {
+    SimulatedServer::new()
+        .device(
+            "inst0",
+            ScriptedDevice::new().respond("*IDN?", "ACME,Simulated,0,1.0"),
+        )
+        .device("echo", Echo::default())
+        .max_recv_size(8)
+        .spawn("127.0.0.1:0")
+        .unwrap()
+}
+
+fn is_device_error(result: Result<(), Vxi11Error>, code: DeviceErrorCode) -> bool {
+    matches!(result, Err(Vxi11Error::Device(c)) if c == code)
+}
+
+#[test]
+fn scripted_and_chunked_io() {
+    let sim = simulator();
+    let mut inst = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+    assert_eq!(inst.identify().unwrap(), "ACME,Simulated,0,1.0");
+    inst.write_str("*RST").unwrap();
+    assert!(is_device_error(
+        inst.read().map(|_| ()),
+        DeviceErrorCode::IO_TIMEOUT
+    ));
+
+    // Longer than max_recv_size on the way in, and read back a few bytes at a time
+    let mut echo = Vxi11Instrument::connect_via(sim.portmapper_addr(), "echo")
+        .unwrap()
+        .read_size(5);
+    let message = b"MEAS:VOLT:DC? 10,0.001\n";
+    assert_eq!(echo.write(message).unwrap(), message.len());
+    assert_eq!(echo.read().unwrap(), &message[..]);
+    assert_eq!(echo.read_stb().unwrap(), 1);
+
+    // Stops at the termination character, leaving the rest for the next read
+    let mut echo = echo.term_char(Some(b','));
+    echo.write(b"1,2,3").unwrap();
+    assert_eq!(echo.read().unwrap(), b"1,");
+    assert_eq!(echo.read().unwrap(), b"2,");
+    assert_eq!(echo.read().unwrap(), b"3");
+
+    assert!(matches!(
+        Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst9").err(),
+        Some(Vxi11Error::Device(DeviceErrorCode::DEVICE_NOT_ACCESSIBLE))
+    ));
+}
+
+#[test]
+fn injected_errors_and_abort() {
+    let sim = simulator();
+    let mut inst = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+    sim.inject_error(DEVICE_TRIGGER, DeviceErrorCode::IO_ERROR);
+    assert!(is_device_error(inst.trigger(), DeviceErrorCode::IO_ERROR));
+    inst.trigger().unwrap();
+
+    sim.set_latency(Duration::from_secs(10));
+    let lid = inst.link_id();
+    let abort_addr = sim.abort_addr();
+    let aborter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        AbortClient::connect(abort_addr)
+            .unwrap()
+            .abort(lid)
+            .unwrap()
+    });
+    let start = Instant::now();
+    assert!(is_device_error(inst.clear(), DeviceErrorCode::ABORT));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(aborter.join().unwrap().error.is_ok());
+
+    sim.set_latency(Duration::from_secs(0));
+    inst.clear().unwrap();
+    inst.abort().unwrap();
+}
+
+#[test]
+fn calls_racing_destroy_link() {
+    let sim = simulator();
+    let inst = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+    let lid = inst.link_id();
+    let core = sim.core_addr();
+    let call = move |proc: u32, delay: u64| {
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(delay));
+            let mut client = RpcClient::connect_tcp(core).unwrap();
+            let resp: DeviceError = match proc {
+                DEVICE_ENABLE_SRQ => {
+                    let parms = DeviceEnableSrqParms {
+                        lid,
+                        enable: true,
+                        handle: Opaque(b"h".to_vec()),
+                    };
+                    client.call(DEVICE_CORE, DEVICE_CORE_VERSION, proc, &parms)
+                }
+                _ => client.call(DEVICE_CORE, DEVICE_CORE_VERSION, proc, &lid),
+            }
+            .unwrap();
+            resp.error
+        })
+    };
+
+    // Both calls find the link when they start, then it's gone by the time their latency is up
+    sim.set_latency(Duration::from_millis(300));
+    let destroy = call(DESTROY_LINK, 0);
+    let unlock = call(DEVICE_UNLOCK, 100);
+    let enable_srq = call(DEVICE_ENABLE_SRQ, 100);
+    assert!(destroy.join().unwrap().is_ok());
+    for racing in [unlock, enable_srq] {
+        assert_eq!(
+            racing.join().unwrap(),
+            DeviceErrorCode::INVALID_LINK_IDENTIFIER
+        );
+    }
+    assert_eq!(
+        call(DESTROY_LINK, 0).join().unwrap(),
+        DeviceErrorCode::INVALID_LINK_IDENTIFIER
+    );
+
+    // And the simulator carries on
+    sim.set_latency(Duration::from_secs(0));
+    drop(inst);
+    let mut inst = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+    assert_eq!(inst.identify().unwrap(), "ACME,Simulated,0,1.0");
+}
+
+#[test]
+fn locking_between_links() {
+    let sim = simulator();
+    let mut first = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+    let mut second = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+
+    first.lock(Duration::from_secs(1)).unwrap();
+    assert!(is_device_error(
+        second.write_str("*CLS").map(|_| ()),
+        DeviceErrorCode::DEVICE_LOCKED_BY_ANOTHER_LINK
+    ));
+    assert!(is_device_error(
+        second.unlock(),
+        DeviceErrorCode::NO_LOCK_HELD_BY_THIS_LINK
+    ));
+
+    // Waits for the lock when given a lock timeout
+    let releaser = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        first.unlock().unwrap();
+        first
+    });
+    let mut second = second.lock_timeout(Duration::from_secs(5));
+    second.write_str("*CLS").unwrap();
+    second.lock(Duration::from_secs(1)).unwrap();
+    drop(second);
+
+    // Dropping the link released its lock
+    let mut first = releaser.join().unwrap();
+    first.lock(Duration::from_secs(0)).unwrap();
+}
+
+#[test]
+fn service_requests() {
+    let sim = simulator();
+    let mut inst = Vxi11Instrument::connect_via(sim.portmapper_addr(), "inst0").unwrap();
+    let (channel, srqs) = IntrChannel::spawn("127.0.0.1:0").unwrap();
+
+    assert_eq!(sim.request_service("inst0").unwrap(), 0);
+    inst.create_intr_chan(&channel.remote_func(Ipv4Addr::LOCALHOST))
+        .unwrap();
+    inst.enable_srq(true, b"dmm").unwrap();
+    assert_eq!(sim.request_service("inst0").unwrap(), 1);
+    let srq = srqs.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(srq, Opaque(b"dmm".to_vec()));
+
+    inst.enable_srq(false, b"").unwrap();
+    assert_eq!(sim.request_service("inst0").unwrap(), 0);
+    inst.destroy_intr_chan().unwrap();
+    assert!(is_device_error(
+        inst.destroy_intr_chan(),
+        DeviceErrorCode::CHANNEL_NOT_ESTABLISHED
+    ));
+}